@@ -1,7 +1,15 @@
-use std::path::PathBuf;
-
 fn main() {
+    #[cfg(feature = "membrane")]
+    compile_schema();
+}
+
+/// Generate `stem_capnp.rs` (included by `stem::stem_capnp`) from the Membrane schema.
+#[cfg(feature = "membrane")]
+fn compile_schema() {
+    use std::path::PathBuf;
+
     let schema = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../capnp/stem.capnp");
+    println!("cargo:rerun-if-changed={}", schema.display());
     capnpc::CompilerCommand::new()
         .src_prefix("../../")
         .file(schema)
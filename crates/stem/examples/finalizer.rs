@@ -10,10 +10,16 @@
 //! Options:
 //!   --depth <K>   Confirmation depth (number of blocks after event before considering finalized). Default: 6.
 //!   --cursor <path>  Path to file containing start block (one line, decimal). If missing or invalid, start from 0.
+//!                    After each printed event it is rewritten (atomically) to the block after
+//!                    the event's, so a restart resumes past events already printed.
 
-use stem::{FinalizerBuilder, IndexerConfig, StemIndexer};
-use std::io::BufRead;
+use futures_util::future::BoxFuture;
+use stem::{
+    read_cursor_file, write_cursor_file, FinalizedEvent, FinalizedSink, FinalizerBuilder, IndexerConfig,
+    SinkError, StdoutJsonSink, StemIndexer,
+};
 use std::sync::Arc;
+use std::time::Duration;
 
 fn parse_contract_address(s: &str) -> Result<[u8; 20], String> {
     let addr_hex = s.strip_prefix("0x").unwrap_or(s);
@@ -27,16 +33,30 @@ fn parse_contract_address(s: &str) -> Result<[u8; 20], String> {
 }
 
 fn read_start_block_from_file(path: &str) -> u64 {
-    let file = match std::fs::File::open(path) {
-        Ok(f) => f,
-        Err(_) => return 0,
-    };
-    let mut line = String::new();
-    let mut reader = std::io::BufReader::new(file);
-    if reader.read_line(&mut line).is_err() || line.is_empty() {
-        return 0;
+    match read_cursor_file(path) {
+        Ok(block) => block.unwrap_or(0),
+        Err(e) => {
+            eprintln!("ignoring cursor file {}: {}", path, e);
+            0
+        }
+    }
+}
+
+/// Prints each event to stdout, then records the next block to start from in the cursor file.
+struct CursorSink {
+    path: Option<String>,
+}
+
+impl FinalizedSink for CursorSink {
+    fn publish<'a>(&'a self, ev: &'a FinalizedEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            StdoutJsonSink.publish(ev).await?;
+            if let Some(path) = &self.path {
+                write_cursor_file(path, ev.block_number + 1)?;
+            }
+            Ok(())
+        })
     }
-    line.trim().parse().unwrap_or(0)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -77,7 +97,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     "Usage: finalizer --ws-url <WS_URL> --http-url <HTTP_URL> --contract <STEM_ADDRESS> [--depth K] [--cursor <path>]\n\
                      Prints one-line JSON per finalized HeadUpdated event (confirmation-depth strategy).\n\
                      --depth K  Confirmation depth (blocks after event before finalized). Default: 6.\n\
-                     --cursor   Path to file with start block (one line, decimal). Optional.\n\
+                     --cursor   Path to file with start block (one line, decimal), updated as events are printed. Optional.\n\
                      Works with local chains (e.g. Anvil)."
                 );
                 std::process::exit(0);
@@ -103,16 +123,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         read_start_block_from_file(&cursor_path)
     };
 
-    let config = IndexerConfig {
-        ws_url: ws_url.clone(),
-        http_url: http_url.clone(),
-        contract_address,
-        start_block,
-        getlogs_max_range: 1000,
-        reconnection: Default::default(),
-    };
+    let config = IndexerConfig::builder()
+        .ws_url(&ws_url)
+        .http_url(&http_url)
+        .contract_address(contract_address)
+        .start_block(start_block)
+        .build()?;
     let indexer = Arc::new(StemIndexer::new(config));
-    let mut recv = indexer.subscribe();
+    let recv = indexer.subscribe();
     let indexer_clone = Arc::clone(&indexer);
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -127,34 +145,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .confirmation_depth(depth)
         .build()?;
 
+    let sink = CursorSink {
+        path: (!cursor_path.is_empty()).then_some(cursor_path),
+    };
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
-        loop {
-            tokio::select! {
-                Ok(ev) = recv.recv() => {
-                    finalizer.feed(ev);
-                    let tip = match finalizer.current_tip().await {
-                        Ok(t) => t,
-                        Err(e) => {
-                            tracing::warn!(%e, "current_tip failed");
-                            continue;
-                        }
-                    };
-                    let events = match finalizer.drain_eligible(tip).await {
-                        Ok(e) => e,
-                        Err(e) => {
-                            tracing::warn!(%e, "drain_eligible failed");
-                            continue;
-                        }
-                    };
-                    for e in events {
-                        let json = serde_json::to_string(&e).unwrap();
-                        println!("{}", json);
-                    }
-                }
-                _ = tokio::signal::ctrl_c() => break,
-            }
+        tokio::select! {
+            res = finalizer.run_to_sink(recv, &sink, Duration::from_secs(1)) => res,
+            _ = tokio::signal::ctrl_c() => Ok(()),
         }
-    });
+    })?;
     Ok(())
 }
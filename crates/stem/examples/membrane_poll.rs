@@ -14,8 +14,8 @@
 
 use capnp_rpc::new_client;
 use stem::stem_capnp;
-use stem::{current_block_number, FinalizerBuilder, IndexerConfig, StemIndexer, Epoch};
-use stem::{FinalizedEvent, membrane_client};
+use stem::{FinalizerBuilder, HeadMode, IndexerConfig, StemIndexer, Epoch};
+use stem::membrane_client;
 use std::sync::Arc;
 use tokio::sync::watch;
 
@@ -30,14 +30,6 @@ fn parse_contract_address(s: &str) -> Result<[u8; 20], String> {
     Ok(out)
 }
 
-fn finalized_to_epoch(e: &FinalizedEvent) -> Epoch {
-    Epoch {
-        seq: e.seq,
-        head: e.cid.clone(),
-        adopted_block: e.block_number,
-    }
-}
-
 /// Stub Signer for graft: returns empty signature (example only).
 struct StubSigner;
 
@@ -116,17 +108,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
-        let start_block = current_block_number(&http_url)
-            .await
-            .map_err(|e| capnp::Error::failed(format!("current_block_number: {}", e)))?;
-        let config = IndexerConfig {
-            ws_url: ws_url.clone(),
-            http_url: http_url.clone(),
-            contract_address,
-            start_block,
-            getlogs_max_range: 1000,
-            reconnection: Default::default(),
-        };
+        let config = IndexerConfig::builder()
+            .ws_url(&ws_url)
+            .http_url(&http_url)
+            .contract_address(contract_address)
+            .head_mode(HeadMode::LiveOnly)
+            .build()
+            .expect("indexer config");
         let indexer = Arc::new(StemIndexer::new(config));
         let mut recv = indexer.subscribe();
         let indexer_clone = Arc::clone(&indexer);
@@ -148,7 +136,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         while !demo_done {
             tokio::select! {
                 Ok(ev) = recv.recv() => {
-                    finalizer.feed(ev);
+                    if let Err(e) = finalizer.feed(ev) {
+                        tracing::warn!(%e, "feed failed");
+                        continue;
+                    }
                     let tip = match finalizer.current_tip().await {
                         Ok(t) => t,
                         Err(e) => {
@@ -165,7 +156,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     };
 
                     for e in events {
-                        let epoch = finalized_to_epoch(&e);
+                        let epoch = Epoch::from(&e);
                         let current_seq = epoch.seq;
 
                         if let Some(tx) = &epoch_tx {
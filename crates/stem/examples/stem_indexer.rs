@@ -1,7 +1,7 @@
 //! Example: connect to an RPC endpoint and log Stem head updates.
 //!
 //! Imports the stem lib, runs StemIndexer against a Stem contract, and prints each
-//! HeadUpdated event (seq, block, writer, cid length). WebSocket URL is derived from
+//! HeadUpdated event (seq, block, writer, cid). WebSocket URL is derived from
 //! the HTTP RPC URL (http -> ws, https -> wss).
 //!
 //! Usage:
@@ -51,27 +51,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         eprintln!("       (WebSocket URL is derived from the RPC URL)");
         std::process::exit(1);
     }
-    let http_url = rpc_url.clone();
-    let ws_url = rpc_url
-        .replace("http://", "ws://")
-        .replace("https://", "wss://");
-
     let addr_hex = contract.strip_prefix("0x").unwrap_or(&contract);
     let addr_bytes = hex::decode(addr_hex)?;
-    if addr_bytes.len() != 20 {
-        eprintln!("contract must be 20 bytes (40 hex chars)");
-        std::process::exit(1);
-    }
-    let mut contract_address = [0u8; 20];
-    contract_address.copy_from_slice(&addr_bytes);
-
-    let config = IndexerConfig {
-        ws_url,
-        http_url,
-        contract_address,
-        start_block: 0,
-        getlogs_max_range: 1000,
-        reconnection: Default::default(),
+    let config = match IndexerConfig::builder()
+        .http_url(rpc_url)
+        .derive_ws_from_http()
+        .contract_address(addr_bytes)
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     };
     let indexer = Arc::new(StemIndexer::new(config));
     let mut recv = indexer.subscribe();
@@ -87,14 +79,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         loop {
             tokio::select! {
                 Ok(ev) = recv.recv() => {
-                    println!(
-                        "HeadUpdated seq={} block={} log_index={} writer=0x{} cid_len={}",
-                        ev.seq,
-                        ev.block_number,
-                        ev.log_index,
-                        hex::encode(ev.writer),
-                        ev.cid.len()
-                    );
+                    println!("{}", ev);
                 }
                 _ = tokio::signal::ctrl_c() => break,
             }
@@ -5,7 +5,10 @@
 
 use alloy::sol_types::SolType;
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha3::Digest;
+use std::sync::Arc;
 
 /// First 4 bytes of keccak256("HeadUpdated(uint64,address,bytes,bytes32)").
 pub const HEAD_UPDATED_TOPIC0: [u8; 4] = [0x85, 0xf2, 0xcb, 0x2e];
@@ -13,29 +16,294 @@ pub const HEAD_UPDATED_TOPIC0: [u8; 4] = [0x85, 0xf2, 0xcb, 0x2e];
 /// Selector for head().
 pub const HEAD_SELECTOR: [u8; 4] = [0x8f, 0x7d, 0xcf, 0xa3];
 
+/// Selector of Solidity's `Error(string)`, which prefixes revert data from `require`/`revert`.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
 /// Observed HeadUpdated event with chain metadata (observed-only; no reorg safety).
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Byte fields serialize as hex strings (no `0x` prefix, same as [crate::FinalizedEvent]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HeadUpdatedObserved {
     pub seq: u64,
+    #[serde(with = "hex_serde::array")]
     pub writer: [u8; 20],
+    #[serde(with = "hex_serde::vec")]
     pub cid: Vec<u8>,
+    #[serde(with = "hex_serde::array")]
     pub cid_hash: [u8; 32],
     pub block_number: u64,
+    #[serde(with = "hex_serde::array")]
     pub tx_hash: [u8; 32],
     pub log_index: u64,
+    /// The log as received from the node, when the indexer runs with
+    /// [crate::IndexerConfig::keep_raw_logs]; `None` otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
+    /// [crate::IndexerConfig::source_label] of the indexer that emitted this event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_label: Option<String>,
+}
+
+impl HeadUpdatedObserved {
+    /// Whether `cid_hash` is `keccak256(cid)`, as a correct contract emits it.
+    pub fn cid_hash_matches(&self) -> bool {
+        cid_hash(&self.cid) == self.cid_hash
+    }
+}
+
+/// `keccak256(cid)`: the `cidHash` topic the contract emits for `cid`.
+pub fn cid_hash(cid: &[u8]) -> [u8; 32] {
+    sha3::Keccak256::digest(cid).into()
+}
+
+/// Builder for synthetic [HeadUpdatedObserved] values, for testing pipelines without an RPC node
+/// (`test-util` feature). Unset fields are zero; [Self::cid] also sets `cid_hash` to
+/// `keccak256(cid)` as the contract does.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone)]
+pub struct HeadUpdatedObservedBuilder(HeadUpdatedObserved);
+
+#[cfg(any(test, feature = "test-util"))]
+impl HeadUpdatedObserved {
+    pub fn builder() -> HeadUpdatedObservedBuilder {
+        HeadUpdatedObservedBuilder(HeadUpdatedObserved {
+            seq: 0,
+            writer: [0; 20],
+            cid: Vec::new(),
+            cid_hash: [0; 32],
+            block_number: 0,
+            tx_hash: [0; 32],
+            log_index: 0,
+            raw: None,
+            source_label: None,
+        })
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl HeadUpdatedObservedBuilder {
+    pub fn seq(mut self, seq: u64) -> Self {
+        self.0.seq = seq;
+        self
+    }
+
+    pub fn writer(mut self, writer: [u8; 20]) -> Self {
+        self.0.writer = writer;
+        self
+    }
+
+    pub fn cid(mut self, cid: impl Into<Vec<u8>>) -> Self {
+        self.0.cid = cid.into();
+        self.0.cid_hash = cid_hash(&self.0.cid);
+        self
+    }
+
+    /// Override the hash set by [Self::cid] (e.g. to simulate a mismatching log).
+    pub fn cid_hash(mut self, cid_hash: [u8; 32]) -> Self {
+        self.0.cid_hash = cid_hash;
+        self
+    }
+
+    pub fn block_number(mut self, block_number: u64) -> Self {
+        self.0.block_number = block_number;
+        self
+    }
+
+    pub fn tx_hash(mut self, tx_hash: [u8; 32]) -> Self {
+        self.0.tx_hash = tx_hash;
+        self
+    }
+
+    pub fn log_index(mut self, log_index: u64) -> Self {
+        self.0.log_index = log_index;
+        self
+    }
+
+    pub fn source_label(mut self, label: impl Into<String>) -> Self {
+        self.0.source_label = Some(label.into());
+        self
+    }
+
+    pub fn build(self) -> HeadUpdatedObserved {
+        self.0
+    }
 }
 
 /// Current head state (from head() or from events).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CurrentHead {
     pub seq: u64,
+    #[serde(with = "hex_serde::vec")]
     pub cid: Vec<u8>,
 }
 
+/// Cids up to this many bytes are shown in full by [CompactCid]; longer ones are elided.
+const CID_DISPLAY_MAX: usize = 32;
+
+/// Compact `Display` for cid bytes: full hex when short, `0x<first 8>..<last 4> (N bytes)` otherwise.
+pub struct CompactCid<'a>(pub &'a [u8]);
+
+impl std::fmt::Display for CompactCid<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cid = self.0;
+        if cid.len() <= CID_DISPLAY_MAX {
+            write!(f, "0x{}", hex::encode(cid))
+        } else {
+            write!(
+                f,
+                "0x{}..{} ({} bytes)",
+                hex::encode(&cid[..8]),
+                hex::encode(&cid[cid.len() - 4..]),
+                cid.len()
+            )
+        }
+    }
+}
+
+/// `raw` parsed as a CID, given either as binary CID bytes or as a UTF-8 string (`Qm...` base58
+/// v0 or multibase v1).
+fn parse_cid(raw: &[u8]) -> Option<cid::Cid> {
+    cid::Cid::try_from(raw).ok().or_else(|| {
+        let s = std::str::from_utf8(raw).ok()?;
+        cid::Cid::try_from(s.trim()).ok()
+    })
+}
+
+/// Canonical binary (CIDv1) form of `raw` when it is a CID.
+fn canonical_cid(raw: &[u8]) -> Option<Vec<u8>> {
+    parse_cid(raw)?.into_v1().ok().map(|c| c.to_bytes())
+}
+
+/// Readable form of cid bytes: the CID's canonical string (base58 for v0, base32 for v1) when
+/// `raw` parses as one, binary or string; plain hex otherwise.
+pub fn cid_string(raw: &[u8]) -> String {
+    parse_cid(raw).map_or_else(|| hex::encode(raw), |c| c.to_string())
+}
+
+/// Multicodec of the CID in `raw` (e.g. 0x71 dag-cbor, 0x55 raw), binary or string; `None`
+/// if `raw` is not a CID.
+pub fn cid_codec(raw: &[u8]) -> Option<u64> {
+    parse_cid(raw).map(|c| c.codec())
+}
+
+/// True if `a` and `b` denote the same CID, regardless of string vs binary encoding or CID
+/// version (v0 vs v1). Non-CID values fall back to byte equality.
+pub fn cid_equivalent(a: &[u8], b: &[u8]) -> bool {
+    if a == b {
+        return true;
+    }
+    match (canonical_cid(a), canonical_cid(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+impl std::fmt::Display for HeadUpdatedObserved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HeadUpdated(seq={}, block={}, log_index={}, writer=0x{}, cid={})",
+            self.seq,
+            self.block_number,
+            self.log_index,
+            hex::encode(self.writer),
+            CompactCid(&self.cid)
+        )
+    }
+}
+
+impl std::fmt::Display for CurrentHead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Head(seq={}, cid={})", self.seq, CompactCid(&self.cid))
+    }
+}
+
+/// Serde helpers for byte fields as hex strings. Deserialization accepts an optional `0x` prefix.
+pub(crate) mod hex_serde {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    fn decode<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(d)?;
+        hex::decode(s.strip_prefix("0x").unwrap_or(&s)).map_err(D::Error::custom)
+    }
+
+    /// `Vec<u8>` as hex.
+    pub mod vec {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&hex::encode(bytes))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+            decode(d)
+        }
+    }
+
+    /// Fixed-size `[u8; N]` as hex; deserialization rejects the wrong length.
+    pub mod array {
+        use super::*;
+
+        pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&hex::encode(bytes))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(d: D) -> Result<[u8; N], D::Error> {
+            let bytes = decode(d)?;
+            bytes
+                .try_into()
+                .map_err(|b: Vec<u8>| D::Error::custom(format!("expected {} bytes, got {}", N, b.len())))
+        }
+    }
+}
+
+/// Returned (inside `anyhow::Error`) by the log decoders for a pending log: one from a
+/// transaction not yet mined, whose `blockNumber`, `logIndex` or `transactionHash` is null.
+/// Not a decoding failure; the log is delivered again once mined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("pending log (not yet mined)")]
+pub struct PendingLog;
+
+/// Returned (inside `anyhow::Error`) by the HeadUpdated decoders for a log with the expected
+/// four topics but too little data to hold an ABI `bytes`: the emitting contract most likely
+/// declares `cid` as `indexed`, so the ABI doesn't match this crate's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("cid appears indexed; expected non-indexed bytes in data (check the contract's HeadUpdated ABI)")]
+pub struct CidIndexed;
+
+/// Fail with [CidIndexed] when a four-topic log's `data` can't hold the ABI `bytes` head
+/// (offset and length words).
+fn check_cid_not_indexed(topics: usize, data: &[u8]) -> Result<()> {
+    if topics == 4 && data.len() < 64 {
+        return Err(CidIndexed.into());
+    }
+    Ok(())
+}
+
+/// Default cap on a decoded HeadUpdated `cid`, in bytes. Real CIDs are well under 100 bytes; the
+/// cap stops a crafted log from forcing a large allocation.
+pub const DEFAULT_MAX_CID_LEN: usize = 4096;
+
 /// Decode a JSON-RPC log (eth_subscription / eth_getLogs result) into HeadUpdatedObserved.
+/// Pending logs fail with [PendingLog] (check with `err.is::<PendingLog>()`), and logs whose
+/// `cid` was emitted as an indexed topic with [CidIndexed].
 /// Option A: event HeadUpdated(uint64 indexed seq, address indexed writer, bytes cid, bytes32 indexed cidHash).
 /// Data is ABI-encoded single bytes: offset (32) then at offset: length then cid.
+/// A cid longer than [DEFAULT_MAX_CID_LEN] fails to decode.
 pub fn decode_log_to_observed(log_value: &Value) -> Result<HeadUpdatedObserved> {
+    decode_log_to_observed_with_max_cid_len(log_value, DEFAULT_MAX_CID_LEN)
+}
+
+/// [decode_log_to_observed] with a custom cap on the cid length.
+pub fn decode_log_to_observed_with_max_cid_len(
+    log_value: &Value,
+    max_cid_len: usize,
+) -> Result<HeadUpdatedObserved> {
+    if ["blockNumber", "logIndex", "transactionHash"]
+        .iter()
+        .any(|k| log_value.get(*k).is_some_and(Value::is_null))
+    {
+        return Err(PendingLog.into());
+    }
     let block_number = parse_hex_u64(
         log_value
             .get("blockNumber")
@@ -84,8 +352,9 @@ pub fn decode_log_to_observed(log_value: &Value) -> Result<HeadUpdatedObserved>
             .as_str()
             .ok_or_else(|| anyhow::anyhow!("topic3 not str"))?,
     )?;
+    check_cid_not_indexed(topics.len(), &data)?;
     // Event data: single ABI-encoded `bytes`. Try alloy first; fall back to manual when contract uses non-standard offset (e.g. 64).
-    let cid = decode_event_data_bytes(&data).context("decode event data bytes")?;
+    let cid = decode_event_data_bytes(&data, max_cid_len).context("decode event data bytes")?;
 
     Ok(HeadUpdatedObserved {
         seq,
@@ -95,12 +364,63 @@ pub fn decode_log_to_observed(log_value: &Value) -> Result<HeadUpdatedObserved>
         block_number,
         tx_hash,
         log_index,
+        raw: None,
+        source_label: None,
     })
 }
 
+/// Decode an alloy RPC log into HeadUpdatedObserved (same layout and cid cap as
+/// [decode_log_to_observed], read from the typed fields instead of JSON). Pending logs (no block number, log index or
+/// transaction hash) fail with [PendingLog].
+pub fn observed_from_alloy_log(log: &alloy::rpc::types::Log) -> Result<HeadUpdatedObserved> {
+    if log.block_number.is_none() || log.log_index.is_none() || log.transaction_hash.is_none() {
+        return Err(PendingLog.into());
+    }
+    let block_number = log.block_number.ok_or_else(|| anyhow::anyhow!("Missing blockNumber"))?;
+    let log_index = log.log_index.ok_or_else(|| anyhow::anyhow!("Missing logIndex"))?;
+    let tx_hash = log
+        .transaction_hash
+        .ok_or_else(|| anyhow::anyhow!("Missing transactionHash"))?;
+    let topics = log.topics();
+    if topics.len() < 4 {
+        anyhow::bail!("Expected at least 4 topics, got {}", topics.len());
+    }
+    let seq = u64::from_be_bytes(topics[1][24..].try_into().unwrap());
+    let writer: [u8; 20] = topics[2][12..].try_into().unwrap();
+    check_cid_not_indexed(topics.len(), &log.data().data)?;
+    let cid = decode_event_data_bytes(&log.data().data, DEFAULT_MAX_CID_LEN).context("decode event data bytes")?;
+
+    Ok(HeadUpdatedObserved {
+        seq,
+        writer,
+        cid,
+        cid_hash: topics[3].0,
+        block_number,
+        tx_hash: tx_hash.0,
+        log_index,
+        raw: None,
+        source_label: None,
+    })
+}
+
+/// Returned (inside `anyhow::Error`) by [decode_head_return] when the data is an
+/// `Error(string)` revert payload rather than a head (e.g. the contract isn't initialized).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("head() reverted: {reason}")]
+pub struct HeadReverted {
+    /// Decoded revert reason, or the payload as hex if it isn't a valid ABI string.
+    pub reason: String,
+}
+
 /// Decode head() return data (eth_call result): (uint64, bytes) ABI via alloy sol-types.
 /// Falls back to manual decode if the contract uses a non-standard offset (e.g. 64 instead of 32).
+/// An `Error(string)` revert payload fails with [HeadReverted].
 pub fn decode_head_return(data: &[u8]) -> Result<CurrentHead> {
+    if let Some(payload) = data.strip_prefix(&ERROR_SELECTOR[..]) {
+        let reason = alloy::sol_types::sol_data::String::abi_decode(payload, false)
+            .unwrap_or_else(|_| format!("0x{}", hex::encode(payload)));
+        return Err(HeadReverted { reason }.into());
+    }
     type HeadReturn = (alloy::sol_types::sol_data::Uint<64>, alloy::sol_types::sol_data::Bytes);
     if let Ok((seq, cid)) = HeadReturn::abi_decode(data, false) {
         return Ok(CurrentHead {
@@ -129,17 +449,60 @@ fn decode_head_return_manual(data: &[u8]) -> Result<CurrentHead> {
     Ok(CurrentHead { seq, cid })
 }
 
-/// Decode event data (single ABI `bytes`). Uses alloy when layout is standard; falls back to manual when offset != 32 (e.g. 64).
-fn decode_event_data_bytes(data: &[u8]) -> Result<Vec<u8>> {
+/// HeadUpdated data section split into the `cid` and any other non-indexed fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadUpdatedData {
+    pub cid: Vec<u8>,
+    /// Head words between the `cid` offset word and the `cid` payload, i.e. extra non-indexed
+    /// fields declared after `bytes cid` (e.g. a `uint256 timestamp` in variant contracts).
+    /// Empty for the standard event.
+    pub extra_words: Vec<[u8; 32]>,
+}
+
+/// Decode HeadUpdated data: the `bytes` at the first offset, plus the remaining head words.
+/// Trailing data after the `cid` payload is ignored, so extra fields don't break decoding.
+/// A cid longer than [DEFAULT_MAX_CID_LEN] fails to decode.
+pub fn decode_head_updated_data(data: &[u8]) -> Result<HeadUpdatedData> {
+    let cid = decode_event_data_bytes(data, DEFAULT_MAX_CID_LEN)?;
+    let cid_offset = u32::from_be_bytes(data[28..32].try_into().unwrap()) as usize;
+    let extra_words = data[32..cid_offset.clamp(32, data.len())]
+        .chunks_exact(32)
+        .map(|w| w.try_into().unwrap())
+        .collect();
+    Ok(HeadUpdatedData { cid, extra_words })
+}
+
+/// Decode event data (ABI `bytes` at the first offset). Uses alloy when layout is standard; falls
+/// back to manual when offset != 32 (e.g. 64, or extra fields after `cid`). Either way a cid
+/// longer than `max_len` is rejected, before it is copied.
+fn decode_event_data_bytes(data: &[u8], max_len: usize) -> Result<Vec<u8>> {
     use alloy::sol_types::sol_data::Bytes;
+    if let Some(len) = declared_cid_len(data) {
+        check_cid_len(len, max_len)?;
+    }
     if let Ok(b) = Bytes::abi_decode(data, false) {
+        check_cid_len(b.len(), max_len)?;
         return Ok(b.to_vec());
     }
-    decode_event_data_bytes_manual(data)
+    decode_event_data_bytes_manual(data, max_len)
+}
+
+/// Length word of the `bytes` at the first offset, if the data is long enough to hold it.
+fn declared_cid_len(data: &[u8]) -> Option<usize> {
+    let offset = usize::try_from(u32::from_be_bytes(data.get(28..32)?.try_into().ok()?)).ok()?;
+    let word = data.get(offset.checked_add(28)?..offset.checked_add(32)?)?;
+    usize::try_from(u32::from_be_bytes(word.try_into().ok()?)).ok()
+}
+
+fn check_cid_len(len: usize, max_len: usize) -> Result<()> {
+    if len > max_len {
+        anyhow::bail!("cid length {} exceeds max {}", len, max_len);
+    }
+    Ok(())
 }
 
 /// Manual decode of ABI-encoded single `bytes`: reads offset from first word (bytes 28..32), then length + payload.
-fn decode_event_data_bytes_manual(data: &[u8]) -> Result<Vec<u8>> {
+fn decode_event_data_bytes_manual(data: &[u8], max_len: usize) -> Result<Vec<u8>> {
     if data.len() < 32 {
         anyhow::bail!("event data too short");
     }
@@ -148,12 +511,60 @@ fn decode_event_data_bytes_manual(data: &[u8]) -> Result<Vec<u8>> {
         anyhow::bail!("event data too short for cid offset");
     }
     let len = u32::from_be_bytes(data[cid_offset + 28..cid_offset + 32].try_into().unwrap()) as usize;
+    check_cid_len(len, max_len)?;
     if data.len() < cid_offset + 32 + len {
         anyhow::bail!("event data too short for cid len {}", len);
     }
     Ok(data[cid_offset + 32..cid_offset + 32 + len].to_vec())
 }
 
+/// A log of another event type (e.g. a future `HeadReverted`), decoded by the [LogDecoder]
+/// registered for its topic0 with [crate::IndexerConfigBuilder::decoder].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedLog {
+    #[serde(with = "hex_serde::array")]
+    pub topic0: [u8; 4],
+    pub block_number: u64,
+    #[serde(with = "hex_serde::array")]
+    pub tx_hash: [u8; 32],
+    pub log_index: u64,
+    /// Whatever the decoder extracted from the log.
+    pub fields: Value,
+}
+
+/// Decoder for one extra event type: takes the JSON-RPC log, returns its fields.
+pub type LogDecoder = Arc<dyn Fn(&Value) -> Result<Value> + Send + Sync>;
+
+/// First 4 bytes of the log's topic0, if it has one.
+pub(crate) fn log_topic0(log: &Value) -> Option<[u8; 4]> {
+    let t0 = log.get("topics")?.as_array()?.first()?.as_str()?;
+    let bytes = hex::decode(t0.strip_prefix("0x").unwrap_or(t0)).ok()?;
+    bytes.get(..4)?.try_into().ok()
+}
+
+/// Decode `log` with `decoder`, taking its chain position from the log itself. Pending logs
+/// fail with [PendingLog], as for [decode_log_to_observed].
+pub fn decode_log_with(log: &Value, decoder: &LogDecoder) -> Result<DecodedLog> {
+    if ["blockNumber", "logIndex", "transactionHash"]
+        .iter()
+        .any(|k| log.get(*k).is_some_and(Value::is_null))
+    {
+        return Err(PendingLog.into());
+    }
+    let field = |k: &str| {
+        log.get(k)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing {}", k))
+    };
+    Ok(DecodedLog {
+        topic0: log_topic0(log).ok_or_else(|| anyhow::anyhow!("Missing topic0"))?,
+        block_number: parse_hex_u64(field("blockNumber")?)?,
+        tx_hash: parse_hex_bytes_32(field("transactionHash")?)?,
+        log_index: parse_hex_u64(field("logIndex")?)?,
+        fields: decoder(log)?,
+    })
+}
+
 fn parse_hex_u64(s: &str) -> Result<u64> {
     let s = s.strip_prefix("0x").unwrap_or(s);
     u64::from_str_radix(s, 16).context("parse hex u64")
@@ -219,12 +630,21 @@ mod tests {
         assert_eq!(head.cid.as_slice(), cid);
     }
 
+    #[test]
+    fn decode_head_return_surfaces_revert_reason() {
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend(alloy::sol_types::sol_data::String::abi_encode("Stem: not initialized"));
+        let err = decode_head_return(&data).unwrap_err();
+        let reverted = err.downcast_ref::<HeadReverted>().expect("HeadReverted");
+        assert_eq!(reverted.reason, "Stem: not initialized");
+    }
+
     #[test]
     fn decode_event_data_bytes_standard() {
         use alloy::sol_types::sol_data::Bytes;
         let cid: &[u8] = b"cid-1";
         let data = Bytes::abi_encode(&alloy::primitives::Bytes::from(cid));
-        let decoded = super::decode_event_data_bytes(&data).unwrap();
+        let decoded = super::decode_event_data_bytes(&data, DEFAULT_MAX_CID_LEN).unwrap();
         assert_eq!(decoded.as_slice(), cid);
     }
 
@@ -237,7 +657,245 @@ mod tests {
         data.resize(64 + 32 + cid.len(), 0);
         data[64 + 28..64 + 32].copy_from_slice(&(cid.len() as u32).to_be_bytes());
         data[64 + 32..64 + 32 + cid.len()].copy_from_slice(cid);
-        let decoded = super::decode_event_data_bytes(&data).unwrap();
+        let decoded = super::decode_event_data_bytes(&data, DEFAULT_MAX_CID_LEN).unwrap();
         assert_eq!(decoded.as_slice(), cid);
     }
+
+    /// `cid` ABI-encoded as a single `bytes` at `offset` (32 is the standard layout).
+    fn event_data_at_offset(cid: &[u8], offset: usize) -> Vec<u8> {
+        let mut data = vec![0u8; offset + 32 + cid.len().div_ceil(32) * 32];
+        data[28..32].copy_from_slice(&(offset as u32).to_be_bytes());
+        data[offset + 28..offset + 32].copy_from_slice(&(cid.len() as u32).to_be_bytes());
+        data[offset + 32..offset + 32 + cid.len()].copy_from_slice(cid);
+        data
+    }
+
+    #[test]
+    fn cid_length_is_capped_on_both_decode_paths() {
+        for offset in [32, 64] {
+            let at_limit = vec![0xab; DEFAULT_MAX_CID_LEN];
+            let data = event_data_at_offset(&at_limit, offset);
+            assert_eq!(super::decode_event_data_bytes(&data, DEFAULT_MAX_CID_LEN).unwrap(), at_limit);
+
+            let over = vec![0xab; DEFAULT_MAX_CID_LEN + 1];
+            let err = super::decode_event_data_bytes(&event_data_at_offset(&over, offset), DEFAULT_MAX_CID_LEN)
+                .unwrap_err();
+            assert!(err.to_string().contains("exceeds max"), "offset {offset}: {err}");
+        }
+        let short = event_data_at_offset(b"cid-1", 32);
+        assert!(super::decode_event_data_bytes(&short, 4).is_err());
+        assert!(super::decode_event_data_bytes_manual(&short, 4).is_err());
+    }
+
+    #[test]
+    fn decode_head_updated_data_tolerates_extra_uint256() {
+        use alloy::sol_types::sol_data::{Bytes, Uint};
+        type WithTimestamp = (Bytes, Uint<256>);
+        let cid: &[u8] = b"ipfs://stamped";
+        let timestamp = alloy::primitives::U256::from(1_700_000_000u64);
+        let data = WithTimestamp::abi_encode_params(&(alloy::primitives::Bytes::from(cid), timestamp));
+        let decoded = decode_head_updated_data(&data).unwrap();
+        assert_eq!(decoded.cid, cid);
+        assert_eq!(decoded.extra_words, vec![timestamp.to_be_bytes::<32>()]);
+        assert_eq!(super::decode_event_data_bytes(&data, DEFAULT_MAX_CID_LEN).unwrap(), cid);
+
+        let standard = Bytes::abi_encode(&alloy::primitives::Bytes::from(cid));
+        assert!(decode_head_updated_data(&standard).unwrap().extra_words.is_empty());
+    }
+
+    #[test]
+    fn cid_hash_is_keccak256() {
+        assert_eq!(
+            hex::encode(cid_hash(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        let ev = HeadUpdatedObserved::builder().cid(b"ipfs://x".to_vec()).build();
+        assert_eq!(cid_hash(&ev.cid), ev.cid_hash);
+    }
+
+    #[test]
+    fn cid_hash_matches_keccak_of_cid() {
+        let genuine = HeadUpdatedObserved::builder().cid(b"ipfs://x".to_vec()).build();
+        assert!(genuine.cid_hash_matches());
+        let tampered = HeadUpdatedObserved { cid: b"ipfs://y".to_vec(), ..genuine };
+        assert!(!tampered.cid_hash_matches());
+    }
+
+    #[test]
+    fn head_updated_observed_serde_roundtrip() {
+        let ev = HeadUpdatedObserved {
+            seq: 7,
+            writer: [0x11; 20],
+            cid: b"cid-7".to_vec(),
+            cid_hash: [0x22; 32],
+            block_number: 100,
+            tx_hash: [0x33; 32],
+            log_index: 2,
+            raw: None,
+            source_label: None,
+        };
+        let json = serde_json::to_value(&ev).unwrap();
+        assert_eq!(json["writer"], hex::encode([0x11; 20]));
+        assert_eq!(json["cid"], hex::encode(b"cid-7"));
+        assert_eq!(json["tx_hash"], hex::encode([0x33; 32]));
+        let back: HeadUpdatedObserved = serde_json::from_value(json).unwrap();
+        assert_eq!(back, ev);
+    }
+
+    #[test]
+    fn current_head_serde_roundtrip() {
+        let head = CurrentHead {
+            seq: 3,
+            cid: b"QmFoo".to_vec(),
+        };
+        let json = serde_json::to_string(&head).unwrap();
+        assert_eq!(json, format!(r#"{{"seq":3,"cid":"{}"}}"#, hex::encode(b"QmFoo")));
+        let back: CurrentHead = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, head);
+    }
+
+    #[test]
+    fn display_head_updated_observed() {
+        let ev = HeadUpdatedObserved {
+            seq: 1,
+            writer: [0xab; 20],
+            cid: b"cid-1".to_vec(),
+            cid_hash: [0; 32],
+            block_number: 42,
+            tx_hash: [0; 32],
+            log_index: 0,
+            raw: None,
+            source_label: None,
+        };
+        assert_eq!(
+            ev.to_string(),
+            "HeadUpdated(seq=1, block=42, log_index=0, writer=0xabababababababababababababababababababab, cid=0x6369642d31)"
+        );
+    }
+
+    #[test]
+    fn display_current_head_elides_long_cid() {
+        let cid: Vec<u8> = (0u8..40).collect();
+        let head = CurrentHead { seq: 9, cid };
+        assert_eq!(head.to_string(), "Head(seq=9, cid=0x0001020304050607..24252627 (40 bytes))");
+        let short = CurrentHead { seq: 0, cid: vec![] };
+        assert_eq!(short.to_string(), "Head(seq=0, cid=0x)");
+    }
+
+    #[test]
+    fn hex_serde_accepts_prefix_and_rejects_wrong_length() {
+        let json = serde_json::json!({"seq": 1, "cid": "0x6162"});
+        let head: CurrentHead = serde_json::from_value(json).unwrap();
+        assert_eq!(head.cid, b"ab");
+
+        let mut ev = serde_json::to_value(HeadUpdatedObserved {
+            seq: 1,
+            writer: [0; 20],
+            cid: vec![],
+            cid_hash: [0; 32],
+            block_number: 0,
+            tx_hash: [0; 32],
+            log_index: 0,
+            raw: None,
+            source_label: None,
+        })
+        .unwrap();
+        ev["writer"] = serde_json::Value::String("00".repeat(19));
+        assert!(serde_json::from_value::<HeadUpdatedObserved>(ev).is_err());
+    }
+
+    const CID_V0: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+
+    #[test]
+    fn cid_equivalent_string_vs_bytes() {
+        let cid = cid::Cid::try_from(CID_V0).unwrap();
+        let v1 = cid.into_v1().unwrap();
+        assert!(cid_equivalent(CID_V0.as_bytes(), &cid.to_bytes()));
+        assert!(cid_equivalent(&cid.to_bytes(), CID_V0.as_bytes()));
+        assert!(cid_equivalent(v1.to_string().as_bytes(), &cid.to_bytes()), "v1 string vs v0 bytes");
+        assert!(cid_equivalent(v1.to_string().as_bytes(), CID_V0.as_bytes()));
+    }
+
+    #[test]
+    fn cid_equivalent_rejects_different_cids() {
+        let other = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+        assert!(!cid_equivalent(CID_V0.as_bytes(), other.as_bytes()));
+        let other_bytes = cid::Cid::try_from(other).unwrap().to_bytes();
+        assert!(!cid_equivalent(CID_V0.as_bytes(), &other_bytes));
+    }
+
+    #[test]
+    fn cid_equivalent_non_cids_compare_bytes() {
+        assert!(cid_equivalent(b"ipfs://first", b"ipfs://first"));
+        assert!(!cid_equivalent(b"ipfs://first", b"ipfs://second"));
+        assert!(!cid_equivalent(b"ipfs://first", CID_V0.as_bytes()));
+    }
+
+    #[test]
+    fn observed_from_alloy_log_decodes_typed_fields() {
+        use alloy::primitives::{Address, Bytes, B256};
+        let mut topic0 = [0u8; 32];
+        topic0[..4].copy_from_slice(&HEAD_UPDATED_TOPIC0);
+        let mut seq_topic = [0u8; 32];
+        seq_topic[24..].copy_from_slice(&9u64.to_be_bytes());
+        let mut writer_topic = [0u8; 32];
+        writer_topic[12..].copy_from_slice(&[0x22; 20]);
+        let data = alloy::sol_types::sol_data::Bytes::abi_encode(&Bytes::from_static(b"ipfs://nine"));
+        let log = alloy::rpc::types::Log {
+            inner: alloy::primitives::Log::new_unchecked(
+                Address::repeat_byte(0x11),
+                vec![B256::from(topic0), B256::from(seq_topic), B256::from(writer_topic), B256::repeat_byte(0x33)],
+                data.into(),
+            ),
+            block_hash: None,
+            block_number: Some(77),
+            block_timestamp: None,
+            transaction_hash: Some(B256::repeat_byte(0x44)),
+            transaction_index: None,
+            log_index: Some(3),
+            removed: false,
+        };
+        assert_eq!(
+            observed_from_alloy_log(&log).unwrap(),
+            HeadUpdatedObserved {
+                seq: 9,
+                writer: [0x22; 20],
+                cid: b"ipfs://nine".to_vec(),
+                cid_hash: [0x33; 32],
+                block_number: 77,
+                tx_hash: [0x44; 32],
+                log_index: 3,
+                raw: None,
+                source_label: None,
+            }
+        );
+        let pending = alloy::rpc::types::Log { block_number: None, ..log };
+        assert!(observed_from_alloy_log(&pending).unwrap_err().is::<PendingLog>());
+    }
+
+    #[test]
+    fn pending_log_is_reported_distinctly() {
+        let mut log = crate::test_support::head_updated_log(1, 5, 0);
+        assert!(decode_log_to_observed(&log).is_ok());
+        for field in ["blockNumber", "logIndex", "transactionHash"] {
+            let mut pending = log.clone();
+            pending[field] = Value::Null;
+            assert!(decode_log_to_observed(&pending).unwrap_err().is::<PendingLog>(), "{field}");
+        }
+        log.as_object_mut().unwrap().remove("blockNumber");
+        let err = decode_log_to_observed(&log).unwrap_err();
+        assert!(!err.is::<PendingLog>(), "a missing field is malformed, not pending");
+    }
+
+    #[test]
+    fn indexed_cid_is_reported_distinctly() {
+        let mut log = crate::test_support::head_updated_log(1, 5, 0);
+        log["data"] = serde_json::json!("0x");
+        let err = decode_log_to_observed(&log).unwrap_err();
+        assert!(err.is::<CidIndexed>());
+        assert!(err.to_string().contains("cid appears indexed"));
+
+        log["data"] = serde_json::json!(format!("0x{}", "00".repeat(32)));
+        assert!(decode_log_to_observed(&log).unwrap_err().is::<CidIndexed>(), "short data");
+    }
 }
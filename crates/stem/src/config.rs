@@ -1,20 +1,542 @@
 //! Indexer configuration.
 
+use crate::abi::LogDecoder;
+use futures_util::future::BoxFuture;
+use rand::Rng;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Default max block range per eth_getLogs request.
+pub const DEFAULT_GETLOGS_MAX_RANGE: u64 = 1000;
+
+/// Default number of eth_getLogs chunk requests in flight during backfill.
+pub const DEFAULT_GETLOGS_CONCURRENCY: usize = 4;
+
+/// Default cap on logs per eth_getLogs response (the common provider limit). A response this
+/// large is assumed truncated.
+pub const DEFAULT_GETLOGS_MAX_RESULTS: usize = 10_000;
+
+/// Default per-request timeout for HTTP JSON-RPC calls and WebSocket handshakes.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of logs held while the indexer is paused.
+pub const DEFAULT_PAUSE_BUFFER_CAPACITY: usize = 4096;
+
+/// Default WebSocket keepalive ping interval (seconds).
+pub const DEFAULT_PING_INTERVAL_SECS: u64 = 30;
+
+/// Default time to wait for a pong before reconnecting (seconds).
+pub const DEFAULT_PONG_TIMEOUT_SECS: u64 = 10;
+
+/// How the indexer follows new blocks after backfill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransportMode {
+    /// Live `eth_subscribe("logs")` over `ws_url`.
+    #[default]
+    WebSocket,
+    /// No subscription: every `interval`, backfill from the cursor to the latest block over HTTP.
+    HttpPoll { interval: Duration },
+}
+
+/// Order in which the indexer surfaces events during catch-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmitOrder {
+    /// History in (block_number, log_index) order only.
+    #[default]
+    Ascending,
+    /// Before backfilling, fetch the newest `getlogs_max_range` blocks and send their latest
+    /// event on the head preview channel (`StemIndexer::subscribe_head_preview`); then backfill
+    /// in ascending order as usual. No preview is sent if that chunk has no events.
+    LatestFirst,
+}
+
+/// Whether the indexer replays history before following live events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeadMode {
+    /// Backfill from `start_block` (then from the cursor on reconnect) before going live.
+    #[default]
+    Backfill,
+    /// No initial backfill: on the first connect, read `head()` at the tip to seed the current
+    /// head and move the cursor to the tip, then publish only events that arrive afterwards.
+    /// Reconnects backfill from the cursor as usual, so events missed while disconnected are
+    /// still delivered.
+    LiveOnly,
+}
+
+/// Where the indexer starts backfilling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartFrom {
+    Number(u64),
+    /// Resolved via `eth_getBlockByHash` on the first connection; the indexer fails if the node
+    /// doesn't know the block (e.g. it was reorged out), instead of indexing a divergent chain.
+    Hash([u8; 32]),
+}
+
+impl Default for StartFrom {
+    fn default() -> Self {
+        StartFrom::Number(0)
+    }
+}
+
+impl From<u64> for StartFrom {
+    fn from(block: u64) -> Self {
+        StartFrom::Number(block)
+    }
+}
+
+/// Resolves a contract name (ENS, a deployment registry, ...) to its address.
+pub type AddressResolver =
+    Arc<dyn Fn(&str) -> BoxFuture<'static, anyhow::Result<[u8; 20]>> + Send + Sync>;
+
+/// A contract given by name, resolved when the indexer starts.
+#[derive(Clone)]
+pub struct ContractName {
+    pub name: String,
+    pub resolver: AddressResolver,
+}
+
+impl std::fmt::Debug for ContractName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContractName").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+/// An extra event type for the indexer to follow: logs whose topic0 is `topic0` are decoded by
+/// `decode` and delivered as [crate::IndexerEvent::Log].
+#[derive(Clone)]
+pub struct TopicDecoder {
+    pub topic0: [u8; 4],
+    pub decode: LogDecoder,
+}
+
+impl std::fmt::Debug for TopicDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopicDecoder")
+            .field("topic0", &hex::encode(self.topic0))
+            .finish_non_exhaustive()
+    }
+}
+
 /// Indexer configuration.
 #[derive(Debug, Clone)]
 pub struct IndexerConfig {
-    /// WebSocket RPC URL for live log subscription.
+    /// WebSocket RPC URL for live log subscription (unused, and may be empty, in
+    /// [TransportMode::HttpPoll]).
     pub ws_url: String,
     /// HTTP RPC URL for backfill (eth_getLogs, eth_blockNumber, eth_call).
     pub http_url: String,
-    /// Stem contract address (20 bytes).
+    /// Stem contract address (20 bytes). Ignored (and zero) when `contract_name` is set.
     pub contract_address: [u8; 20],
+    /// Contract to resolve by name on startup, instead of `contract_address`.
+    pub contract_name: Option<ContractName>,
+    /// Event types followed besides HeadUpdated (whose topic0 always takes the built-in decoder).
+    pub decoders: Vec<TopicDecoder>,
     /// First block to backfill from on startup.
-    pub start_block: u64,
+    pub start_block: StartFrom,
+    /// Cap on the initial backfill: start at `max(start_block, tip - max_backfill_blocks)`.
+    /// Later catch-ups (after reconnects) are not capped. `None` backfills from `start_block`.
+    pub max_backfill_blocks: Option<u64>,
     /// Max block range per eth_getLogs request.
     pub getlogs_max_range: u64,
+    /// Max concurrent eth_getLogs requests (adjacent chunks) during backfill; 0 is treated as 1.
+    pub getlogs_concurrency: usize,
+    /// Node's cap on logs per eth_getLogs response. A full response is split into smaller
+    /// ranges; a full single-block response fails with
+    /// [crate::IndexerError::BlockTooDense]. 0 disables the guard.
+    pub getlogs_max_results: usize,
+    /// After backfill, read `head()` at the backfilled tip block (needs archival state; falls
+    /// back to `"latest"` when the node rejects historical calls).
+    pub head_at_backfill_tip: bool,
+    /// Drop (with a warning) events whose `cid_hash` is not `keccak256(cid)`.
+    pub verify_cid_hash: bool,
+    /// On startup, fail unless `eth_getCode` finds code at the contract address (and the address
+    /// is non-zero). Disable for setups where the node can't answer `eth_getCode`.
+    pub verify_contract_code: bool,
+    /// Logs whose `cid` is longer than this many bytes fail to decode and are skipped
+    /// (default [crate::abi::DEFAULT_MAX_CID_LEN]).
+    pub max_cid_len: usize,
+    /// Attach each log's JSON to [crate::HeadUpdatedObserved::raw], for debugging decode issues
+    /// (off by default: it roughly doubles the memory held per event).
+    pub keep_raw_logs: bool,
+    /// Set as [crate::HeadUpdatedObserved::source_label] on every emitted event, so a consumer
+    /// merging several indexers (e.g. atom and stem) can tell them apart.
+    pub source_label: Option<String>,
+    /// Catch-up emission order (default [EmitOrder::Ascending]). Only the head preview channel
+    /// is affected; subscribers always see ascending order.
+    pub emit_order: EmitOrder,
+    /// Hold up to this many live WebSocket logs and release them sorted by
+    /// `(block_number, log_index)`, oldest first once the buffer overflows (or the stream goes
+    /// briefly idle), to repair providers that deliver slightly out of order. 0 (the default)
+    /// publishes each notification as it arrives.
+    pub reorder_window: usize,
+    /// Logs held while paused (`StemIndexer::pause`); beyond this the oldest are dropped, and
+    /// not delivered again since the cursor has moved past them.
+    pub pause_buffer_capacity: usize,
+    /// Backfill history or follow live events only (default [HeadMode::Backfill]).
+    pub head_mode: HeadMode,
+    /// Live-follow transport (default [TransportMode::WebSocket]).
+    pub transport: TransportMode,
     /// Reconnection backoff (initial and max seconds).
     pub reconnection: ReconnectionConfig,
+    /// Delay before reconnecting after a transient failure; `None` uses [Exponential] built from
+    /// `reconnection`.
+    pub backoff: Option<Box<dyn BackoffStrategy>>,
+    /// Send a WebSocket ping this often while the subscription is idle (0 disables keepalive).
+    pub ping_interval_secs: u64,
+    /// Treat the connection as dead (and reconnect) if no pong arrives within this many seconds.
+    pub pong_timeout_secs: u64,
+    /// Fail any single HTTP JSON-RPC call, WebSocket connect or `eth_subscribe` exchange that
+    /// takes longer than this (`Duration::ZERO` disables the HTTP timeout).
+    pub request_timeout: Duration,
+    /// HTTP client for JSON-RPC calls to `http_url`; `None` builds one (no proxy,
+    /// `request_timeout`).
+    pub http_client: Option<reqwest::Client>,
+    /// Extra PEM root certificates trusted, alongside the system roots, for `wss://` and for
+    /// `https://` JSON-RPC (e.g. a private CA or a TLS-intercepting proxy). Not applied to
+    /// `http_client`.
+    pub root_certificates: Vec<Vec<u8>>,
+}
+
+impl IndexerConfig {
+    /// Start a builder with defaults for everything except the URLs and contract address.
+    pub fn builder() -> IndexerConfigBuilder {
+        IndexerConfigBuilder::new()
+    }
+}
+
+/// Invalid or incomplete [IndexerConfig].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("{0} required")]
+    Missing(&'static str),
+    #[error("{0} must not be empty")]
+    EmptyUrl(&'static str),
+    #[error("contract address must be 20 bytes, got {0}")]
+    InvalidAddress(usize),
+    #[error("invalid root certificate: {0}")]
+    InvalidCertificate(String),
+}
+
+/// Builder for [IndexerConfig]. `http_url`, `ws_url` and `contract_address` (or
+/// `contract_name`) are required (`ws_url` only with [TransportMode::WebSocket]);
+/// `start_block` defaults to 0, `max_backfill_blocks` to `None`, `getlogs_max_range` to [DEFAULT_GETLOGS_MAX_RANGE],
+/// `getlogs_concurrency` to [DEFAULT_GETLOGS_CONCURRENCY], `getlogs_max_results` to
+/// [DEFAULT_GETLOGS_MAX_RESULTS], `head_at_backfill_tip`, `verify_cid_hash` and `keep_raw_logs` to false,
+/// `verify_contract_code` to true,
+/// `max_cid_len` to [crate::abi::DEFAULT_MAX_CID_LEN], `emit_order` to [EmitOrder::Ascending],
+/// `reorder_window` to 0, `pause_buffer_capacity` to [DEFAULT_PAUSE_BUFFER_CAPACITY], `head_mode` to [HeadMode::Backfill], `source_label` to `None`, `decoders` to none,
+/// `reconnection` to [ReconnectionConfig::default], `backoff` to `None` and keepalive to
+/// [DEFAULT_PING_INTERVAL_SECS] / [DEFAULT_PONG_TIMEOUT_SECS] and `request_timeout` to
+/// [DEFAULT_REQUEST_TIMEOUT].
+#[derive(Debug, Clone)]
+pub struct IndexerConfigBuilder {
+    ws_url: Option<String>,
+    http_url: Option<String>,
+    contract_address: Option<Vec<u8>>,
+    contract_name: Option<ContractName>,
+    decoders: Vec<TopicDecoder>,
+    start_block: StartFrom,
+    max_backfill_blocks: Option<u64>,
+    getlogs_max_range: u64,
+    getlogs_concurrency: usize,
+    getlogs_max_results: usize,
+    head_at_backfill_tip: bool,
+    verify_cid_hash: bool,
+    verify_contract_code: bool,
+    max_cid_len: usize,
+    keep_raw_logs: bool,
+    source_label: Option<String>,
+    emit_order: EmitOrder,
+    reorder_window: usize,
+    pause_buffer_capacity: usize,
+    head_mode: HeadMode,
+    transport: TransportMode,
+    reconnection: ReconnectionConfig,
+    backoff: Option<Box<dyn BackoffStrategy>>,
+    ping_interval_secs: u64,
+    pong_timeout_secs: u64,
+    request_timeout: Duration,
+    http_client: Option<reqwest::Client>,
+    root_certificates: Vec<Vec<u8>>,
+}
+
+impl IndexerConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            ws_url: None,
+            http_url: None,
+            contract_address: None,
+            contract_name: None,
+            decoders: Vec::new(),
+            start_block: StartFrom::default(),
+            max_backfill_blocks: None,
+            getlogs_max_range: DEFAULT_GETLOGS_MAX_RANGE,
+            getlogs_concurrency: DEFAULT_GETLOGS_CONCURRENCY,
+            getlogs_max_results: DEFAULT_GETLOGS_MAX_RESULTS,
+            head_at_backfill_tip: false,
+            verify_cid_hash: false,
+            verify_contract_code: true,
+            max_cid_len: crate::abi::DEFAULT_MAX_CID_LEN,
+            keep_raw_logs: false,
+            source_label: None,
+            emit_order: EmitOrder::Ascending,
+            reorder_window: 0,
+            pause_buffer_capacity: DEFAULT_PAUSE_BUFFER_CAPACITY,
+            head_mode: HeadMode::Backfill,
+            transport: TransportMode::WebSocket,
+            reconnection: ReconnectionConfig::default(),
+            backoff: None,
+            ping_interval_secs: DEFAULT_PING_INTERVAL_SECS,
+            pong_timeout_secs: DEFAULT_PONG_TIMEOUT_SECS,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            http_client: None,
+            root_certificates: Vec::new(),
+        }
+    }
+
+    pub fn ws_url(mut self, url: impl Into<String>) -> Self {
+        self.ws_url = Some(url.into());
+        self
+    }
+
+    pub fn http_url(mut self, url: impl Into<String>) -> Self {
+        self.http_url = Some(url.into());
+        self
+    }
+
+    /// Set `ws_url` from the HTTP URL (http -> ws, https -> wss). Call after `http_url`.
+    pub fn derive_ws_from_http(mut self) -> Self {
+        self.ws_url = self
+            .http_url
+            .as_ref()
+            .map(|u| u.replace("http://", "ws://").replace("https://", "wss://"));
+        self
+    }
+
+    /// Contract address bytes; length is validated in [Self::build].
+    pub fn contract_address(mut self, addr: impl AsRef<[u8]>) -> Self {
+        self.contract_address = Some(addr.as_ref().to_vec());
+        self
+    }
+
+    /// Identify the contract by `name`, resolved with `resolver` once when the indexer starts
+    /// (`StemIndexer::run`, `backfill_once` or `replay`); a resolution failure is returned from
+    /// there. Takes precedence over [Self::contract_address].
+    pub fn contract_name<F>(mut self, name: impl Into<String>, resolver: F) -> Self
+    where
+        F: Fn(&str) -> BoxFuture<'static, anyhow::Result<[u8; 20]>> + Send + Sync + 'static,
+    {
+        self.contract_name = Some(ContractName {
+            name: name.into(),
+            resolver: Arc::new(resolver),
+        });
+        self
+    }
+
+    /// Also follow logs whose topic0 is `topic0`, decoding them with `decode` (see
+    /// [TopicDecoder]). A decoder for HeadUpdated's own topic0 is never used.
+    pub fn decoder<F>(mut self, topic0: [u8; 4], decode: F) -> Self
+    where
+        F: Fn(&serde_json::Value) -> anyhow::Result<serde_json::Value> + Send + Sync + 'static,
+    {
+        self.decoders.push(TopicDecoder {
+            topic0,
+            decode: Arc::new(decode),
+        });
+        self
+    }
+
+    pub fn start_block(mut self, block: u64) -> Self {
+        self.start_block = StartFrom::Number(block);
+        self
+    }
+
+    /// Start at the block with this hash (see [StartFrom::Hash]).
+    pub fn start_block_hash(mut self, hash: [u8; 32]) -> Self {
+        self.start_block = StartFrom::Hash(hash);
+        self
+    }
+
+    pub fn max_backfill_blocks(mut self, max: u64) -> Self {
+        self.max_backfill_blocks = Some(max);
+        self
+    }
+
+    pub fn getlogs_max_range(mut self, range: u64) -> Self {
+        self.getlogs_max_range = range;
+        self
+    }
+
+    pub fn getlogs_concurrency(mut self, concurrency: usize) -> Self {
+        self.getlogs_concurrency = concurrency;
+        self
+    }
+
+    pub fn getlogs_max_results(mut self, max_results: usize) -> Self {
+        self.getlogs_max_results = max_results;
+        self
+    }
+
+    pub fn head_at_backfill_tip(mut self, enabled: bool) -> Self {
+        self.head_at_backfill_tip = enabled;
+        self
+    }
+
+    pub fn verify_cid_hash(mut self, enabled: bool) -> Self {
+        self.verify_cid_hash = enabled;
+        self
+    }
+
+    pub fn verify_contract_code(mut self, enabled: bool) -> Self {
+        self.verify_contract_code = enabled;
+        self
+    }
+
+    pub fn max_cid_len(mut self, max: usize) -> Self {
+        self.max_cid_len = max;
+        self
+    }
+
+    pub fn keep_raw_logs(mut self, enabled: bool) -> Self {
+        self.keep_raw_logs = enabled;
+        self
+    }
+
+    pub fn source_label(mut self, label: impl Into<String>) -> Self {
+        self.source_label = Some(label.into());
+        self
+    }
+
+    pub fn emit_order(mut self, order: EmitOrder) -> Self {
+        self.emit_order = order;
+        self
+    }
+
+    pub fn reorder_window(mut self, logs: usize) -> Self {
+        self.reorder_window = logs;
+        self
+    }
+
+    pub fn pause_buffer_capacity(mut self, logs: usize) -> Self {
+        self.pause_buffer_capacity = logs;
+        self
+    }
+
+    pub fn head_mode(mut self, mode: HeadMode) -> Self {
+        self.head_mode = mode;
+        self
+    }
+
+    pub fn transport(mut self, transport: TransportMode) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Convenience: equivalent to `.transport(TransportMode::HttpPoll { interval })`.
+    pub fn http_poll(mut self, interval: Duration) -> Self {
+        self.transport = TransportMode::HttpPoll { interval };
+        self
+    }
+
+    pub fn reconnection(mut self, reconnection: ReconnectionConfig) -> Self {
+        self.reconnection = reconnection;
+        self
+    }
+
+    /// Reconnection delay strategy (e.g. [Fixed]) in place of the default [Exponential].
+    pub fn backoff(mut self, strategy: impl BackoffStrategy + 'static) -> Self {
+        self.backoff = Some(Box::new(strategy));
+        self
+    }
+
+    /// WebSocket keepalive: ping every `ping_interval_secs` (0 disables), reconnect if no pong
+    /// within `pong_timeout_secs`.
+    pub fn keepalive(mut self, ping_interval_secs: u64, pong_timeout_secs: u64) -> Self {
+        self.ping_interval_secs = ping_interval_secs;
+        self.pong_timeout_secs = pong_timeout_secs;
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Send HTTP JSON-RPC through `client` (custom TLS roots, proxy, a shared connection pool)
+    /// instead of building one. `request_timeout` then only bounds the WebSocket handshake;
+    /// configure HTTP timeouts on `client`.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// Also trust this PEM root certificate for the WebSocket and HTTP connections (see
+    /// [IndexerConfig::root_certificates]). May be called repeatedly.
+    pub fn add_root_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    pub fn build(self) -> Result<IndexerConfig, ConfigError> {
+        for pem in &self.root_certificates {
+            native_tls::Certificate::from_pem(pem).map_err(|e| ConfigError::InvalidCertificate(e.to_string()))?;
+        }
+        let http_url = self.http_url.ok_or(ConfigError::Missing("http_url"))?;
+        if http_url.is_empty() {
+            return Err(ConfigError::EmptyUrl("http_url"));
+        }
+        let ws_url = match (self.ws_url, self.transport) {
+            (Some(url), _) if !url.is_empty() => url,
+            (_, TransportMode::HttpPoll { .. }) => String::new(),
+            (Some(_), TransportMode::WebSocket) => return Err(ConfigError::EmptyUrl("ws_url")),
+            (None, TransportMode::WebSocket) => return Err(ConfigError::Missing("ws_url")),
+        };
+        let contract_address: [u8; 20] = match (self.contract_address, &self.contract_name) {
+            (_, Some(_)) => [0; 20],
+            (Some(addr), None) => addr
+                .as_slice()
+                .try_into()
+                .map_err(|_| ConfigError::InvalidAddress(addr.len()))?,
+            (None, None) => return Err(ConfigError::Missing("contract_address")),
+        };
+        Ok(IndexerConfig {
+            ws_url,
+            http_url,
+            contract_address,
+            contract_name: self.contract_name,
+            decoders: self.decoders,
+            start_block: self.start_block,
+            max_backfill_blocks: self.max_backfill_blocks,
+            getlogs_max_range: self.getlogs_max_range,
+            getlogs_concurrency: self.getlogs_concurrency,
+            getlogs_max_results: self.getlogs_max_results,
+            head_at_backfill_tip: self.head_at_backfill_tip,
+            verify_cid_hash: self.verify_cid_hash,
+            verify_contract_code: self.verify_contract_code,
+            max_cid_len: self.max_cid_len,
+            keep_raw_logs: self.keep_raw_logs,
+            source_label: self.source_label,
+            emit_order: self.emit_order,
+            reorder_window: self.reorder_window,
+            pause_buffer_capacity: self.pause_buffer_capacity,
+            head_mode: self.head_mode,
+            transport: self.transport,
+            reconnection: self.reconnection,
+            backoff: self.backoff,
+            ping_interval_secs: self.ping_interval_secs,
+            pong_timeout_secs: self.pong_timeout_secs,
+            request_timeout: self.request_timeout,
+            http_client: self.http_client,
+            root_certificates: self.root_certificates,
+        })
+    }
+}
+
+impl Default for IndexerConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Reconnection backoff.
@@ -22,6 +544,8 @@ pub struct IndexerConfig {
 pub struct ReconnectionConfig {
     pub initial_backoff_secs: u64,
     pub max_backoff_secs: u64,
+    /// Give up after this many consecutive failed connections (`None` retries forever).
+    pub max_attempts: Option<u32>,
 }
 
 impl Default for ReconnectionConfig {
@@ -29,6 +553,114 @@ impl Default for ReconnectionConfig {
         Self {
             initial_backoff_secs: 1,
             max_backoff_secs: 60,
+            max_attempts: None,
+        }
+    }
+}
+
+/// Delay between reconnection attempts. The indexer calls [Self::next_delay] after each
+/// consecutive transient failure and [Self::reset] once a connection ends cleanly.
+pub trait BackoffStrategy: Send + Sync + std::fmt::Debug {
+    /// Delay before the next attempt; `attempt` counts consecutive failures (1 for the first).
+    fn next_delay(&mut self, attempt: u32) -> Duration;
+
+    /// Forget state accumulated across failures.
+    fn reset(&mut self) {}
+
+    /// Clone into a box, so [IndexerConfig] stays `Clone`.
+    fn clone_box(&self) -> Box<dyn BackoffStrategy>;
+}
+
+impl Clone for Box<dyn BackoffStrategy> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// `initial * 2^attempt`, capped at `max`, plus up to `jitter` of random delay (the default).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exponential {
+    pub initial: Duration,
+    pub max: Duration,
+    pub jitter: Duration,
+}
+
+impl From<&ReconnectionConfig> for Exponential {
+    fn from(reconnection: &ReconnectionConfig) -> Self {
+        Self {
+            initial: Duration::from_secs(reconnection.initial_backoff_secs),
+            max: Duration::from_secs(reconnection.max_backoff_secs),
+            jitter: Duration::from_millis(500),
+        }
+    }
+}
+
+impl BackoffStrategy for Exponential {
+    fn next_delay(&mut self, attempt: u32) -> Duration {
+        let base = self
+            .initial
+            .saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+            .min(self.max);
+        let jitter = match self.jitter.as_millis() as u64 {
+            0 => 0,
+            ms => rand::thread_rng().gen_range(0..ms),
+        };
+        base + Duration::from_millis(jitter)
+    }
+
+    fn clone_box(&self) -> Box<dyn BackoffStrategy> {
+        Box::new(*self)
+    }
+}
+
+/// The same delay after every failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixed(pub Duration);
+
+impl BackoffStrategy for Fixed {
+    fn next_delay(&mut self, _attempt: u32) -> Duration {
+        self.0
+    }
+
+    fn clone_box(&self) -> Box<dyn BackoffStrategy> {
+        Box::new(*self)
+    }
+}
+
+/// Retry policy for individual RPC calls: exponential backoff with jitter, same shape as
+/// [ReconnectionConfig] but in milliseconds and bounded by `max_attempts` (including the first try).
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl RetryConfig {
+    /// Single attempt, no retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Backoff before retry number `retry` (0-based): `initial * 2^retry`, capped at `max`.
+    pub fn backoff(&self, retry: u32) -> std::time::Duration {
+        let ms = self
+            .initial_backoff_ms
+            .saturating_mul(1u64.checked_shl(retry).unwrap_or(u64::MAX))
+            .min(self.max_backoff_ms);
+        std::time::Duration::from_millis(ms)
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 5_000,
         }
     }
 }
@@ -37,10 +669,160 @@ impl Default for ReconnectionConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn builder_rejects_invalid_root_certificate() {
+        let builder = || {
+            IndexerConfig::builder()
+                .ws_url("wss://x")
+                .http_url("https://x")
+                .contract_address([0x11; 20])
+        };
+        let err = builder().add_root_certificate("not a certificate").build().unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidCertificate(_)));
+        let c = builder().add_root_certificate(crate::test_support::TEST_CA_PEM).build().unwrap();
+        assert_eq!(c.root_certificates.len(), 1);
+    }
+
     #[test]
     fn reconnection_config_default() {
         let c = ReconnectionConfig::default();
         assert_eq!(c.initial_backoff_secs, 1);
         assert_eq!(c.max_backoff_secs, 60);
+        assert_eq!(c.max_attempts, None);
+    }
+
+    #[test]
+    fn retry_config_backoff_grows_and_caps() {
+        let r = RetryConfig {
+            max_attempts: 10,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+        };
+        let ms: Vec<u128> = (0..6).map(|i| r.backoff(i).as_millis()).collect();
+        assert_eq!(ms, vec![100, 200, 400, 800, 1_000, 1_000]);
+        assert_eq!(r.backoff(200).as_millis(), 1_000);
+    }
+
+    #[test]
+    fn exponential_backoff_doubles_and_caps() {
+        let mut s = Exponential {
+            jitter: Duration::ZERO,
+            ..Exponential::from(&ReconnectionConfig {
+                initial_backoff_secs: 1,
+                max_backoff_secs: 10,
+                max_attempts: None,
+            })
+        };
+        let secs: Vec<u64> = (1..=5).map(|a| s.next_delay(a).as_secs()).collect();
+        assert_eq!(secs, vec![2, 4, 8, 10, 10]);
+        assert_eq!(s.next_delay(100), Duration::from_secs(10));
+
+        let mut jittered = Exponential { jitter: Duration::from_millis(500), ..s };
+        let d = jittered.next_delay(1);
+        assert!(d >= Duration::from_secs(2) && d < Duration::from_millis(2_500), "{d:?}");
+    }
+
+    #[test]
+    fn fixed_backoff_is_constant() {
+        let mut s = Fixed(Duration::from_millis(750));
+        let ms: Vec<u128> = (1..=4).map(|a| s.next_delay(a).as_millis()).collect();
+        assert_eq!(ms, vec![750; 4]);
+        let c = IndexerConfig::builder()
+            .http_url("http://127.0.0.1:8545")
+            .http_poll(Duration::from_secs(1))
+            .contract_address([0x11; 20])
+            .backoff(s)
+            .build()
+            .unwrap();
+        assert_eq!(c.clone().backoff.unwrap().next_delay(9), Duration::from_millis(750));
+    }
+
+    #[test]
+    fn builder_minimal() {
+        let c = IndexerConfig::builder()
+            .http_url("https://rpc.example")
+            .derive_ws_from_http()
+            .contract_address([0x11; 20])
+            .build()
+            .unwrap();
+        assert_eq!(c.http_url, "https://rpc.example");
+        assert_eq!(c.ws_url, "wss://rpc.example");
+        assert_eq!(c.contract_address, [0x11; 20]);
+        assert_eq!(c.start_block, StartFrom::Number(0));
+        assert_eq!(c.max_backfill_blocks, None);
+        assert!(c.decoders.is_empty());
+        assert_eq!(c.getlogs_max_range, DEFAULT_GETLOGS_MAX_RANGE);
+        assert_eq!(c.getlogs_concurrency, DEFAULT_GETLOGS_CONCURRENCY);
+        assert_eq!(c.getlogs_max_results, DEFAULT_GETLOGS_MAX_RESULTS);
+        assert!(!c.head_at_backfill_tip);
+        assert!(!c.verify_cid_hash);
+        assert!(c.verify_contract_code);
+        assert!(!c.keep_raw_logs);
+        assert_eq!(c.source_label, None);
+        assert_eq!(c.max_cid_len, crate::abi::DEFAULT_MAX_CID_LEN);
+        assert_eq!(c.emit_order, EmitOrder::Ascending);
+        assert_eq!(c.head_mode, HeadMode::Backfill);
+        assert_eq!(c.reconnection.initial_backoff_secs, 1);
+        assert!(c.backoff.is_none());
+        assert_eq!(c.ping_interval_secs, DEFAULT_PING_INTERVAL_SECS);
+        assert_eq!(c.pong_timeout_secs, DEFAULT_PONG_TIMEOUT_SECS);
+        assert_eq!(c.request_timeout, DEFAULT_REQUEST_TIMEOUT);
+    }
+
+    #[test]
+    fn contract_name_replaces_address() {
+        let c = IndexerConfig::builder()
+            .http_url("http://127.0.0.1:8545")
+            .http_poll(Duration::from_secs(1))
+            .contract_name("stem.eth", |_| Box::pin(async { Ok([0x22; 20]) }))
+            .build()
+            .unwrap();
+        assert_eq!(c.contract_name.as_ref().unwrap().name, "stem.eth");
+        assert_eq!(c.contract_address, [0; 20]);
+    }
+
+    #[test]
+    fn builder_validation_failures() {
+        let base = IndexerConfig::builder()
+            .http_url("http://127.0.0.1:8545")
+            .ws_url("ws://127.0.0.1:8545");
+        assert_eq!(
+            base.clone().build().unwrap_err(),
+            ConfigError::Missing("contract_address")
+        );
+        assert_eq!(
+            base.clone().contract_address([0u8; 19]).build().unwrap_err(),
+            ConfigError::InvalidAddress(19)
+        );
+        assert_eq!(
+            base.contract_address([0u8; 20]).ws_url("").build().unwrap_err(),
+            ConfigError::EmptyUrl("ws_url")
+        );
+        assert_eq!(
+            IndexerConfig::builder()
+                .ws_url("ws://x")
+                .contract_address([0u8; 20])
+                .build()
+                .unwrap_err(),
+            ConfigError::Missing("http_url")
+        );
+    }
+
+    #[test]
+    fn http_poll_does_not_require_ws_url() {
+        let c = IndexerConfig::builder()
+            .http_url("http://127.0.0.1:8545")
+            .contract_address([0x11; 20])
+            .http_poll(Duration::from_secs(2))
+            .build()
+            .unwrap();
+        assert_eq!(c.transport, TransportMode::HttpPoll { interval: Duration::from_secs(2) });
+        assert!(c.ws_url.is_empty());
+        let err = IndexerConfig::builder()
+            .http_url("http://127.0.0.1:8545")
+            .contract_address([0x11; 20])
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::Missing("ws_url"));
     }
 }
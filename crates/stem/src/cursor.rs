@@ -1,6 +1,10 @@
 //! In-memory cursor for the indexer (no disk persistence).
 //!
-//! Process restarts start from start_block again (duplicates possible).
+//! Process restarts start from start_block again (duplicates possible), unless the caller
+//! records progress with [write_cursor_file] and resumes from [read_cursor_file].
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 /// Cursor: last processed block. In-memory only.
 #[derive(Debug, Clone, Copy, Default)]
@@ -16,9 +20,48 @@ impl Cursor {
     }
 }
 
+/// Block number stored in a cursor file (one line, decimal), or `None` if `path` doesn't exist.
+/// Unparseable contents fail with [std::io::ErrorKind::InvalidData].
+pub fn read_cursor_file(path: impl AsRef<Path>) -> std::io::Result<Option<u64>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let line = contents.lines().next().unwrap_or("").trim();
+    line.parse()
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("cursor {line:?}: {e}")))
+}
+
+/// Store `block` in the cursor file at `path`, atomically: the number is written and fsynced to
+/// `path.tmp`, which is then renamed over `path`. A crash leaves either the old or the new value,
+/// never a partial one.
+pub fn write_cursor_file(path: impl AsRef<Path>, block: u64) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let tmp = tmp_path(path);
+    let mut file = std::fs::File::create(&tmp)?;
+    writeln!(file, "{block}")?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    // Persist the rename itself (directory fsync isn't possible on every platform).
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::scratch_dir;
 
     #[test]
     fn cursor_new() {
@@ -31,4 +74,36 @@ mod tests {
         let c = Cursor::default();
         assert_eq!(c.last_processed_block, 0);
     }
+
+    #[test]
+    fn cursor_file_round_trip() {
+        let dir = scratch_dir("cursor-file");
+        let path = dir.join("cursor");
+        assert_eq!(read_cursor_file(&path).unwrap(), None);
+
+        write_cursor_file(&path, 41).unwrap();
+        assert_eq!(read_cursor_file(&path).unwrap(), Some(41));
+        write_cursor_file(&path, 42).unwrap();
+        assert_eq!(read_cursor_file(&path).unwrap(), Some(42));
+        assert!(!tmp_path(&path).exists(), "temp file renamed away");
+
+        std::fs::write(&path, "garbage\n").unwrap();
+        assert_eq!(read_cursor_file(&path).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn interrupted_cursor_write_keeps_previous_value() {
+        let dir = scratch_dir("cursor-file-crash");
+        let path = dir.join("cursor");
+        write_cursor_file(&path, 7).unwrap();
+        // A crash mid-write leaves only a partial temp file; the cursor itself is untouched.
+        std::fs::write(tmp_path(&path), "12").unwrap();
+        assert_eq!(read_cursor_file(&path).unwrap(), Some(7));
+
+        write_cursor_file(&path, 8).unwrap();
+        assert_eq!(read_cursor_file(&path).unwrap(), Some(8));
+        assert!(!tmp_path(&path).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
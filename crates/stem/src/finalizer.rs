@@ -2,19 +2,105 @@
 //!
 //! Consumes observed [HeadUpdatedObserved] from the indexer and outputs only events that are
 //! eligible per the configured [Strategy] and pass the canonical cross-check (`Stem.head()`).
-//! Dedup key is `(tx_hash, log_index)` (globally unique per log; stable across reconnects/backfill).
+//! Dedup key is `(tx_hash, log_index)` by default (globally unique per log; stable across
+//! reconnects/backfill); see [DedupKey] for per-seq and per-cid alternatives.
 //! Configure via [Strategy]; use [ConfirmationDepth] for depth-K finalization. See the
 //! `finalizer` example for a full pipeline (indexer → finalizer → JSON output).
 
-use crate::abi::{decode_head_return, HeadUpdatedObserved, HEAD_SELECTOR};
+use crate::abi::{cid_codec, cid_equivalent, cid_string, decode_head_return, HeadReverted, HeadUpdatedObserved, HEAD_SELECTOR};
+use crate::config::{RetryConfig, DEFAULT_REQUEST_TIMEOUT};
+use crate::indexer::StemIndexer;
+use crate::rpc::{HttpRpcClient, RpcClient, RpcError};
+use crate::sink::{FinalizedSink, SinkError};
+use anyhow::Context;
+use futures_util::future::BoxFuture;
+use rand::Rng;
 use serde::Serialize;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
 
-/// Defines when an observed event is eligible for finalization given the current chain tip.
+/// Chain state a [Strategy] sees on each drain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FinalityContext {
+    /// Latest block number.
+    pub tip: u64,
+    /// Block reported for the `"finalized"` tag; only fetched when
+    /// [Strategy::needs_finalized_block] is true, and `None` if the node has none.
+    pub finalized_block: Option<u64>,
+    /// Block reported for the `"safe"` tag; only fetched when [Strategy::needs_safe_block] is
+    /// true, and `None` if the node has none.
+    pub safe_block: Option<u64>,
+    /// Wall-clock time of the drain (seconds since the Unix epoch).
+    pub now_unix: u64,
+}
+
+impl FinalityContext {
+    /// Context at `tip`, stamped with the current time and no finalized or safe block.
+    pub fn at_tip(tip: u64) -> Self {
+        Self {
+            tip,
+            finalized_block: None,
+            safe_block: None,
+            now_unix: unix_now(),
+        }
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Replacement tip source for [Finalizer::with_tip_source].
+type TipSource = Box<dyn Fn() -> BoxFuture<'static, Result<u64, FinalizerError>> + Send + Sync>;
+
+/// Defines when an observed event is eligible for finalization given the current chain state.
 pub trait Strategy: Send + Sync {
     /// Returns true if the event has enough confirmations (or otherwise meets the strategy).
-    fn is_eligible(&self, ev: &HeadUpdatedObserved, tip: u64) -> bool;
+    /// Called once per pending event on each drain; stateful strategies may record the call.
+    fn is_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool;
+
+    /// [Self::is_eligible] without side effects, for reporting ([Finalizer::pending_status]).
+    /// Stateful strategies must override it.
+    fn peek_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        self.is_eligible(ev, ctx)
+    }
+
+    /// Whether the finalizer must fill [FinalityContext::finalized_block] (one extra RPC per drain).
+    fn needs_finalized_block(&self) -> bool {
+        false
+    }
+
+    /// Whether the finalizer must fill [FinalityContext::safe_block] (one extra RPC per drain).
+    fn needs_safe_block(&self) -> bool {
+        false
+    }
+
+    /// Confirmations (`tip - block_number`) this strategy requires for `ev`, if it is a
+    /// confirmation count at all; shown by [Finalizer::pending_status].
+    fn required_confirmations(&self, _ev: &HeadUpdatedObserved) -> Option<u64> {
+        None
+    }
+}
+
+/// Strategy that only looks at the chain tip (the pre-[FinalityContext] signature). Implement
+/// this instead of [Strategy] for tip-only rules; the blanket impl passes `ctx.tip`.
+pub trait TipStrategy: Send + Sync {
+    fn is_eligible_at_tip(&self, ev: &HeadUpdatedObserved, tip: u64) -> bool;
+}
+
+impl<T: TipStrategy> Strategy for T {
+    fn is_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        self.is_eligible_at_tip(ev, ctx.tip)
+    }
 }
 
 /// Confirmation-depth strategy: eligible when `tip >= event.block_number + K`.
@@ -22,9 +108,192 @@ pub trait Strategy: Send + Sync {
 pub struct ConfirmationDepth(pub u64);
 
 impl Strategy for ConfirmationDepth {
-    fn is_eligible(&self, ev: &HeadUpdatedObserved, tip: u64) -> bool {
-        tip >= ev.block_number.saturating_add(self.0)
+    fn is_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        ctx.tip >= ev.block_number.saturating_add(self.0)
+    }
+
+    fn required_confirmations(&self, _ev: &HeadUpdatedObserved) -> Option<u64> {
+        Some(self.0)
+    }
+}
+
+/// Zero-confirmation strategy for chains with instant finality: every event is eligible at
+/// once, so finalization rests solely on the canonical `head()` cross-check. Equivalent to
+/// `ConfirmationDepth(0)`, but states the intent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImmediateCanonical;
+
+impl Strategy for ImmediateCanonical {
+    fn is_eligible(&self, _ev: &HeadUpdatedObserved, _ctx: &FinalityContext) -> bool {
+        true
+    }
+}
+
+/// Confirmation depth chosen by the event's writer: `depths[writer]`, else `default`. For
+/// multi-writer deployments where some writers warrant deeper confirmation than others.
+#[derive(Debug, Clone)]
+pub struct PerWriterDepth {
+    pub depths: HashMap<[u8; 20], u64>,
+    pub default: u64,
+}
+
+impl PerWriterDepth {
+    pub fn new(depths: HashMap<[u8; 20], u64>, default: u64) -> Self {
+        Self { depths, default }
+    }
+
+    /// Depth required for events from `writer`.
+    pub fn depth_for(&self, writer: &[u8; 20]) -> u64 {
+        self.depths.get(writer).copied().unwrap_or(self.default)
+    }
+}
+
+impl Strategy for PerWriterDepth {
+    fn is_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        ConfirmationDepth(self.depth_for(&ev.writer)).is_eligible(ev, ctx)
+    }
+
+    fn required_confirmations(&self, ev: &HeadUpdatedObserved) -> Option<u64> {
+        Some(self.depth_for(&ev.writer))
+    }
+}
+
+/// Finalized-tag strategy: eligible once the event's block is at or below the node's
+/// `"finalized"` block. Nothing is eligible while the node reports no finalized block.
+#[derive(Debug, Clone, Default)]
+pub struct FinalizedTag;
+
+impl Strategy for FinalizedTag {
+    fn is_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        ctx.finalized_block.is_some_and(|f| ev.block_number <= f)
+    }
+
+    fn needs_finalized_block(&self) -> bool {
+        true
+    }
+}
+
+/// Safe-tag strategy: eligible once the event's block is at or below the node's `"safe"`
+/// (justified, not yet finalized) block, for lower latency than [FinalizedTag]. While the node
+/// reports no safe block (pre-merge and dev chains), `fallback` decides instead.
+#[derive(Debug, Clone)]
+pub struct SafeTag {
+    pub fallback: ConfirmationDepth,
+}
+
+impl SafeTag {
+    /// Safe tag, falling back to `ConfirmationDepth(k)`.
+    pub fn with_fallback_depth(k: u64) -> Self {
+        Self {
+            fallback: ConfirmationDepth(k),
+        }
+    }
+}
+
+impl Default for SafeTag {
+    /// Falls back to the builder's default depth of 6.
+    fn default() -> Self {
+        Self::with_fallback_depth(6)
+    }
+}
+
+impl Strategy for SafeTag {
+    fn is_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        match ctx.safe_block {
+            Some(safe) => ev.block_number <= safe,
+            None => self.fallback.is_eligible(ev, ctx),
+        }
     }
+
+    fn needs_safe_block(&self) -> bool {
+        true
+    }
+}
+
+/// [Debounced] forgets an event not checked for this long (or `delay`, if longer), i.e. one
+/// that left the pending buffer.
+const DEBOUNCE_FORGET_AFTER: Duration = Duration::from_secs(60);
+
+/// Cool-down wrapper: an event is eligible only once `inner` has considered it eligible for at
+/// least `delay` of wall-clock time, e.g. to leave a window for manual intervention. The clock
+/// starts at the first drain where `inner` reports it eligible and restarts if `inner` stops
+/// doing so (e.g. after a reorg). Time is read from [FinalityContext::now_unix], so it is
+/// measured in whole seconds and follows [Finalizer::with_clock].
+pub struct Debounced {
+    inner: Box<dyn Strategy>,
+    delay: Duration,
+    /// Keyed by `(tx_hash, log_index)`.
+    first_eligible: Mutex<HashMap<([u8; 32], u64), DebounceEntry>>,
+}
+
+struct DebounceEntry {
+    /// First seen `inner`-eligible (Unix seconds).
+    since: u64,
+    checked: u64,
+}
+
+impl Debounced {
+    pub fn new(inner: impl Strategy + 'static, delay: Duration) -> Self {
+        Self {
+            inner: Box::new(inner),
+            delay,
+            first_eligible: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Strategy for Debounced {
+    fn is_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        let key = (ev.tx_hash, ev.log_index);
+        let mut first_eligible = self.first_eligible.lock().unwrap();
+        if !self.inner.is_eligible(ev, ctx) {
+            first_eligible.remove(&key);
+            return false;
+        }
+        let now = ctx.now_unix;
+        if !first_eligible.contains_key(&key) {
+            let forget_after = self.delay.max(DEBOUNCE_FORGET_AFTER).as_secs();
+            first_eligible.retain(|_, e| now.saturating_sub(e.checked) < forget_after);
+        }
+        let entry = first_eligible.entry(key).or_insert(DebounceEntry { since: now, checked: now });
+        entry.checked = now;
+        now.saturating_sub(entry.since) >= self.delay.as_secs()
+    }
+
+    fn peek_eligible(&self, ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+        if !self.inner.peek_eligible(ev, ctx) {
+            return false;
+        }
+        let first_eligible = self.first_eligible.lock().unwrap();
+        let since = first_eligible.get(&(ev.tx_hash, ev.log_index)).map_or(ctx.now_unix, |e| e.since);
+        ctx.now_unix.saturating_sub(since) >= self.delay.as_secs()
+    }
+
+    fn needs_finalized_block(&self) -> bool {
+        self.inner.needs_finalized_block()
+    }
+
+    fn needs_safe_block(&self) -> bool {
+        self.inner.needs_safe_block()
+    }
+
+    fn required_confirmations(&self, ev: &HeadUpdatedObserved) -> Option<u64> {
+        self.inner.required_confirmations(ev)
+    }
+}
+
+/// Where one pending event stands, from [Finalizer::pending_status].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingStatus {
+    pub seq: u64,
+    pub block_number: u64,
+    /// Blocks on top of the event's: `tip - block_number`.
+    pub confirmations: u64,
+    /// Confirmations the strategy requires, for depth-based strategies ([ConfirmationDepth],
+    /// [PerWriterDepth]); `None` for others.
+    pub required: Option<u64>,
+    /// Whether the strategy would release the event at `tip` (before the `head()` cross-check).
+    pub eligible: bool,
 }
 
 /// One finalized event, ready for JSON output.
@@ -33,6 +302,8 @@ pub struct FinalizedEvent {
     pub seq: u64,
     /// Raw head bytes from the event (used to build Epoch.head).
     pub cid: Vec<u8>,
+    /// `cid` as a CID string, or hex if it doesn't parse as one (see [cid_string]).
+    pub cid_str: String,
     #[serde(rename = "cid_hash")]
     pub cid_hash_hex: String,
     /// Block that satisfied eligibility; use as Epoch.adopted_block.
@@ -41,6 +312,9 @@ pub struct FinalizedEvent {
     pub tx_hash_hex: String,
     pub log_index: u64,
     pub writer: String,
+    /// [crate::IndexerConfig::source_label] of the indexer that observed the event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_label: Option<String>,
 }
 
 impl FinalizedEvent {
@@ -48,74 +322,189 @@ impl FinalizedEvent {
         Self {
             seq: ev.seq,
             cid: ev.cid.clone(),
+            cid_str: cid_string(&ev.cid),
             cid_hash_hex: hex::encode(ev.cid_hash),
             block_number: ev.block_number,
             tx_hash_hex: hex::encode(ev.tx_hash),
             log_index: ev.log_index,
             writer: hex::encode(ev.writer),
+            source_label: ev.source_label.clone(),
         }
     }
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum FinalizerError {
+    /// Transport failure; the underlying `reqwest::Error` is stringified so the enum stays `Clone`.
     #[error("HTTP request failed: {0}")]
-    Http(#[from] reqwest::Error),
-    #[error("RPC error: {0}")]
-    Rpc(String),
+    Http(String),
+    /// JSON-RPC error object (`code` per the JSON-RPC spec, e.g. -32601 method not found).
+    #[error("RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
     #[error("decode error: {0}")]
     Decode(String),
+    /// An RPC call got no response within the builder's `request_timeout`.
+    #[error("request timed out: {0}")]
+    Timeout(String),
+    /// A [FinalizedSink] failed to publish (stringified so the enum stays `Clone`).
+    #[error("sink error: {0}")]
+    Sink(String),
+    /// [Finalizer::feed] rejected an event: the pending buffer holds `max_pending` events and
+    /// the overflow policy is [OverflowPolicy::Error].
+    #[error("pending buffer full ({0} events)")]
+    PendingFull(usize),
+    /// A block an already-emitted event was finalized in is no longer canonical; `depth` is how
+    /// far below the tip it was. Raised only with [FinalizerBuilder::detect_deep_reorgs].
+    #[error("reorg replaced a finalized block {depth} blocks below tip")]
+    DeepReorg { depth: u64 },
+    /// [assert_same_chain]: the indexer's and the finalizer's RPCs serve different chains.
+    #[error("indexer is on chain {indexer} but finalizer is on chain {finalizer}")]
+    ChainMismatch { indexer: u64, finalizer: u64 },
+    /// `head()` reverted with this reason (e.g. the contract isn't initialized).
+    #[error("head() reverted: {0}")]
+    Reverted(String),
+}
+
+impl From<reqwest::Error> for FinalizerError {
+    fn from(e: reqwest::Error) -> Self {
+        FinalizerError::Http(e.to_string())
+    }
+}
+
+impl From<RpcError> for FinalizerError {
+    fn from(e: RpcError) -> Self {
+        match e {
+            RpcError::Transport(msg) => FinalizerError::Http(msg),
+            RpcError::Rpc { code, message } => FinalizerError::Rpc { code, message },
+            RpcError::Decode(msg) => FinalizerError::Decode(msg),
+            RpcError::Timeout(msg) => FinalizerError::Timeout(msg),
+        }
+    }
+}
+
+impl FinalizerError {
+    /// Worth retrying: transport failures, timeouts and JSON-RPC internal / limit-exceeded errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            FinalizerError::Http(_) | FinalizerError::Timeout(_) => true,
+            FinalizerError::Rpc { code, .. } => matches!(code, -32603 | -32005),
+            FinalizerError::Decode(_)
+            | FinalizerError::Sink(_)
+            | FinalizerError::PendingFull(_)
+            | FinalizerError::DeepReorg { .. }
+            | FinalizerError::ChainMismatch { .. }
+            | FinalizerError::Reverted(_) => false,
+        }
+    }
+}
+
+/// Run `op` until it succeeds, fails with a non-transient error, or `policy.max_attempts` is reached.
+/// Sleeps `policy.backoff(n)` plus up to 25% jitter between attempts.
+async fn with_retry<T, F, Fut>(policy: &RetryConfig, mut op: F) -> Result<T, FinalizerError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, FinalizerError>>,
+{
+    let mut attempt = 0u32;
+    loop {
+        match op().await {
+            Ok(v) => return Ok(v),
+            Err(e) if e.is_transient() && attempt + 1 < policy.max_attempts => {
+                let base = policy.backoff(attempt);
+                let jitter_ms = rand::thread_rng().gen_range(0..=base.as_millis() as u64 / 4);
+                tracing::debug!(%e, attempt, "finalizer RPC failed, retrying");
+                tokio::time::sleep(base + std::time::Duration::from_millis(jitter_ms)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// What the finalizer treats as "the same event" when suppressing repeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupKey {
+    /// `(tx_hash, log_index)`: each log is emitted at most once (default).
+    #[default]
+    TxLog,
+    /// `seq`: at most one event per head sequence number. Also suppresses a different cid
+    /// reusing an already-emitted seq, which a correct contract never produces.
+    Seq,
+    /// `cid_hash`: at most one event per head content; re-publishing an earlier cid under a
+    /// new seq is not emitted again.
+    CidHash,
 }
 
-fn dedup_key(ev: &HeadUpdatedObserved) -> String {
-    format!("{}:{}", hex::encode(ev.tx_hash), ev.log_index)
-}
-
-async fn http_json_rpc(
-    client: &reqwest::Client,
-    url: &str,
-    method: &str,
-    params: serde_json::Value,
-    id: u64,
-) -> Result<serde_json::Value, FinalizerError> {
-    let body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": id,
-        "method": method,
-        "params": params
-    });
-    let resp = client.post(url).json(&body).send().await?;
-    let json: serde_json::Value = resp.json().await?;
-    if let Some(err) = json.get("error") {
-        return Err(FinalizerError::Rpc(err.to_string()));
-    }
-    let result = json
-        .get("result")
-        .cloned()
-        .ok_or_else(|| FinalizerError::Decode("Missing result".into()))?;
-    Ok(result)
-}
-
-async fn eth_block_number(client: &reqwest::Client, http_url: &str) -> Result<u64, FinalizerError> {
-    let result = http_json_rpc(client, http_url, "eth_blockNumber", serde_json::json!([]), 1).await?;
+impl DedupKey {
+    fn key(self, ev: &HeadUpdatedObserved) -> String {
+        match self {
+            DedupKey::TxLog => format!("{}:{}", hex::encode(ev.tx_hash), ev.log_index),
+            DedupKey::Seq => ev.seq.to_string(),
+            DedupKey::CidHash => hex::encode(ev.cid_hash),
+        }
+    }
+}
+
+async fn eth_block_number(client: &dyn RpcClient) -> Result<u64, FinalizerError> {
+    eth_quantity(client, "eth_blockNumber").await
+}
+
+async fn eth_chain_id(client: &dyn RpcClient) -> Result<u64, FinalizerError> {
+    eth_quantity(client, "eth_chainId").await
+}
+
+/// Call a parameterless `method` that returns a hex quantity.
+async fn eth_quantity(client: &dyn RpcClient, method: &str) -> Result<u64, FinalizerError> {
+    let result = client.call(method, serde_json::json!([])).await?;
     let s = result
         .as_str()
-        .ok_or_else(|| FinalizerError::Decode("blockNumber not string".into()))?;
+        .ok_or_else(|| FinalizerError::Decode(format!("{} result not string", method)))?;
     let s = s.strip_prefix("0x").unwrap_or(s);
     u64::from_str_radix(s, 16).map_err(|e| FinalizerError::Decode(e.to_string()))
 }
 
+/// Number of the block for `tag` (`"finalized"` or `"safe"`), or `None` if the node has none
+/// (e.g. pre-merge chains).
+async fn eth_tagged_block(client: &dyn RpcClient, tag: &str) -> Result<Option<u64>, FinalizerError> {
+    let block = client.call("eth_getBlockByNumber", serde_json::json!([tag, false])).await?;
+    if block.is_null() {
+        return Ok(None);
+    }
+    let s = block["number"]
+        .as_str()
+        .ok_or_else(|| FinalizerError::Decode("block number not string".into()))?;
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u64::from_str_radix(s, 16)
+        .map(Some)
+        .map_err(|e| FinalizerError::Decode(e.to_string()))
+}
+
+/// Hash of block `number`, or `None` if the node has no such block.
+async fn eth_block_hash(client: &dyn RpcClient, number: u64) -> Result<Option<String>, FinalizerError> {
+    let block = client
+        .call("eth_getBlockByNumber", serde_json::json!([format!("0x{:x}", number), false]))
+        .await?;
+    if block.is_null() {
+        return Ok(None);
+    }
+    block["hash"]
+        .as_str()
+        .map(|h| Some(h.to_string()))
+        .ok_or_else(|| FinalizerError::Decode("block hash not string".into()))
+}
+
+/// `eth_call` against `block` (a tag such as `"latest"` or a hex block number).
 async fn eth_call(
-    client: &reqwest::Client,
-    http_url: &str,
+    client: &dyn RpcClient,
     to: &[u8; 20],
     calldata: &[u8],
+    block: &str,
 ) -> Result<Vec<u8>, FinalizerError> {
     let params = serde_json::json!([{
         "to": format!("0x{}", hex::encode(to)),
         "data": format!("0x{}", hex::encode(calldata)),
-    }, "latest"]);
-    let result = http_json_rpc(client, http_url, "eth_call", params, 3).await?;
+    }, block]);
+    let result = client.call("eth_call", params).await?;
     let s = result
         .as_str()
         .ok_or_else(|| FinalizerError::Decode("eth_call result not string".into()))?;
@@ -124,11 +513,44 @@ async fn eth_call(
     Ok(bytes)
 }
 
+/// What [Finalizer::feed] does when the pending buffer is at `max_pending`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Evict the oldest pending event by `(block_number, log_index)`.
+    DropOldest,
+    /// Reject the new event with [FinalizerError::PendingFull].
+    Error,
+}
+
+/// Async check on a candidate's `cid`, run after the canonical cross-check passes
+/// (e.g. fetch the referenced root node and validate it).
+pub type RootValidator = Box<
+    dyn Fn(&[u8]) -> BoxFuture<'static, Result<(), Box<dyn std::error::Error + Send + Sync>>>
+        + Send
+        + Sync,
+>;
+
+/// Default number of blocks an event may be ahead of the tip before the finalizer treats it as
+/// bogus (see [FinalizerBuilder::future_block_slack]).
+pub const DEFAULT_FUTURE_BLOCK_SLACK: u64 = 64;
+
 /// Builder for the finalizer.
 pub struct FinalizerBuilder {
     strategy: Option<Box<dyn Strategy + Send>>,
     http_url: Option<String>,
+    rpc: Option<Arc<dyn RpcClient>>,
+    http_client: Option<reqwest::Client>,
     contract_address: Option<[u8; 20]>,
+    retry: RetryConfig,
+    root_validator: Option<RootValidator>,
+    allowed_cid_codecs: Option<Vec<u64>>,
+    allow_unparseable_cids: bool,
+    dedup: DedupKey,
+    max_pending: Option<(usize, OverflowPolicy)>,
+    reorg_window: Option<u64>,
+    tip_ttl: Duration,
+    request_timeout: Duration,
+    future_block_slack: u64,
 }
 
 impl FinalizerBuilder {
@@ -136,7 +558,19 @@ impl FinalizerBuilder {
         Self {
             strategy: None,
             http_url: None,
+            rpc: None,
+            http_client: None,
             contract_address: None,
+            retry: RetryConfig::default(),
+            root_validator: None,
+            allowed_cid_codecs: None,
+            allow_unparseable_cids: false,
+            dedup: DedupKey::default(),
+            max_pending: None,
+            reorg_window: None,
+            tip_ttl: Duration::ZERO,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            future_block_slack: DEFAULT_FUTURE_BLOCK_SLACK,
         }
     }
 
@@ -152,37 +586,153 @@ impl FinalizerBuilder {
         self
     }
 
+    /// Convenience: equivalent to `.strategy(PerWriterDepth::new(depths, default))`.
+    pub fn per_writer_depth(mut self, depths: HashMap<[u8; 20], u64>, default: u64) -> Self {
+        self.strategy = Some(Box::new(PerWriterDepth::new(depths, default)));
+        self
+    }
+
     pub fn http_url(mut self, url: impl Into<String>) -> Self {
         self.http_url = Some(url.into());
         self
     }
 
+    /// Send JSON-RPC through `rpc` instead of an HTTP client for `http_url` (which is then not
+    /// required), e.g. `MockRpcClient` from the `test-util` feature.
+    pub fn rpc_client(mut self, rpc: Arc<dyn RpcClient>) -> Self {
+        self.rpc = Some(rpc);
+        self
+    }
+
+    /// Call `http_url` through `client` (custom TLS roots, proxy, a shared connection pool)
+    /// instead of building one; [Self::request_timeout] is then ignored in favour of the
+    /// client's own timeouts.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
     pub fn contract_address(mut self, addr: [u8; 20]) -> Self {
         self.contract_address = Some(addr);
         self
     }
 
+    /// Retry policy for `eth_blockNumber` / `eth_call` (default: [RetryConfig::default]).
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Validate each candidate's `cid` before emitting it. Candidates that fail are held in
+    /// the pending buffer (and logged) and re-checked on the next drain.
+    pub fn with_root_validator<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[u8]) -> BoxFuture<'static, Result<(), Box<dyn std::error::Error + Send + Sync>>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.root_validator = Some(Box::new(f));
+        self
+    }
+
+    /// Only emit events whose `cid` is a CID with one of these multicodecs (e.g. `0x71`
+    /// dag-cbor, `0x55` raw). Others fail after the canonical cross-check and are held in the
+    /// pending buffer with a warning, like a root-validator rejection. Cids that don't parse as
+    /// CIDs are held too unless [Self::allow_unparseable_cids] is set.
+    pub fn allowed_cid_codecs(mut self, codecs: &[u64]) -> Self {
+        self.allowed_cid_codecs = Some(codecs.to_vec());
+        self
+    }
+
+    /// With [Self::allowed_cid_codecs], whether a `cid` that isn't a CID may be emitted
+    /// (default false).
+    pub fn allow_unparseable_cids(mut self, allow: bool) -> Self {
+        self.allow_unparseable_cids = allow;
+        self
+    }
+
+    /// Key used to suppress repeated events (default [DedupKey::TxLog]).
+    pub fn dedup_by(mut self, key: DedupKey) -> Self {
+        self.dedup = key;
+        self
+    }
+
+    /// Bound the pending buffer to `max` events, applying `policy` on overflow (default: unbounded).
+    pub fn max_pending(mut self, max: usize, policy: OverflowPolicy) -> Self {
+        self.max_pending = Some((max, policy));
+        self
+    }
+
+    /// Record the canonical block hash of every emitted event and, on each drain, re-check those
+    /// within `window` blocks of the tip; a replaced block fails the drain with
+    /// [FinalizerError::DeepReorg]. Costs one `eth_getBlockByNumber` per emitted event and per
+    /// tracked block each drain.
+    pub fn detect_deep_reorgs(mut self, window: u64) -> Self {
+        self.reorg_window = Some(window);
+        self
+    }
+
+    /// Reuse the tip from [Finalizer::current_tip] for `ttl` before asking the node again
+    /// (default: zero, i.e. every call hits the RPC).
+    pub fn tip_ttl(mut self, ttl: Duration) -> Self {
+        self.tip_ttl = ttl;
+        self
+    }
+
+    /// Drop (with a warning) pending events more than `slack` blocks past the tip on each drain:
+    /// no real log can be that far ahead, and such an event would otherwise never become
+    /// eligible (default [DEFAULT_FUTURE_BLOCK_SLACK]). The slack covers the indexer's tip
+    /// running ahead of the finalizer's.
+    pub fn future_block_slack(mut self, slack: u64) -> Self {
+        self.future_block_slack = slack;
+        self
+    }
+
+    /// Fail any RPC call to `http_url` not answered within `timeout` with
+    /// [FinalizerError::Timeout] (default [DEFAULT_REQUEST_TIMEOUT]; `Duration::ZERO` disables).
+    /// Ignored with [Self::rpc_client].
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
     pub fn build(self) -> Result<Finalizer, FinalizerError> {
         let strategy = self
             .strategy
             .unwrap_or_else(|| Box::new(ConfirmationDepth(6)));
-        let http_url = self
-            .http_url
-            .ok_or_else(|| FinalizerError::Decode("http_url required".into()))?;
+        let rpc = match (self.rpc, self.http_url) {
+            (Some(rpc), _) => rpc,
+            (None, Some(url)) => match self.http_client {
+                Some(http) => Arc::new(HttpRpcClient::with_client(http, url)),
+                None => Arc::new(HttpRpcClient::with_timeout(url, self.request_timeout)),
+            },
+            (None, None) => return Err(FinalizerError::Decode("http_url required".into())),
+        };
         let contract_address = self
             .contract_address
             .ok_or_else(|| FinalizerError::Decode("contract_address required".into()))?;
-        let http_client = reqwest::Client::builder()
-            .no_proxy()
-            .build()
-            .map_err(|e| FinalizerError::Decode(e.to_string()))?;
         Ok(Finalizer {
             strategy,
-            http_client,
-            http_url,
+            rpc,
             contract_address,
-            pending: Vec::new(),
+            retry: self.retry,
+            root_validator: self.root_validator,
+            allowed_cid_codecs: self.allowed_cid_codecs,
+            allow_unparseable_cids: self.allow_unparseable_cids,
+            pending: BTreeMap::new(),
+            max_pending: self.max_pending,
+            dedup: self.dedup,
             emitted: HashSet::new(),
+            reorg_window: self.reorg_window,
+            emitted_blocks: BTreeMap::new(),
+            tip_ttl: self.tip_ttl,
+            tip_cache: Mutex::new(None),
+            future_block_slack: self.future_block_slack,
+            clock: Box::new(SystemTime::now),
+            tip_source: None,
+            cross_check_drops: 0,
+            last_finalized: None,
         })
     }
 }
@@ -193,65 +743,1823 @@ impl Default for FinalizerBuilder {
     }
 }
 
+/// Buffer size of the channel returned by [Finalizer::spawn].
+const FINALIZED_CHANNEL_CAPACITY: usize = 256;
+
+/// Forwards finalized events to [Finalizer::spawn]'s channel.
+struct ChannelSink(mpsc::Sender<FinalizedEvent>);
+
+impl FinalizedSink for ChannelSink {
+    fn publish<'a>(&'a self, ev: &'a FinalizedEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            self.0.send(ev.clone()).await?;
+            Ok(())
+        })
+    }
+}
+
+/// An observed event waiting in [Finalizer]'s pending buffer.
+#[derive(Clone)]
+struct PendingEvent {
+    ev: HeadUpdatedObserved,
+    /// When the event was first fed (for [Finalizer::oldest_pending_age]).
+    fed_at: Instant,
+}
+
+/// Outcome of [Finalizer::cross_check] for one candidate.
+enum CrossCheck {
+    /// Canonical and valid; carries its block hash when deep-reorg detection is on.
+    Emit(Option<String>),
+    /// Canonical but not (yet) acceptable; back to pending.
+    Hold,
+    /// Not the on-chain head.
+    Drop,
+}
+
 /// Finalizer: consumes observed events, outputs only eligible and canonical-finalized events.
 pub struct Finalizer {
     strategy: Box<dyn Strategy + Send>,
-    http_client: reqwest::Client,
-    http_url: String,
+    rpc: Arc<dyn RpcClient>,
     contract_address: [u8; 20],
-    pending: Vec<HeadUpdatedObserved>,
+    retry: RetryConfig,
+    root_validator: Option<RootValidator>,
+    allowed_cid_codecs: Option<Vec<u64>>,
+    allow_unparseable_cids: bool,
+    /// Keyed by `(block_number, log_index)`, so iteration is in chain order.
+    pending: BTreeMap<(u64, u64), PendingEvent>,
+    max_pending: Option<(usize, OverflowPolicy)>,
+    dedup: DedupKey,
     emitted: HashSet<String>,
+    reorg_window: Option<u64>,
+    /// Canonical hash of each block an event was emitted from (only with `reorg_window`).
+    emitted_blocks: BTreeMap<u64, String>,
+    tip_ttl: Duration,
+    /// Last tip and when it was fetched (per `clock`).
+    tip_cache: Mutex<Option<(u64, SystemTime)>>,
+    future_block_slack: u64,
+    /// Time source for the tip cache and [FinalityContext::now_unix] (see [Self::with_clock]).
+    clock: Box<dyn Fn() -> SystemTime + Send + Sync>,
+    /// Replaces `eth_blockNumber` in [Self::current_tip] (see [Self::with_tip_source]).
+    tip_source: Option<TipSource>,
+    /// Eligible events dropped because `head()` didn't match them.
+    cross_check_drops: u64,
+    /// Highest-seq event emitted so far.
+    last_finalized: Option<FinalizedEvent>,
 }
 
 impl Finalizer {
-    /// Push an observed event into the pending buffer (sorted by block_number, log_index).
-    pub fn feed(&mut self, ev: HeadUpdatedObserved) {
-        self.pending.push(ev);
+    /// Push an observed event into the pending buffer (ordered by block_number, log_index).
+    /// With `max_pending` set, a full buffer either evicts its oldest event or rejects `ev`
+    /// with [FinalizerError::PendingFull], per the [OverflowPolicy].
+    pub fn feed(&mut self, ev: HeadUpdatedObserved) -> Result<(), FinalizerError> {
+        let key = (ev.block_number, ev.log_index);
+        if let Some((max, policy)) = self.max_pending {
+            if self.pending.len() >= max && !self.pending.contains_key(&key) {
+                match policy {
+                    OverflowPolicy::Error => return Err(FinalizerError::PendingFull(max)),
+                    OverflowPolicy::DropOldest => {
+                        self.insert_pending(ev);
+                        if let Some((_, dropped)) = self.pending.pop_first() {
+                            tracing::warn!(seq = dropped.ev.seq, block = dropped.ev.block_number, "pending buffer full, dropping oldest");
+                        }
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        self.insert_pending(ev);
+        Ok(())
+    }
+
+    /// Insert `ev`, keeping the original `fed_at` if the same log is already pending.
+    fn insert_pending(&mut self, ev: HeadUpdatedObserved) {
+        let fed_at = Instant::now();
+        self.pending
+            .entry((ev.block_number, ev.log_index))
+            .and_modify(|p| p.ev = ev.clone())
+            .or_insert(PendingEvent { ev, fed_at });
+    }
+
+    /// Number of events waiting for eligibility.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Confirmation progress of every pending event at `tip`, in chain order, e.g. "3 of 6" for
+    /// a dashboard. Eligibility is judged with [Strategy::peek_eligible] (so a status call never
+    /// changes when events finalize) on [FinalityContext::at_tip], so strategies needing the
+    /// finalized or safe block see none.
+    pub fn pending_status(&self, tip: u64) -> Vec<PendingStatus> {
+        let ctx = self.context_at(tip);
         self.pending
-            .sort_by_key(|o| (o.block_number, o.log_index));
+            .values()
+            .map(|p| PendingStatus {
+                seq: p.ev.seq,
+                block_number: p.ev.block_number,
+                confirmations: tip.saturating_sub(p.ev.block_number),
+                required: self.strategy.required_confirmations(&p.ev),
+                eligible: self.strategy.peek_eligible(&p.ev, &ctx),
+            })
+            .collect()
     }
 
-    /// Return the current chain tip (latest block number) via JSON-RPC.
+    /// Block number of the oldest pending event, if any.
+    pub fn oldest_pending_block(&self) -> Option<u64> {
+        self.pending.first_key_value().map(|((block, _), _)| *block)
+    }
+
+    /// How long ago the oldest pending event (by chain position) was fed. Observed events
+    /// carry no block timestamp, so this measures local wait time, not block age.
+    pub fn oldest_pending_age(&self) -> Option<Duration> {
+        self.pending.first_key_value().map(|(_, p)| p.fed_at.elapsed())
+    }
+
+    /// Eligible events dropped so far because `head()` no longer matched their `(seq, cid)`
+    /// (reorged out or superseded before finalizing). A high rate suggests a deeper
+    /// confirmation depth. Cleared by [Self::reset].
+    pub fn cross_check_drops(&self) -> u64 {
+        self.cross_check_drops
+    }
+
+    /// The highest-seq event emitted so far, i.e. the latest finalized head. Cleared by
+    /// [Self::reset] (but not [Self::reset_emitted_only]).
+    pub fn last_finalized(&self) -> Option<FinalizedEvent> {
+        self.last_finalized.clone()
+    }
+
+    /// Drop all pending events and forget everything emitted (dedup keys, tracked blocks,
+    /// [Self::last_finalized], [Self::cross_check_drops] and the cached tip), keeping the
+    /// configuration and RPC client. Events fed afterwards, including ones already emitted, are
+    /// finalized and emitted again; blocks emitted before the reset are no longer checked for
+    /// deep reorgs.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.reset_emitted_only();
+        self.last_finalized = None;
+        self.cross_check_drops = 0;
+        *self.tip_cache.lock().unwrap() = None;
+    }
+
+    /// Forget emitted events (dedup keys and deep-reorg tracking) but keep the pending buffer,
+    /// so already-finalized events can be emitted again without re-feeding. As with
+    /// [Self::reset], a reorg of blocks emitted before the call goes undetected.
+    pub fn reset_emitted_only(&mut self) {
+        self.emitted.clear();
+        self.emitted_blocks.clear();
+    }
+
+    /// Chain id (`eth_chainId`) of the RPC the finalizer cross-checks against.
+    pub async fn chain_id(&self) -> Result<u64, FinalizerError> {
+        with_retry(&self.retry, || eth_chain_id(self.rpc.as_ref())).await
+    }
+
+    /// Return the current chain tip (latest block number) via JSON-RPC, retrying transient failures.
+    /// With [FinalizerBuilder::tip_ttl], a tip fetched less than `ttl` ago is reused.
     pub async fn current_tip(&self) -> Result<u64, FinalizerError> {
-        eth_block_number(&self.http_client, &self.http_url).await
+        if !self.tip_ttl.is_zero() {
+            let cached = *self.tip_cache.lock().unwrap();
+            if let Some((tip, fetched_at)) = cached {
+                // A clock that stepped backwards counts as expired.
+                let age = (self.clock)().duration_since(fetched_at).unwrap_or(Duration::MAX);
+                if age < self.tip_ttl {
+                    return Ok(tip);
+                }
+            }
+        }
+        let tip = match &self.tip_source {
+            Some(source) => source().await?,
+            None => with_retry(&self.retry, || eth_block_number(self.rpc.as_ref())).await?,
+        };
+        *self.tip_cache.lock().unwrap() = Some((tip, (self.clock)()));
+        Ok(tip)
+    }
+
+    /// [FinalityContext::at_tip], stamped by [Self::with_clock]'s clock.
+    fn context_at(&self, tip: u64) -> FinalityContext {
+        FinalityContext {
+            now_unix: (self.clock)()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            ..FinalityContext::at_tip(tip)
+        }
+    }
+
+    /// Read the time from `clock` instead of the system clock: it stamps
+    /// [FinalityContext::now_unix] (and so drives time-based strategies such as [Debounced]) and
+    /// ages the [FinalizerBuilder::tip_ttl] cache, so both can be tested deterministically.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn with_clock(mut self, clock: impl Fn() -> SystemTime + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Take the tip for [Self::current_tip] from `source` instead of `eth_blockNumber`, so tests
+    /// can drive the chain height without a node.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn with_tip_source<F>(mut self, source: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Result<u64, FinalizerError>> + Send + Sync + 'static,
+    {
+        self.tip_source = Some(Box::new(source));
+        self
+    }
+
+    /// Build the [FinalityContext] for `tip`, fetching the finalized and safe blocks if the
+    /// strategy needs them.
+    pub async fn finality_context(&self, tip: u64) -> Result<FinalityContext, FinalizerError> {
+        let mut ctx = self.context_at(tip);
+        if self.strategy.needs_finalized_block() {
+            ctx.finalized_block =
+                with_retry(&self.retry, || eth_tagged_block(self.rpc.as_ref(), "finalized")).await?;
+        }
+        if self.strategy.needs_safe_block() {
+            ctx.safe_block =
+                with_retry(&self.retry, || eth_tagged_block(self.rpc.as_ref(), "safe")).await?;
+        }
+        Ok(ctx)
     }
 
     /// Drain events that are eligible per strategy and pass the canonical cross-check.
-    /// Eligibility is checked with `strategy.is_eligible(ev, tip)`; then we call `Stem.head()`
-    /// and only emit if (seq, cid) matches the candidate. Dedup by (tx_hash, log_index).
+    /// Equivalent to [Self::drain_eligible_with] on [Self::finality_context] for `tip`.
     pub async fn drain_eligible(&mut self, tip: u64) -> Result<Vec<FinalizedEvent>, FinalizerError> {
-        // Collect eligible in order (block_number, log_index), then remove them from pending.
-        let mut eligible: Vec<HeadUpdatedObserved> = self
+        let ctx = self.finality_context(tip).await?;
+        self.drain_eligible_with(&ctx).await
+    }
+
+    /// Drain events that are eligible per strategy and pass the canonical cross-check.
+    /// Eligibility is checked with `strategy.is_eligible(ev, ctx)`; then we call `Stem.head()`
+    /// and only emit if (seq, cid) matches the candidate (cids compared with [cid_equivalent]).
+    /// Dedup per the configured [DedupKey] (default `(tx_hash, log_index)`).
+    /// With a root validator configured, matching candidates it rejects stay pending. Events
+    /// further past `ctx.tip` than [FinalizerBuilder::future_block_slack] are dropped.
+    /// Candidates not yet decided when an RPC call fails stay pending for the next drain; after
+    /// a transient failure the events accepted before it are still returned, after any other
+    /// failure none are.
+    pub async fn drain_eligible_with(
+        &mut self,
+        ctx: &FinalityContext,
+    ) -> Result<Vec<FinalizedEvent>, FinalizerError> {
+        self.check_emitted_blocks(ctx.tip).await?;
+        let limit = ctx.tip.saturating_add(self.future_block_slack);
+        self.pending.retain(|_, p| {
+            let plausible = p.ev.block_number <= limit;
+            if !plausible {
+                tracing::warn!(seq = p.ev.seq, block = p.ev.block_number, tip = ctx.tip, "dropping event from beyond the tip");
+            }
+            plausible
+        });
+        // Ask the strategy once per event (it may be stateful, e.g. [Debounced]), then remove
+        // exactly those keys; iteration is in (block_number, log_index) order.
+        let keys: Vec<(u64, u64)> = self
             .pending
             .iter()
-            .filter(|ev| self.strategy.is_eligible(ev, tip))
-            .cloned()
+            .filter(|(_, p)| self.strategy.is_eligible(&p.ev, ctx))
+            .map(|(key, _)| *key)
             .collect();
-        eligible.sort_by_key(|o| (o.block_number, o.log_index));
-        self.pending
-            .retain(|ev| !self.strategy.is_eligible(ev, tip));
+        let eligible = keys.iter().filter_map(|key| self.pending.remove(key)).collect();
+        self.emit_canonical(eligible).await
+    }
 
-        let mut out = Vec::new();
-        for ev in eligible {
-            let key = dedup_key(&ev);
-            if self.emitted.contains(&key) {
+    /// Drain every pending event regardless of the strategy (forced finalization, e.g. for
+    /// emergency catch-up or tests). The `head()` cross-check, root validation and dedup still
+    /// apply, as does the deep-reorg check (against [Self::current_tip]) when enabled.
+    pub async fn drain_all(&mut self) -> Result<Vec<FinalizedEvent>, FinalizerError> {
+        if self.reorg_window.is_some() {
+            let tip = self.current_tip().await?;
+            self.check_emitted_blocks(tip).await?;
+        }
+        let candidates = std::mem::take(&mut self.pending).into_values().collect();
+        self.emit_canonical(candidates).await
+    }
+
+    /// Cross-check `candidates` (already removed from pending, in chain order) against
+    /// `Stem.head()` and emit the matches; root-validation failures go back to pending.
+    ///
+    /// If an RPC call fails, the failing candidate and every later one go back to pending too.
+    /// For a transient failure the events accepted before it are still emitted (the error is
+    /// logged, the rest retried on the next drain). Otherwise, or if none were accepted, nothing
+    /// is emitted: every candidate goes back to pending and the error is returned.
+    async fn emit_canonical(
+        &mut self,
+        candidates: Vec<PendingEvent>,
+    ) -> Result<Vec<FinalizedEvent>, FinalizerError> {
+        let mut accepted: Vec<(PendingEvent, Option<String>)> = Vec::new();
+        let mut accepted_keys = HashSet::new();
+        let mut held = Vec::new();
+        let mut candidates = candidates.into_iter();
+        let mut failure = None;
+        while let Some(pending) = candidates.next() {
+            let key = self.dedup.key(&pending.ev);
+            if self.emitted.contains(&key) || accepted_keys.contains(&key) {
                 continue;
             }
-            let head_bytes = eth_call(
-                &self.http_client,
-                &self.http_url,
+            match self.cross_check(&pending.ev).await {
+                Ok(CrossCheck::Emit(block_hash)) => {
+                    accepted_keys.insert(key);
+                    accepted.push((pending, block_hash));
+                }
+                Ok(CrossCheck::Hold) => held.push(pending),
+                Ok(CrossCheck::Drop) => self.cross_check_drops += 1,
+                Err(e) => {
+                    held.push(pending);
+                    held.extend(candidates.by_ref());
+                    failure = Some(e);
+                }
+            }
+        }
+        let failure = match failure {
+            Some(e) if accepted.is_empty() || !e.is_transient() => {
+                held.extend(accepted.into_iter().map(|(p, _)| p));
+                self.restore_pending(held);
+                return Err(e);
+            }
+            failure => failure,
+        };
+        self.restore_pending(held);
+        let mut out = Vec::with_capacity(accepted.len());
+        for (pending, block_hash) in accepted {
+            let ev = &pending.ev;
+            if let Some(hash) = block_hash {
+                self.emitted_blocks.insert(ev.block_number, hash);
+            }
+            self.emitted.insert(self.dedup.key(ev));
+            let finalized = FinalizedEvent::from_observed(ev);
+            if self.last_finalized.as_ref().is_none_or(|last| finalized.seq >= last.seq) {
+                self.last_finalized = Some(finalized.clone());
+            }
+            out.push(finalized);
+        }
+        if let Some(e) = failure {
+            tracing::warn!(%e, emitted = out.len(), "cross-check failed mid-drain, retrying the rest later");
+        }
+        Ok(out)
+    }
+
+    /// Put events taken out by a drain back into pending. They were just removed, so this
+    /// cannot overflow.
+    fn restore_pending(&mut self, events: Vec<PendingEvent>) {
+        for p in events {
+            self.pending.insert((p.ev.block_number, p.ev.log_index), p);
+        }
+    }
+
+    /// Decide one candidate: `head()` cross-check, codec and root validation, then (with deep
+    /// reorg detection) the hash of its block. Commits nothing, so a failure loses no state.
+    async fn cross_check(&self, ev: &HeadUpdatedObserved) -> Result<CrossCheck, FinalizerError> {
+        let head_bytes = with_retry(&self.retry, || {
+            eth_call(
+                self.rpc.as_ref(),
                 &self.contract_address,
                 &HEAD_SELECTOR,
+                "latest",
             )
-            .await?;
-            let head = decode_head_return(&head_bytes)
-                .map_err(|e| FinalizerError::Decode(e.to_string()))?;
-            if head.seq == ev.seq && head.cid == ev.cid {
-                self.emitted.insert(key);
-                out.push(FinalizedEvent::from_observed(&ev));
+        })
+        .await?;
+        let head = decode_head_return(&head_bytes).map_err(|e| match e.downcast::<HeadReverted>() {
+            Ok(reverted) => FinalizerError::Reverted(reverted.reason),
+            Err(e) => FinalizerError::Decode(e.to_string()),
+        })?;
+        if head.seq != ev.seq || !cid_equivalent(&head.cid, &ev.cid) {
+            // Already dropped from pending, do not emit (reorg'd or superseded).
+            tracing::debug!(seq = ev.seq, head_seq = head.seq, block = ev.block_number, "dropping event: head() does not match");
+            return Ok(CrossCheck::Drop);
+        }
+        if !self.codec_allowed(&ev.cid) {
+            tracing::warn!(
+                seq = ev.seq,
+                cid = %cid_string(&ev.cid),
+                codec = ?cid_codec(&ev.cid),
+                "cid codec not allowed, holding event"
+            );
+            return Ok(CrossCheck::Hold);
+        }
+        if let Some(validate) = &self.root_validator {
+            if let Err(e) = validate(&ev.cid).await {
+                tracing::warn!(seq = ev.seq, reason = %e, "root validation failed, holding event");
+                return Ok(CrossCheck::Hold);
             }
-            // If mismatch: already dropped from pending, do not emit (reorg'd or superseded).
         }
-        Ok(out)
+        let block_hash = match self.reorg_window {
+            Some(_) => with_retry(&self.retry, || eth_block_hash(self.rpc.as_ref(), ev.block_number)).await?,
+            None => None,
+        };
+        Ok(CrossCheck::Emit(block_hash))
+    }
+
+    /// Whether `cid` passes [FinalizerBuilder::allowed_cid_codecs] (always, if unset).
+    fn codec_allowed(&self, cid: &[u8]) -> bool {
+        let Some(allowed) = &self.allowed_cid_codecs else {
+            return true;
+        };
+        match cid_codec(cid) {
+            Some(codec) => allowed.contains(&codec),
+            None => self.allow_unparseable_cids,
+        }
+    }
+
+    /// Forget emitted blocks that fell out of the reorg window, then verify the rest are still
+    /// canonical. No-op unless [FinalizerBuilder::detect_deep_reorgs] is set.
+    async fn check_emitted_blocks(&mut self, tip: u64) -> Result<(), FinalizerError> {
+        let Some(window) = self.reorg_window else {
+            return Ok(());
+        };
+        self.emitted_blocks = self.emitted_blocks.split_off(&tip.saturating_sub(window));
+        for (&number, stored) in &self.emitted_blocks {
+            let current = with_retry(&self.retry, || eth_block_hash(self.rpc.as_ref(), number)).await?;
+            if current.as_deref() != Some(stored.as_str()) {
+                let depth = tip.saturating_sub(number);
+                tracing::error!(block = number, depth, "finalized block replaced by reorg");
+                return Err(FinalizerError::DeepReorg { depth });
+            }
+        }
+        Ok(())
+    }
+
+    /// Drive the finalizer: feed events from `indexer_rx`, and every `poll_interval` drain
+    /// eligible events at the current tip and publish them to `sink` in order.
+    ///
+    /// RPC failures are logged and retried on the next tick. Returns `Ok` after a final drain
+    /// once the indexer channel closes, or the first [FinalizerError::Sink] or
+    /// [FinalizerError::DeepReorg] error.
+    pub async fn run_to_sink<S: FinalizedSink + ?Sized>(
+        &mut self,
+        mut indexer_rx: broadcast::Receiver<HeadUpdatedObserved>,
+        sink: &S,
+        poll_interval: Duration,
+    ) -> Result<(), FinalizerError> {
+        let mut tick = tokio::time::interval(poll_interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            let closed = tokio::select! {
+                ev = indexer_rx.recv() => match ev {
+                    Ok(ev) => {
+                        if let Err(e) = self.feed(ev) {
+                            tracing::warn!(%e, "dropping observed event");
+                        }
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!(skipped = n, "finalizer lagged behind indexer");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => true,
+                },
+                _ = tick.tick() => false,
+            };
+            match self.drain_to_sink(sink).await {
+                Err(e @ (FinalizerError::Sink(_) | FinalizerError::DeepReorg { .. })) => return Err(e),
+                Err(e) => tracing::warn!(%e, "finalizer drain failed"),
+                Ok(()) => {}
+            }
+            if closed {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Run [Self::run_to_sink] on a spawned task, delivering finalized events on the returned
+    /// channel. The task ends (with `Ok`) when `indexer_rx` closes or the receiver is dropped.
+    pub fn spawn(
+        mut self,
+        indexer_rx: broadcast::Receiver<HeadUpdatedObserved>,
+        poll_interval: Duration,
+    ) -> (JoinHandle<Result<(), FinalizerError>>, mpsc::Receiver<FinalizedEvent>) {
+        let (tx, rx) = mpsc::channel(FINALIZED_CHANNEL_CAPACITY);
+        let handle = tokio::spawn(async move {
+            match self.run_to_sink(indexer_rx, &ChannelSink(tx), poll_interval).await {
+                // Only the receiver going away fails a ChannelSink.
+                Err(FinalizerError::Sink(_)) => Ok(()),
+                res => res,
+            }
+        });
+        (handle, rx)
+    }
+
+    async fn drain_to_sink<S: FinalizedSink + ?Sized>(&mut self, sink: &S) -> Result<(), FinalizerError> {
+        let tip = self.current_tip().await?;
+        for ev in self.drain_eligible(tip).await? {
+            sink.publish(&ev)
+                .await
+                .map_err(|e| FinalizerError::Sink(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Fail with [FinalizerError::ChainMismatch] unless `indexer` and `finalizer` talk to the same
+/// chain. Call it at startup: with mismatched RPCs the `head()` cross-check never matches and
+/// nothing is ever finalized, silently.
+pub async fn assert_same_chain(indexer: &StemIndexer, finalizer: &Finalizer) -> anyhow::Result<()> {
+    let indexer_chain = indexer.chain_id().await.context("indexer eth_chainId")?;
+    let finalizer_chain = finalizer.chain_id().await.context("finalizer eth_chainId")?;
+    if indexer_chain != finalizer_chain {
+        return Err(FinalizerError::ChainMismatch { indexer: indexer_chain, finalizer: finalizer_chain }.into());
+    }
+    Ok(())
+}
+
+/// How often [wait_for_seq] re-checks the tip while no new events arrive.
+const WAIT_FOR_SEQ_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Drive `finalizer` from `indexer_rx` until an event with `seq == target_seq` is finalized,
+/// and return it. Drains after every fed event and on a short poll; other events finalized
+/// on the way are discarded. Fails with [FinalizerError::Timeout] once `timeout` elapses, or
+/// early on [FinalizerError::DeepReorg]; other drain errors are logged and retried, with the
+/// target kept pending.
+pub async fn wait_for_seq(
+    mut indexer_rx: broadcast::Receiver<HeadUpdatedObserved>,
+    finalizer: &mut Finalizer,
+    target_seq: u64,
+    timeout: Duration,
+) -> Result<FinalizedEvent, FinalizerError> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut tick = tokio::time::interval(WAIT_FOR_SEQ_POLL_INTERVAL);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut open = true;
+    loop {
+        tokio::select! {
+            ev = indexer_rx.recv(), if open => match ev {
+                Ok(ev) => {
+                    if let Err(e) = finalizer.feed(ev) {
+                        tracing::warn!(%e, "dropping observed event");
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(skipped = n, "finalizer lagged behind indexer");
+                }
+                // Pending events can still finalize as the tip advances.
+                Err(broadcast::error::RecvError::Closed) => open = false,
+            },
+            _ = tick.tick() => {}
+            _ = tokio::time::sleep_until(deadline) => {
+                return Err(FinalizerError::Timeout(format!(
+                    "seq {} not finalized within {:?}",
+                    target_seq, timeout
+                )));
+            }
+        }
+        let drained = async {
+            let tip = finalizer.current_tip().await?;
+            finalizer.drain_eligible(tip).await
+        };
+        match drained.await {
+            Ok(events) => {
+                if let Some(ev) = events.into_iter().find(|e| e.seq == target_seq) {
+                    return Ok(ev);
+                }
+            }
+            Err(e @ FinalizerError::DeepReorg { .. }) => return Err(e),
+            Err(e) => tracing::warn!(%e, "finalizer drain failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{head_return_hex, spawn_http_rpc, spawn_json_rpc, spawn_stalled_http, MockRpc};
+
+    /// [crate::rpc::rpc_result], as the finalizer surfaces it.
+    fn rpc_result(json: serde_json::Value) -> Result<serde_json::Value, FinalizerError> {
+        Ok(crate::rpc::rpc_result(json)?)
+    }
+
+    #[test]
+    fn rpc_error_is_structured() {
+        let json = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": -32000, "message": "execution reverted"}
+        });
+        let err = rpc_result(json).unwrap_err();
+        match err.clone() {
+            FinalizerError::Rpc { code, message } => {
+                assert_eq!(code, -32000);
+                assert_eq!(message, "execution reverted");
+            }
+            other => panic!("expected Rpc, got {other:?}"),
+        }
+        assert_eq!(err.to_string(), "RPC error -32000: execution reverted");
+    }
+
+    #[test]
+    fn rpc_error_without_message_keeps_raw_json() {
+        let json = serde_json::json!({"error": {"data": "0x"}});
+        match rpc_result(json).unwrap_err() {
+            FinalizerError::Rpc { code, message } => {
+                assert_eq!(code, 0);
+                assert_eq!(message, r#"{"data":"0x"}"#);
+            }
+            other => panic!("expected Rpc, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn current_tip_against_mock_rpc() {
+        let rpc = MockRpc::new();
+        rpc.push_error("eth_blockNumber", serde_json::json!({"code": -32603, "message": "internal error"}))
+            .push("eth_blockNumber", serde_json::json!("0x10"));
+        let f = FinalizerBuilder::new()
+            .http_url(rpc.serve().await)
+            .contract_address([0x11; 20])
+            .retry(fast_retry(3))
+            .build()
+            .unwrap();
+        assert_eq!(f.current_tip().await.unwrap(), 16);
+        let requests = rpc.requests_for("eth_blockNumber");
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]["id"].as_u64() < requests[1]["id"].as_u64());
+        assert_eq!(rpc.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn finalizer_runs_against_mock_rpc_client() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", serde_json::json!("0x10"))
+            .push("eth_call", serde_json::json!(head_return_hex(1, b"cid")));
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(6)
+            .rpc_client(Arc::new(rpc.clone()))
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        f.feed(at_block(10)).unwrap();
+        let tip = f.current_tip().await.unwrap();
+        assert_eq!(tip, 16);
+        assert_eq!(f.drain_eligible(tip).await.unwrap().len(), 1);
+        let methods: Vec<_> = rpc.requests().iter().map(|r| r["method"].clone()).collect();
+        assert_eq!(methods, ["eth_blockNumber", "eth_call"]);
+    }
+
+    #[tokio::test]
+    async fn failed_cross_check_keeps_remaining_candidates_pending() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_call", serde_json::json!(head_return_hex(1, b"cid-1")))
+            .push_error("eth_call", serde_json::json!({"code": -32603, "message": "upstream unavailable"}))
+            .push("eth_call", serde_json::json!(head_return_hex(2, b"cid-2")));
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .rpc_client(Arc::new(rpc))
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 5, ..observed(1, b"cid-1") }).unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 6, ..observed(2, b"cid-2") }).unwrap();
+
+        let first = f.drain_eligible_with(&FinalityContext::at_tip(10)).await.unwrap();
+        assert_eq!(first.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(f.pending_len(), 1);
+
+        let second = f.drain_eligible_with(&FinalityContext::at_tip(10)).await.unwrap();
+        assert_eq!(second.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(f.pending_len(), 0);
+        assert_eq!(f.last_finalized().map(|e| e.seq), Some(2));
+    }
+
+    async fn wait_for_seq_setup() -> (Finalizer, broadcast::Sender<HeadUpdatedObserved>) {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", serde_json::json!("0x10"))
+            .push("eth_call", serde_json::json!(head_return_hex(2, b"cid-2")));
+        let f = FinalizerBuilder::new()
+            .confirmation_depth(6)
+            .rpc_client(Arc::new(rpc))
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        (f, broadcast::channel(16).0)
+    }
+
+    #[tokio::test]
+    async fn non_transient_cross_check_failure_emits_nothing() {
+        use alloy::sol_types::SolType;
+        let mut revert = crate::abi::ERROR_SELECTOR.to_vec();
+        revert.extend(alloy::sol_types::sol_data::String::abi_encode("paused"));
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_call", serde_json::json!(head_return_hex(1, b"cid-1")))
+            .push("eth_call", serde_json::json!(format!("0x{}", hex::encode(revert))))
+            .push("eth_call", serde_json::json!(head_return_hex(1, b"cid-1")));
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .rpc_client(Arc::new(rpc))
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 5, ..observed(1, b"cid-1") }).unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 6, ..observed(2, b"cid-2") }).unwrap();
+
+        let err = f.drain_eligible_with(&FinalityContext::at_tip(10)).await.unwrap_err();
+        assert!(matches!(err, FinalizerError::Reverted(ref r) if r == "paused"), "{err:?}");
+        assert_eq!(f.pending_len(), 2, "the accepted event goes back too");
+        assert!(f.last_finalized().is_none());
+
+        let out = f.drain_eligible_with(&FinalityContext::at_tip(10)).await.unwrap();
+        assert_eq!(out.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn wait_for_seq_returns_target_once_finalized() {
+        let (mut f, tx) = wait_for_seq_setup().await;
+        let rx = tx.subscribe();
+        tx.send(HeadUpdatedObserved { block_number: 5, ..observed(1, b"cid-1") }).unwrap();
+        tx.send(HeadUpdatedObserved { block_number: 6, ..observed(2, b"cid-2") }).unwrap();
+        let ev = wait_for_seq(rx, &mut f, 2, Duration::from_secs(5)).await.unwrap();
+        assert_eq!((ev.seq, ev.cid.as_slice()), (2, b"cid-2".as_slice()));
+    }
+
+    #[tokio::test]
+    async fn wait_for_seq_retries_after_failed_cross_check() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", serde_json::json!("0x10"))
+            .push_error("eth_call", serde_json::json!({"code": -32000, "message": "upstream unavailable"}))
+            .push("eth_call", serde_json::json!(head_return_hex(2, b"cid-2")));
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(6)
+            .rpc_client(Arc::new(rpc.clone()))
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        let tx = broadcast::channel(16).0;
+        let rx = tx.subscribe();
+        tx.send(HeadUpdatedObserved { block_number: 6, ..observed(2, b"cid-2") }).unwrap();
+        let ev = wait_for_seq(rx, &mut f, 2, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(ev.seq, 2);
+        assert!(rpc.requests_for("eth_call").len() >= 2);
+    }
+
+    #[tokio::test]
+    async fn wait_for_seq_times_out() {
+        let (mut f, tx) = wait_for_seq_setup().await;
+        let rx = tx.subscribe();
+        tx.send(HeadUpdatedObserved { block_number: 6, ..observed(2, b"cid-2") }).unwrap();
+        drop(tx);
+        let res = wait_for_seq(rx, &mut f, 3, Duration::from_millis(200)).await;
+        assert!(matches!(res, Err(FinalizerError::Timeout(_))), "{res:?}");
+    }
+
+    #[test]
+    fn builder_requires_http_url_or_rpc_client() {
+        assert!(FinalizerBuilder::new().contract_address([0x11; 20]).build().is_err());
+    }
+
+    #[tokio::test]
+    async fn stalled_rpc_times_out() {
+        let f = FinalizerBuilder::new()
+            .http_url(spawn_stalled_http().await)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .request_timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let res = tokio::time::timeout(Duration::from_secs(5), f.current_tip())
+            .await
+            .expect("current_tip hung past the request timeout");
+        assert!(matches!(res, Err(FinalizerError::Timeout(_))), "{res:?}");
+    }
+
+    fn fast_retry(max_attempts: u32) -> RetryConfig {
+        RetryConfig {
+            max_attempts,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_recovers_after_transient_failures() {
+        let mut calls = 0;
+        let res = with_retry(&fast_retry(3), || {
+            calls += 1;
+            let n = calls;
+            async move {
+                if n <= 2 {
+                    Err(FinalizerError::Http("connection reset".into()))
+                } else {
+                    Ok(42u64)
+                }
+            }
+        })
+        .await;
+        assert_eq!(res.unwrap(), 42);
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up_after_max_attempts() {
+        let mut calls = 0;
+        let res: Result<u64, _> = with_retry(&fast_retry(2), || {
+            calls += 1;
+            async { Err(FinalizerError::Http("down".into())) }
+        })
+        .await;
+        assert!(matches!(res, Err(FinalizerError::Http(_))));
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn retry_skips_non_transient_errors() {
+        let mut calls = 0;
+        let res: Result<u64, _> = with_retry(&fast_retry(5), || {
+            calls += 1;
+            async { Err(FinalizerError::Decode("bad".into())) }
+        })
+        .await;
+        assert!(matches!(res, Err(FinalizerError::Decode(_))));
+        assert_eq!(calls, 1);
+    }
+
+    /// Finalizer (depth 0, no retries) against a stub whose `head()` returns `(seq, cid)`.
+    async fn builder_with_head(seq: u64, cid: &[u8]) -> FinalizerBuilder {
+        let ret = head_return_hex(seq, cid);
+        let url = spawn_http_rpc(move |_| serde_json::json!(ret.clone())).await;
+        FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+    }
+
+    fn observed(seq: u64, cid: &[u8]) -> HeadUpdatedObserved {
+        HeadUpdatedObserved {
+            seq,
+            writer: [0; 20],
+            cid: cid.to_vec(),
+            cid_hash: [0; 32],
+            block_number: 1,
+            tx_hash: [seq as u8; 32],
+            log_index: 0,
+            raw: None,
+            source_label: None,
+        }
+    }
+
+    #[test]
+    fn finalized_json_renders_cid_str() {
+        let v0 = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+        let binary = cid::Cid::try_from(v0).unwrap().to_bytes();
+        let json = serde_json::to_value(FinalizedEvent::from_observed(&observed(1, &binary))).unwrap();
+        assert_eq!(json["cid_str"], v0);
+        assert_eq!(json["cid"], serde_json::json!(binary), "raw bytes kept");
+
+        let json = serde_json::to_value(FinalizedEvent::from_observed(&observed(2, b"not a cid"))).unwrap();
+        assert_eq!(json["cid_str"], hex::encode(b"not a cid"));
+    }
+
+    #[tokio::test]
+    async fn source_label_flows_from_indexer_config_to_finalized_json() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", serde_json::json!("0x1"))
+            .push("eth_getLogs", serde_json::json!([crate::test_support::head_updated_log(1, 1, 0)]));
+        let config = crate::IndexerConfig::builder()
+            .http_url("http://unused.invalid")
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .http_poll(Duration::from_millis(10))
+            .source_label("atom")
+            .build()
+            .unwrap();
+        let indexer = crate::StemIndexer::with_rpc_client(config, Arc::new(rpc));
+        let mut heads = indexer.subscribe();
+        indexer.backfill_once().await.unwrap();
+        let ev = heads.try_recv().unwrap();
+        assert_eq!(ev.source_label.as_deref(), Some("atom"));
+
+        let mut f = builder_with_head(1, b"cid-1").await.build().unwrap();
+        f.feed(ev).unwrap();
+        let finalized = f.drain_eligible(10).await.unwrap();
+        let json = serde_json::to_value(&finalized[0]).unwrap();
+        assert_eq!(json["source_label"], "atom");
+
+        // Unlabelled events leave the field out.
+        let json = serde_json::to_value(FinalizedEvent::from_observed(&observed(1, b"cid"))).unwrap();
+        assert!(json.get("source_label").is_none());
+    }
+
+    #[tokio::test]
+    async fn root_validator_rejection_holds_event() {
+        let mut f = builder_with_head(1, b"cid")
+            .await
+            .with_root_validator(|_| Box::pin(async { Err("bad root".into()) }))
+            .build()
+            .unwrap();
+        f.feed(observed(1, b"cid")).unwrap();
+        assert!(f.drain_eligible(10).await.unwrap().is_empty());
+        assert_eq!(f.pending.len(), 1, "rejected event stays pending");
+    }
+
+    #[tokio::test]
+    async fn root_validator_sees_cid_and_accepts() {
+        let mut f = builder_with_head(1, b"cid")
+            .await
+            .with_root_validator(|cid| {
+                let ok = cid == b"cid";
+                Box::pin(async move { if ok { Ok(()) } else { Err("unexpected cid".into()) } })
+            })
+            .build()
+            .unwrap();
+        f.feed(observed(1, b"cid")).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+        assert!(f.pending.is_empty());
+    }
+
+    /// Binary CIDv1 of `data` with multicodec `codec`.
+    fn cid_with_codec(codec: u64, data: &[u8]) -> Vec<u8> {
+        use sha3::Digest;
+        let digest = sha3::Sha3_256::digest(data);
+        let hash = cid::multihash::Multihash::<64>::wrap(0x16, &digest).unwrap();
+        cid::Cid::new_v1(codec, hash).to_bytes()
+    }
+
+    async fn codec_finalizer(cid: &[u8], allow_unparseable: bool) -> Finalizer {
+        builder_with_head(1, cid)
+            .await
+            .allowed_cid_codecs(&[0x71, 0x55])
+            .allow_unparseable_cids(allow_unparseable)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn allowed_cid_codec_is_emitted() {
+        let cid = cid_with_codec(0x71, b"root");
+        let mut f = codec_finalizer(&cid, false).await;
+        f.feed(observed(1, &cid)).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn disallowed_cid_codec_is_held() {
+        // dag-json
+        let cid = cid_with_codec(0x0129, b"root");
+        let mut f = codec_finalizer(&cid, false).await;
+        f.feed(observed(1, &cid)).unwrap();
+        assert!(f.drain_eligible(10).await.unwrap().is_empty());
+        assert_eq!(f.pending_len(), 1, "held, not dropped");
+    }
+
+    #[tokio::test]
+    async fn unparseable_cid_follows_allow_unparseable_cids() {
+        let mut strict = codec_finalizer(b"not-a-cid", false).await;
+        strict.feed(observed(1, b"not-a-cid")).unwrap();
+        assert!(strict.drain_eligible(10).await.unwrap().is_empty());
+        assert_eq!(strict.pending_len(), 1);
+
+        let mut lenient = codec_finalizer(b"not-a-cid", true).await;
+        lenient.feed(observed(1, b"not-a-cid")).unwrap();
+        assert_eq!(lenient.drain_eligible(10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cross_check_accepts_string_cid_against_binary_head() {
+        let cid_str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+        let cid_bin = cid::Cid::try_from(cid_str).unwrap().to_bytes();
+        let mut f = builder_with_head(1, &cid_bin).await.build().unwrap();
+        f.feed(observed(1, cid_str.as_bytes())).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rpc_result_ok() {
+        let json = serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": "0x10"});
+        assert_eq!(rpc_result(json).unwrap(), serde_json::json!("0x10"));
+    }
+
+    fn at_block(block_number: u64) -> HeadUpdatedObserved {
+        HeadUpdatedObserved { block_number, ..observed(1, b"cid") }
+    }
+
+    #[test]
+    fn confirmation_depth_reads_context_tip() {
+        let ctx = |tip| FinalityContext { tip, finalized_block: None, safe_block: None, now_unix: 0 };
+        assert!(!ConfirmationDepth(6).is_eligible(&at_block(1), &ctx(6)));
+        assert!(ConfirmationDepth(6).is_eligible(&at_block(1), &ctx(7)));
+        assert!(!ConfirmationDepth(6).needs_finalized_block());
+    }
+
+    /// Context at `tip`, `secs` seconds after the Unix epoch.
+    fn ctx_at(tip: u64, secs: u64) -> FinalityContext {
+        FinalityContext { tip, finalized_block: None, safe_block: None, now_unix: secs }
+    }
+
+    #[tokio::test]
+    async fn pending_status_reports_confirmation_progress() {
+        let mut f = builder_with_head(1, b"cid").await.confirmation_depth(6).build().unwrap();
+        for (i, block) in [94, 95, 97, 101].into_iter().enumerate() {
+            f.feed(HeadUpdatedObserved { seq: i as u64 + 1, block_number: block, ..observed(1, b"cid") }).unwrap();
+        }
+        let status: Vec<_> = f
+            .pending_status(100)
+            .into_iter()
+            .map(|s| (s.seq, s.confirmations, s.required, s.eligible))
+            .collect();
+        assert_eq!(
+            status,
+            vec![
+                (1, 6, Some(6), true),
+                (2, 5, Some(6), false),
+                (3, 3, Some(6), false),
+                (4, 0, Some(6), false),
+            ]
+        );
+        assert_eq!(f.pending_len(), 4, "status does not drain");
+
+        let mut f = builder_with_head(1, b"cid").await.strategy(FinalizedTag).build().unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 90, ..observed(1, b"cid") }).unwrap();
+        let status = &f.pending_status(100)[0];
+        assert_eq!((status.confirmations, status.required, status.eligible), (10, None, false));
+    }
+
+    #[tokio::test]
+    async fn pending_status_does_not_start_debounce() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let now = Arc::new(AtomicU64::new(100));
+        let mut f = builder_with_head(1, b"cid")
+            .await
+            .strategy(Debounced::new(ConfirmationDepth(2), Duration::from_secs(30)))
+            .build()
+            .unwrap()
+            .with_clock({
+                let now = Arc::clone(&now);
+                move || UNIX_EPOCH + Duration::from_secs(now.load(Ordering::SeqCst))
+            });
+        f.feed(observed(1, b"cid")).unwrap();
+        assert!(!f.pending_status(10)[0].eligible);
+
+        // Had the status call started the cool-down at 100s, this drain would emit.
+        now.store(130, Ordering::SeqCst);
+        assert!(!f.pending_status(10)[0].eligible);
+        assert!(f.drain_eligible(10).await.unwrap().is_empty(), "cool-down starts at the first drain");
+        now.store(160, Ordering::SeqCst);
+        assert!(f.pending_status(10)[0].eligible);
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn debounced_is_not_eligible_before_delay() {
+        let s = Debounced::new(ConfirmationDepth(2), Duration::from_secs(5));
+        assert!(!s.is_eligible(&at_block(1), &ctx_at(2, 0)), "inner not eligible yet");
+        assert!(!s.is_eligible(&at_block(1), &ctx_at(3, 0)), "cool-down starts");
+        assert!(!s.is_eligible(&at_block(1), &ctx_at(3, 4)), "4s < 5s");
+    }
+
+    #[test]
+    fn debounced_is_eligible_after_delay() {
+        let s = Debounced::new(ConfirmationDepth(2), Duration::from_secs(5));
+        assert!(!s.is_eligible(&at_block(1), &ctx_at(3, 0)));
+        assert!(s.is_eligible(&at_block(1), &ctx_at(3, 5)));
+        assert!(s.is_eligible(&at_block(1), &ctx_at(3, 5)), "stays eligible across repeated checks");
+
+        // Dropping out of inner eligibility (a reorg) restarts the cool-down.
+        assert!(!s.is_eligible(&at_block(1), &ctx_at(1, 5)));
+        assert!(!s.is_eligible(&at_block(1), &ctx_at(3, 5)));
+    }
+
+    #[tokio::test]
+    async fn assert_same_chain_rejects_mismatched_rpcs() {
+        let indexer_rpc = crate::rpc::MockRpcClient::new();
+        indexer_rpc.push("eth_chainId", serde_json::json!("0x1")).push("eth_chainId", serde_json::json!("0x1"));
+        let config = crate::IndexerConfig::builder()
+            .http_url("http://unused.invalid")
+            .contract_address([0x11; 20])
+            .http_poll(Duration::from_secs(1))
+            .build()
+            .unwrap();
+        let indexer = StemIndexer::with_rpc_client(config, Arc::new(indexer_rpc));
+        let finalizer_on = |chain: &str| {
+            let rpc = crate::rpc::MockRpcClient::new();
+            rpc.push("eth_chainId", serde_json::json!(chain));
+            FinalizerBuilder::new()
+                .rpc_client(Arc::new(rpc))
+                .contract_address([0x11; 20])
+                .build()
+                .unwrap()
+        };
+
+        let err = assert_same_chain(&indexer, &finalizer_on("0xa")).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(FinalizerError::ChainMismatch { indexer: 1, finalizer: 10 })),
+            "{err:#}"
+        );
+        assert_same_chain(&indexer, &finalizer_on("0x1")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn events_far_beyond_tip_are_dropped() {
+        let mut f = builder_with_head(1, b"cid").await.confirmation_depth(6).future_block_slack(10).build().unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 1_000_000, ..observed(1, b"cid") }).unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 110, log_index: 1, ..observed(1, b"cid") }).unwrap();
+        assert_eq!(f.pending_len(), 2);
+
+        assert!(f.drain_eligible(100).await.unwrap().is_empty());
+        assert_eq!(f.pending_len(), 1, "future event dropped, in-slack event kept");
+        assert_eq!(f.oldest_pending_block(), Some(110));
+    }
+
+    #[tokio::test]
+    async fn cross_check_mismatch_is_counted() {
+        let mut f = builder_with_head(2, b"cid-2").await.build().unwrap();
+        f.feed(observed(1, b"cid-1")).unwrap();
+        f.feed(HeadUpdatedObserved { log_index: 1, ..observed(2, b"forged") }).unwrap();
+        assert_eq!(f.cross_check_drops(), 0);
+        assert!(f.drain_eligible(10).await.unwrap().is_empty());
+        assert_eq!(f.cross_check_drops(), 2, "superseded seq and mismatched cid");
+        assert_eq!(f.pending_len(), 0);
+
+        f.feed(HeadUpdatedObserved { log_index: 2, ..observed(2, b"cid-2") }).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+        assert_eq!(f.cross_check_drops(), 2, "matching event isn't counted");
+    }
+
+    #[tokio::test]
+    async fn last_finalized_tracks_highest_emitted_seq() {
+        let rpc = MockRpc::new();
+        rpc.push("eth_call", serde_json::json!(head_return_hex(1, b"cid-1")))
+            .push("eth_call", serde_json::json!(head_return_hex(3, b"cid-3")));
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .http_url(rpc.serve().await)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        assert!(f.last_finalized().is_none());
+
+        f.feed(observed(1, b"cid-1")).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+        assert_eq!(f.last_finalized().map(|e| e.seq), Some(1));
+
+        f.feed(HeadUpdatedObserved { block_number: 2, ..observed(2, b"cid-2") }).unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 3, ..observed(3, b"cid-3") }).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1, "seq 2 was superseded");
+        let last = f.last_finalized().unwrap();
+        assert_eq!((last.seq, last.block_number), (3, 3));
+
+        assert!(f.drain_eligible(10).await.unwrap().is_empty());
+        assert_eq!(f.last_finalized().map(|e| e.seq), Some(3));
+    }
+
+    #[tokio::test]
+    async fn reverted_head_call_surfaces_reason() {
+        use alloy::sol_types::SolType;
+        let mut revert = crate::abi::ERROR_SELECTOR.to_vec();
+        revert.extend(alloy::sol_types::sol_data::String::abi_encode("not initialized"));
+        let ret = format!("0x{}", hex::encode(revert));
+        let url = spawn_http_rpc(move |_| serde_json::json!(ret.clone())).await;
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        f.feed(observed(1, b"cid")).unwrap();
+        let err = f.drain_eligible(10).await.unwrap_err();
+        assert!(matches!(&err, FinalizerError::Reverted(reason) if reason == "not initialized"), "{err}");
+        assert!(!err.is_transient());
+    }
+
+    #[tokio::test]
+    async fn immediate_canonical_finalizes_on_feed_tick_when_head_matches() {
+        let fresh = HeadUpdatedObserved { block_number: 9, ..observed(1, b"cid") };
+        let mut f = builder_with_head(1, b"cid").await.strategy(ImmediateCanonical).build().unwrap();
+        f.feed(fresh.clone()).unwrap();
+        let out = f.drain_eligible(9).await.unwrap();
+        assert_eq!(out.len(), 1, "finalized at its own block, no confirmations");
+        assert_eq!(out[0].block_number, 9);
+
+        // head() has moved on: eligibility alone doesn't finalize.
+        let mut f = builder_with_head(2, b"cid-2").await.strategy(ImmediateCanonical).build().unwrap();
+        f.feed(fresh).unwrap();
+        assert!(f.drain_eligible(9).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn per_writer_depth_picks_k_by_writer() {
+        let ctx = |tip| FinalityContext { tip, finalized_block: None, safe_block: None, now_unix: 0 };
+        let s = PerWriterDepth::new(HashMap::from([([0xaa; 20], 10)]), 2);
+        let trusted = HeadUpdatedObserved { writer: [0xbb; 20], ..at_block(1) };
+        let cautious = HeadUpdatedObserved { writer: [0xaa; 20], ..at_block(1) };
+        assert!(!s.is_eligible(&trusted, &ctx(2)));
+        assert!(s.is_eligible(&trusted, &ctx(3)), "default depth 2");
+        assert!(!s.is_eligible(&cautious, &ctx(10)));
+        assert!(s.is_eligible(&cautious, &ctx(11)), "custom depth 10");
+    }
+
+    #[tokio::test]
+    async fn builder_per_writer_depth_holds_deep_writer() {
+        let mut f = builder_with_head(1, b"cid")
+            .await
+            .per_writer_depth(HashMap::from([([0xaa; 20], 10)]), 0)
+            .build()
+            .unwrap();
+        f.feed(HeadUpdatedObserved { writer: [0xaa; 20], ..at_block(1) }).unwrap();
+        assert!(f.drain_eligible(5).await.unwrap().is_empty());
+        assert_eq!(f.drain_eligible(11).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn tip_strategy_shim_passes_tip() {
+        struct AtLeast(u64);
+        impl TipStrategy for AtLeast {
+            fn is_eligible_at_tip(&self, _ev: &HeadUpdatedObserved, tip: u64) -> bool {
+                tip >= self.0
+            }
+        }
+        let s: Box<dyn Strategy> = Box::new(AtLeast(10));
+        assert!(!s.is_eligible(&at_block(1), &FinalityContext::at_tip(9)));
+        assert!(s.is_eligible(&at_block(1), &FinalityContext::at_tip(10)));
+    }
+
+    #[test]
+    fn finalized_tag_needs_finalized_block() {
+        let ctx = |finalized_block| FinalityContext { tip: 100, finalized_block, safe_block: None, now_unix: 0 };
+        assert!(!FinalizedTag.is_eligible(&at_block(5), &ctx(None)));
+        assert!(FinalizedTag.is_eligible(&at_block(5), &ctx(Some(5))));
+        assert!(!FinalizedTag.is_eligible(&at_block(6), &ctx(Some(5))));
+    }
+
+    #[tokio::test]
+    async fn drain_populates_finalized_block() {
+        let ret = head_return_hex(1, b"cid");
+        let url = spawn_http_rpc(move |req| match req["method"].as_str() {
+            Some("eth_getBlockByNumber") => {
+                assert_eq!(req["params"][0], "finalized");
+                serde_json::json!({"number": "0x5"})
+            }
+            _ => serde_json::json!(ret.clone()),
+        }).await;
+        let mut f = FinalizerBuilder::new()
+            .strategy(FinalizedTag)
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        let ctx = f.finality_context(100).await.unwrap();
+        assert_eq!((ctx.tip, ctx.finalized_block), (100, Some(5)));
+        assert!(ctx.now_unix > 0);
+        f.feed(at_block(5)).unwrap();
+        assert_eq!(f.drain_eligible(100).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn safe_tag_falls_back_to_depth_without_safe_block() {
+        let ctx = |tip, safe_block| FinalityContext { tip, finalized_block: None, safe_block, now_unix: 0 };
+        let s = SafeTag::with_fallback_depth(6);
+        assert!(s.needs_safe_block() && !s.needs_finalized_block());
+        assert!(s.is_eligible(&at_block(5), &ctx(5, Some(5))));
+        assert!(!s.is_eligible(&at_block(6), &ctx(100, Some(5))));
+        assert!(!s.is_eligible(&at_block(5), &ctx(10, None)));
+        assert!(s.is_eligible(&at_block(5), &ctx(11, None)));
+    }
+
+    #[tokio::test]
+    async fn drain_uses_safe_block() {
+        let rpc = MockRpc::new();
+        rpc.push("eth_getBlockByNumber", serde_json::json!({"number": "0x5"}))
+            .push("eth_call", serde_json::json!(head_return_hex(1, b"cid")));
+        let mut f = FinalizerBuilder::new()
+            .strategy(SafeTag::default())
+            .http_url(rpc.serve().await)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        let ctx = f.finality_context(100).await.unwrap();
+        assert_eq!((ctx.finalized_block, ctx.safe_block), (None, Some(5)));
+        assert_eq!(rpc.requests_for("eth_getBlockByNumber")[0]["params"][0], "safe");
+        f.feed(at_block(5)).unwrap();
+        f.feed(HeadUpdatedObserved { log_index: 1, ..at_block(6) }).unwrap();
+        let out = f.drain_eligible(100).await.unwrap();
+        assert_eq!(out.iter().map(|e| e.block_number).collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[tokio::test]
+    async fn builder_uses_provided_http_client() {
+        let rpc = MockRpc::new();
+        rpc.push("eth_blockNumber", serde_json::json!("0x2a"));
+        // Only a client proxying through the mock can reach this host.
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::http(rpc.serve().await).unwrap())
+            .build()
+            .unwrap();
+        let f = FinalizerBuilder::new()
+            .http_url("http://stem-rpc.invalid")
+            .http_client(client)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        assert_eq!(f.current_tip().await.unwrap(), 42);
+        assert_eq!(rpc.requests_for("eth_blockNumber").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn drain_falls_back_when_node_has_no_safe_block() {
+        let rpc = MockRpc::new();
+        rpc.push("eth_getBlockByNumber", serde_json::Value::Null)
+            .push("eth_call", serde_json::json!(head_return_hex(1, b"cid")));
+        let mut f = FinalizerBuilder::new()
+            .strategy(SafeTag::with_fallback_depth(6))
+            .http_url(rpc.serve().await)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        f.feed(at_block(5)).unwrap();
+        assert!(f.drain_eligible(10).await.unwrap().is_empty());
+        assert_eq!(f.drain_eligible(11).await.unwrap().len(), 1);
+    }
+
+    /// Sink that records published events in memory.
+    #[derive(Default)]
+    struct VecSink(std::sync::Mutex<Vec<FinalizedEvent>>);
+
+    impl FinalizedSink for VecSink {
+        fn publish<'a>(&'a self, ev: &'a FinalizedEvent) -> BoxFuture<'a, Result<(), crate::SinkError>> {
+            self.0.lock().unwrap().push(ev.clone());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    async fn tip_and_head_rpc(tip: u64, seq: u64, cid: &[u8]) -> String {
+        let ret = head_return_hex(seq, cid);
+        spawn_http_rpc(move |req| match req["method"].as_str() {
+            Some("eth_blockNumber") => serde_json::json!(format!("0x{:x}", tip)),
+            _ => serde_json::json!(ret.clone()),
+        }).await
+    }
+
+    #[tokio::test]
+    async fn run_to_sink_publishes_finalized_events() {
+        let url = tip_and_head_rpc(10, 2, b"cid-2").await;
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(3)
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        let (tx, rx) = broadcast::channel(16);
+        tx.send(HeadUpdatedObserved { block_number: 5, ..observed(1, b"cid-1") }).unwrap();
+        tx.send(HeadUpdatedObserved { block_number: 6, ..observed(2, b"cid-2") }).unwrap();
+        tx.send(HeadUpdatedObserved { block_number: 9, ..observed(3, b"cid-3") }).unwrap();
+        drop(tx);
+        let sink = VecSink::default();
+        f.run_to_sink(rx, &sink, Duration::from_millis(10)).await.unwrap();
+        // seq 1 is superseded on-chain; seq 3 lacks confirmations at tip 10.
+        let published = sink.0.into_inner().unwrap();
+        assert_eq!(published.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(f.pending.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn run_to_sink_stops_on_sink_error() {
+        struct FailingSink;
+        impl FinalizedSink for FailingSink {
+            fn publish<'a>(&'a self, _ev: &'a FinalizedEvent) -> BoxFuture<'a, Result<(), crate::SinkError>> {
+                Box::pin(async { Err("bus down".into()) })
+            }
+        }
+        let url = tip_and_head_rpc(10, 1, b"cid-1").await;
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        let (tx, rx) = broadcast::channel(16);
+        tx.send(observed(1, b"cid-1")).unwrap();
+        let err = f.run_to_sink(rx, &FailingSink, Duration::from_millis(10)).await.unwrap_err();
+        assert!(matches!(err, FinalizerError::Sink(ref m) if m == "bus down"), "{err:?}");
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn spawn_delivers_finalized_events_and_stops_on_close() {
+        let url = tip_and_head_rpc(10, 2, b"cid-2").await;
+        let f = FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        let (tx, rx) = broadcast::channel(16);
+        let (handle, mut finalized) = f.spawn(rx, Duration::from_millis(10));
+        tx.send(HeadUpdatedObserved { block_number: 6, ..observed(2, b"cid-2") }).unwrap();
+        let ev = tokio::time::timeout(Duration::from_secs(5), finalized.recv())
+            .await
+            .expect("finalized event")
+            .unwrap();
+        assert_eq!((ev.seq, ev.block_number), (2, 6));
+        drop(tx);
+        assert!(finalized.recv().await.is_none(), "channel closes with the indexer");
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn spawn_survives_tip_poll_errors() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let seen = calls.clone();
+        let ret = head_return_hex(1, b"cid-1");
+        let url = spawn_json_rpc(move |req| match req["method"].as_str() {
+            Some("eth_blockNumber") if seen.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 2 => {
+                Err(serde_json::json!({"code": -32000, "message": "header not found"}))
+            }
+            Some("eth_blockNumber") => Ok(serde_json::json!("0xa")),
+            _ => Ok(serde_json::json!(ret.clone())),
+        }).await;
+        let f = FinalizerBuilder::new()
+            .confirmation_depth(0)
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap();
+        let (tx, rx) = broadcast::channel(16);
+        tx.send(observed(1, b"cid-1")).unwrap();
+        let (_handle, mut finalized) = f.spawn(rx, Duration::from_millis(10));
+        let ev = tokio::time::timeout(Duration::from_secs(5), finalized.recv())
+            .await
+            .expect("finalized after tip errors")
+            .unwrap();
+        assert_eq!(ev.seq, 1);
+        assert!(calls.load(std::sync::atomic::Ordering::SeqCst) >= 3);
+    }
+
+    /// Feed and drain `events` one at a time through a single finalizer, pointing it at a head
+    /// RPC that matches each event in turn; returns the emitted seqs.
+    async fn drain_each(key: DedupKey, events: Vec<HeadUpdatedObserved>) -> Vec<u64> {
+        let mut f = builder_with_head(0, b"").await.dedup_by(key).build().unwrap();
+        let mut out = Vec::new();
+        for ev in events {
+            let ret = head_return_hex(ev.seq, &ev.cid);
+            f.rpc = Arc::new(HttpRpcClient::new(spawn_http_rpc(move |_| serde_json::json!(ret.clone())).await));
+            f.feed(ev).unwrap();
+            out.extend(f.drain_eligible(10).await.unwrap().into_iter().map(|e| e.seq));
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn drain_all_ignores_depth_but_not_cross_check() {
+        let mut f = builder_with_head(2, b"b").await.confirmation_depth(100).build().unwrap();
+        f.feed(event(1, b"a", 1, 0)).unwrap();
+        f.feed(event(2, b"b", 2, 1)).unwrap();
+        assert!(f.drain_eligible(10).await.unwrap().is_empty(), "below depth");
+        assert_eq!(f.pending_len(), 2);
+
+        let seqs: Vec<_> = f.drain_all().await.unwrap().into_iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![2], "seq 1 is superseded by head()");
+        assert_eq!(f.pending_len(), 0);
+        f.feed(event(2, b"b", 2, 1)).unwrap();
+        assert!(f.drain_all().await.unwrap().is_empty(), "deduplicated");
+    }
+
+    #[tokio::test]
+    async fn reset_allows_re_emitting() {
+        let mut f = builder_with_head(1, b"a").await.build().unwrap();
+        let ev = event(1, b"a", 1, 0);
+        f.feed(ev.clone()).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+        f.feed(ev.clone()).unwrap();
+        f.feed(event(2, b"b", 2, 1)).unwrap();
+        assert!(f.drain_eligible(10).await.unwrap().is_empty(), "deduplicated");
+        assert_eq!(f.cross_check_drops(), 1, "seq 2 is not the head");
+
+        f.reset();
+        assert_eq!(f.pending_len(), 0);
+        assert!(f.last_finalized().is_none());
+        assert_eq!(f.cross_check_drops(), 0);
+        f.feed(ev.clone()).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap()[0].seq, 1);
+        assert_eq!(f.last_finalized().map(|e| e.seq), Some(1));
+    }
+
+    #[tokio::test]
+    async fn reset_emitted_only_keeps_pending() {
+        let mut f = builder_with_head(1, b"a").await.build().unwrap();
+        let ev = event(1, b"a", 1, 0);
+        f.feed(ev.clone()).unwrap();
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+        f.feed(ev.clone()).unwrap();
+        f.feed(event(2, b"b", 2, 1)).unwrap();
+        assert!(f.drain_eligible(0).await.unwrap().is_empty(), "not yet eligible at tip 0");
+
+        f.reset_emitted_only();
+        assert_eq!(f.pending_len(), 2);
+        let seqs: Vec<_> = f.drain_eligible(10).await.unwrap().into_iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![1]);
+    }
+
+    fn event(seq: u64, cid: &[u8], tx: u8, log_index: u64) -> HeadUpdatedObserved {
+        HeadUpdatedObserved {
+            tx_hash: [tx; 32],
+            log_index,
+            cid_hash: crate::abi::cid_hash(cid),
+            ..observed(seq, cid)
+        }
+    }
+
+    #[tokio::test]
+    async fn dedup_tx_log_suppresses_only_the_same_log() {
+        let events = vec![
+            event(1, b"a", 1, 0),
+            event(1, b"a", 1, 0), // same log redelivered
+            event(1, b"a", 2, 0), // same seq/cid, different log
+        ];
+        assert_eq!(drain_each(DedupKey::TxLog, events).await, vec![1, 1]);
+    }
+
+    #[tokio::test]
+    async fn dedup_seq_collapses_republished_heads() {
+        let events = vec![
+            event(1, b"a", 1, 0),
+            event(1, b"a", 2, 0), // re-published head
+            event(1, b"b", 3, 0), // different cid reusing seq 1: also suppressed
+            event(2, b"a", 4, 0),
+        ];
+        assert_eq!(drain_each(DedupKey::Seq, events).await, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn dedup_cid_hash_collapses_repeated_content() {
+        let events = vec![
+            event(1, b"a", 1, 0),
+            event(2, b"a", 2, 0), // same content under a new seq
+            event(3, b"b", 3, 0),
+        ];
+        assert_eq!(drain_each(DedupKey::CidHash, events).await, vec![1, 3]);
+    }
+
+    fn at(block_number: u64, log_index: u64) -> HeadUpdatedObserved {
+        HeadUpdatedObserved { block_number, log_index, ..observed(block_number, b"cid") }
+    }
+
+    #[tokio::test]
+    async fn pending_stays_ordered_and_bounded_under_load() {
+        use rand::seq::SliceRandom;
+        let mut f = builder_with_head(0, b"")
+            .await
+            .max_pending(1_000, OverflowPolicy::DropOldest)
+            .build()
+            .unwrap();
+        let mut events: Vec<_> = (0..10_000u64).map(|i| at(i / 4, i % 4)).collect();
+        events.shuffle(&mut rand::thread_rng());
+        for ev in events {
+            f.feed(ev).unwrap();
+        }
+        assert_eq!(f.pending.len(), 1_000);
+        // Shuffled input can evict an event before a newer-but-smaller one arrives, so only check
+        // that what remains is strictly ordered and skews to the newest blocks.
+        let keys: Vec<_> = f.pending.values().map(|p| (p.ev.block_number, p.ev.log_index)).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(*keys.last().unwrap(), (2_499, 3));
+    }
+
+    #[tokio::test]
+    async fn pending_overflow_error_rejects_new_events() {
+        let mut f = builder_with_head(0, b"")
+            .await
+            .max_pending(2, OverflowPolicy::Error)
+            .build()
+            .unwrap();
+        f.feed(at(1, 0)).unwrap();
+        f.feed(at(2, 0)).unwrap();
+        f.feed(at(2, 0)).unwrap(); // same key replaces, no growth
+        assert!(matches!(f.feed(at(3, 0)), Err(FinalizerError::PendingFull(2))));
+        assert_eq!(f.pending.keys().copied().collect::<Vec<_>>(), vec![(1, 0), (2, 0)]);
+    }
+
+    #[tokio::test]
+    async fn pending_overflow_drop_oldest_evicts_first_key() {
+        let mut f = builder_with_head(0, b"")
+            .await
+            .max_pending(2, OverflowPolicy::DropOldest)
+            .build()
+            .unwrap();
+        for ev in [at(2, 0), at(1, 0), at(3, 0)] {
+            f.feed(ev).unwrap();
+        }
+        assert_eq!(f.pending.keys().copied().collect::<Vec<_>>(), vec![(2, 0), (3, 0)]);
+    }
+
+    #[tokio::test]
+    async fn pending_stats_report_count_and_oldest() {
+        let mut f = builder_with_head(0, b"").await.build().unwrap();
+        assert_eq!((f.pending_len(), f.oldest_pending_block()), (0, None));
+        assert!(f.oldest_pending_age().is_none());
+        for ev in [at(12, 0), at(7, 1), at(9, 0)] {
+            f.feed(ev).unwrap();
+        }
+        assert_eq!(f.pending_len(), 3);
+        assert_eq!(f.oldest_pending_block(), Some(7));
+        assert!(f.oldest_pending_age().unwrap() < Duration::from_secs(5));
+    }
+
+    /// Head RPC for seq 1 plus `eth_getBlockByNumber` whose hash changes once `reorged` is set.
+    async fn reorgable_rpc(reorged: std::sync::Arc<std::sync::atomic::AtomicBool>) -> String {
+        let ret = head_return_hex(1, b"cid");
+        spawn_http_rpc(move |req| match req["method"].as_str() {
+            Some("eth_getBlockByNumber") => {
+                let fork = if reorged.load(std::sync::atomic::Ordering::SeqCst) { "b" } else { "a" };
+                serde_json::json!({"number": req["params"][0], "hash": format!("0x{fork}")})
+            }
+            _ => serde_json::json!(ret.clone()),
+        }).await
+    }
+
+    #[tokio::test]
+    async fn deep_reorg_within_window_is_an_error() {
+        let reorged = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(2)
+            .http_url(reorgable_rpc(reorged.clone()).await)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .detect_deep_reorgs(10)
+            .build()
+            .unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 5, ..observed(1, b"cid") }).unwrap();
+        assert_eq!(f.drain_eligible(8).await.unwrap().len(), 1);
+        assert!(f.drain_eligible(9).await.unwrap().is_empty(), "hash unchanged");
+        reorged.store(true, std::sync::atomic::Ordering::SeqCst);
+        let err = f.drain_eligible(9).await.unwrap_err();
+        assert!(matches!(err, FinalizerError::DeepReorg { depth: 4 }), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn deep_reorg_outside_window_is_ignored() {
+        let reorged = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut f = FinalizerBuilder::new()
+            .confirmation_depth(2)
+            .http_url(reorgable_rpc(reorged.clone()).await)
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .detect_deep_reorgs(3)
+            .build()
+            .unwrap();
+        f.feed(HeadUpdatedObserved { block_number: 5, ..observed(1, b"cid") }).unwrap();
+        assert_eq!(f.drain_eligible(8).await.unwrap().len(), 1);
+        reorged.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert!(f.drain_eligible(9).await.unwrap().is_empty());
+        assert!(f.emitted_blocks.is_empty(), "block 5 left the window at tip 9");
+    }
+
+    /// Counts `eth_blockNumber` calls; the tip is always 10.
+    async fn counting_tip_rpc() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let seen = calls.clone();
+        let url = spawn_http_rpc(move |req| {
+            assert_eq!(req["method"], "eth_blockNumber");
+            seen.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            serde_json::json!("0xa")
+        }).await;
+        (url, calls)
+    }
+
+    #[tokio::test]
+    async fn tip_ttl_reuses_tip_within_window() {
+        let (url, calls) = counting_tip_rpc().await;
+        let mut f = FinalizerBuilder::new()
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .tip_ttl(Duration::from_secs(1))
+            .build()
+            .unwrap();
+        for _ in 0..2 {
+            let tip = f.current_tip().await.unwrap();
+            assert!(f.drain_eligible(tip).await.unwrap().is_empty());
+        }
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn drain_asks_a_stateful_strategy_once_per_event() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Ineligible on the first check, eligible on every later one.
+        struct SecondTime(AtomicUsize);
+        impl Strategy for SecondTime {
+            fn is_eligible(&self, _ev: &HeadUpdatedObserved, _ctx: &FinalityContext) -> bool {
+                self.0.fetch_add(1, Ordering::SeqCst) > 0
+            }
+        }
+
+        let mut f = builder_with_head(1, b"cid")
+            .await
+            .strategy(SecondTime(AtomicUsize::new(0)))
+            .build()
+            .unwrap();
+        f.feed(observed(1, b"cid")).unwrap();
+        assert!(f.drain_eligible_with(&FinalityContext::at_tip(10)).await.unwrap().is_empty());
+        assert_eq!(f.pending_len(), 1, "not removed without being drained");
+        let out = f.drain_eligible_with(&FinalityContext::at_tip(10)).await.unwrap();
+        assert_eq!(out.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn injected_clock_and_tip_finalize_at_exact_time_boundary() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        /// Eligible from `self.0` (Unix seconds) on.
+        struct NotBefore(u64);
+        impl Strategy for NotBefore {
+            fn is_eligible(&self, _ev: &HeadUpdatedObserved, ctx: &FinalityContext) -> bool {
+                ctx.now_unix >= self.0
+            }
+        }
+
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_call", serde_json::json!(head_return_hex(1, b"cid")));
+        let now = Arc::new(AtomicU64::new(999));
+        let mut f = FinalizerBuilder::new()
+            .strategy(NotBefore(1_000))
+            .rpc_client(Arc::new(rpc.clone()))
+            .contract_address([0x11; 20])
+            .retry(RetryConfig::none())
+            .build()
+            .unwrap()
+            .with_clock({
+                let now = Arc::clone(&now);
+                move || UNIX_EPOCH + Duration::from_secs(now.load(Ordering::SeqCst))
+            })
+            .with_tip_source(|| Box::pin(async { Ok(50) }));
+        f.feed(observed(1, b"cid")).unwrap();
+
+        let tip = f.current_tip().await.unwrap();
+        assert_eq!(tip, 50);
+        assert!(f.drain_eligible(tip).await.unwrap().is_empty(), "one second early");
+        now.store(1_000, Ordering::SeqCst);
+        let out = f.drain_eligible(f.current_tip().await.unwrap()).await.unwrap();
+        assert_eq!(out.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1]);
+        assert!(rpc.requests_for("eth_blockNumber").is_empty());
+    }
+
+    #[tokio::test]
+    async fn injected_clock_drives_debounce() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let now = Arc::new(AtomicU64::new(100));
+        let mut f = builder_with_head(1, b"cid")
+            .await
+            .strategy(Debounced::new(ConfirmationDepth(2), Duration::from_secs(30)))
+            .build()
+            .unwrap()
+            .with_clock({
+                let now = Arc::clone(&now);
+                move || UNIX_EPOCH + Duration::from_secs(now.load(Ordering::SeqCst))
+            });
+        f.feed(observed(1, b"cid")).unwrap();
+
+        assert!(f.drain_eligible(10).await.unwrap().is_empty(), "cool-down starts");
+        now.store(129, Ordering::SeqCst);
+        assert!(f.drain_eligible(10).await.unwrap().is_empty(), "29s < 30s");
+        now.store(130, Ordering::SeqCst);
+        assert_eq!(f.drain_eligible(10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn tip_ttl_refreshes_after_expiry() {
+        // Clock that runs 10s ahead after each reading.
+        let readings = std::sync::atomic::AtomicU64::new(0);
+        let (url, calls) = counting_tip_rpc().await;
+        let f = FinalizerBuilder::new()
+            .http_url(url)
+            .contract_address([0x11; 20])
+            .tip_ttl(Duration::from_secs(1))
+            .build()
+            .unwrap()
+            .with_clock(move || {
+                UNIX_EPOCH + Duration::from_secs(10 * readings.fetch_add(1, std::sync::atomic::Ordering::SeqCst))
+            });
+        f.current_tip().await.unwrap();
+        f.current_tip().await.unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn builder_events_feed_a_finalizer() {
+        let mut f = builder_with_head(2, b"ipfs://two").await.confirmation_depth(3).build().unwrap();
+        for (seq, block) in [(1, 4), (2, 5)] {
+            let ev = HeadUpdatedObserved::builder()
+                .seq(seq)
+                .cid(format!("ipfs://{}", ["one", "two"][seq as usize - 1]))
+                .block_number(block)
+                .tx_hash([seq as u8; 32])
+                .build();
+            f.feed(ev).unwrap();
+        }
+        let out = f.drain_eligible(8).await.unwrap();
+        assert_eq!(out.iter().map(|e| (e.seq, e.block_number)).collect::<Vec<_>>(), vec![(2, 5)]);
+        assert_eq!(out[0].cid_hash_hex, hex::encode(crate::abi::cid_hash(b"ipfs://two")));
     }
 }
@@ -0,0 +1,108 @@
+//! Liveness/readiness probe for a [StemIndexer] (feature `health`, on by default).
+//!
+//! A minimal HTTP/1.1 responder over [StemIndexer::sync_status]: every request, whatever its
+//! path, gets `200 OK` once the indexer is synced and `503 Service Unavailable` while it is
+//! backfilling or reconnecting, with the [crate::indexer::SyncStatus] as a JSON body.
+
+use crate::indexer::StemIndexer;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Request heads larger than this are answered without reading further.
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// Serve the probe on every connection accepted from `listener`. Returns only if accepting fails.
+pub async fn serve_health(indexer: Arc<StemIndexer>, listener: TcpListener) -> std::io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let indexer = Arc::clone(&indexer);
+        tokio::spawn(async move {
+            if let Err(e) = respond(&indexer, stream).await {
+                tracing::debug!(%peer, reason = %e, "health probe connection failed");
+            }
+        });
+    }
+}
+
+async fn respond(indexer: &StemIndexer, mut stream: TcpStream) -> std::io::Result<()> {
+    // Read (and discard) the request head; the probe answers the same on every path.
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&chunk[..n]);
+    }
+    let status = indexer.sync_status().await;
+    let body = serde_json::to_string(&status).expect("SyncStatus serializes");
+    let status_line = if status.synced {
+        "200 OK"
+    } else {
+        "503 Service Unavailable"
+    };
+    let resp = format!(
+        "HTTP/1.1 {status_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+    stream.write_all(resp.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IndexerConfig;
+    use crate::test_support::spawn_http_rpc;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    async fn probe(client: &reqwest::Client, url: &str) -> (u16, Value) {
+        let resp = client.get(url).send().await.unwrap();
+        (resp.status().as_u16(), resp.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn probe_turns_ready_once_synced() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x5"),
+            Some("eth_getLogs") => json!([]),
+            _ => Value::Null,
+        }).await;
+        let config = IndexerConfig::builder()
+            .http_url(http_url)
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .http_poll(Duration::from_millis(20))
+            .verify_contract_code(false)
+            .build()
+            .unwrap();
+        let indexer = Arc::new(StemIndexer::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/healthz", listener.local_addr().unwrap());
+        tokio::spawn(serve_health(Arc::clone(&indexer), listener));
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+        let (code, body) = probe(&client, &url).await;
+        assert_eq!(code, 503);
+        assert_eq!(body["synced"], false);
+
+        let task = tokio::spawn(Arc::clone(&indexer).run());
+        let (code, body) = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let (code, body) = probe(&client, &url).await;
+                if code == 200 {
+                    return (code, body);
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("probe becomes ready");
+        assert_eq!(code, 200);
+        assert_eq!(body, json!({"synced": true, "last_processed_block": 5, "current_head_seq": null}));
+        task.abort();
+    }
+}
@@ -6,32 +6,46 @@
 //! for reorg-safe, confirmation-based output.
 
 use crate::abi::{
-    decode_log_to_observed, CurrentHead, HeadUpdatedObserved,
-    HEAD_UPDATED_TOPIC0,
+    decode_head_return, decode_log_to_observed_with_max_cid_len, decode_log_with, log_topic0, CidIndexed, CurrentHead,
+    DecodedLog, HeadReverted, HeadUpdatedObserved, PendingLog, HEAD_SELECTOR, HEAD_UPDATED_TOPIC0,
+};
+use crate::config::{
+    BackoffStrategy, EmitOrder, Exponential, HeadMode, IndexerConfig, ReconnectionConfig, RetryConfig, StartFrom,
+    TopicDecoder, TransportMode,
 };
-use crate::config::IndexerConfig;
 use crate::cursor::Cursor;
+use crate::rpc::{HttpRpcClient, RpcClient, RpcError};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 use tokio::sync::{broadcast, RwLock};
-use tokio::time::{sleep, Duration, timeout};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-use futures_util::{SinkExt, StreamExt};
-use rand::Rng;
+use tokio::time::{sleep, sleep_until, Duration, Instant, timeout};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async_tls_with_config, tungstenite::Message, Connector, MaybeTlsStream, WebSocketStream};
+use tracing::Instrument;
+use futures_util::{stream, SinkExt, StreamExt};
 
 fn build_logs_filter(
     address: &[u8; 20],
-    topic0: Option<&[u8; 4]>,
+    topics0: &[[u8; 4]],
     from_block: Option<u64>,
     to_block: Option<u64>,
 ) -> Value {
     let mut filter = json!({
         "address": format!("0x{}", hex::encode(address)),
     });
-    // Single-topic filter: [topic0] only (some nodes reject [topic0, null, null, null]).
-    if let Some(t0) = topic0 {
-        filter["topics"] = json!([format!("0x{}", hex::encode(t0))]);
+    // Position-0 filter only: [topic0] (some nodes reject [topic0, null, null, null]), or
+    // [[topic0, ...]] to match any of several events.
+    match topics0 {
+        [] => {}
+        [t0] => filter["topics"] = json!([format!("0x{}", hex::encode(t0))]),
+        many => {
+            let any: Vec<String> = many.iter().map(|t| format!("0x{}", hex::encode(t))).collect();
+            filter["topics"] = json!([any]);
+        }
     }
     if let Some(from) = from_block {
         filter["fromBlock"] = Value::String(format!("0x{:x}", from));
@@ -60,35 +74,143 @@ fn build_logs_filter_address_only(
     filter
 }
 
-async fn http_json_rpc(client: &reqwest::Client, url: &str, method: &str, params: Value, id: u64) -> Result<Value> {
-    let body = json!({
-        "jsonrpc": "2.0",
-        "id": id,
-        "method": method,
-        "params": params
-    });
-    let resp = client
-        .post(url)
-        .json(&body)
-        .send()
-        .await
-        .context("HTTP request failed")?;
-    let json: Value = resp.json().await.context("parse response")?;
-    if let Some(err) = json.get("error") {
-        anyhow::bail!("RPC error: {}", err);
+fn parse_quantity(v: &Value, what: &str) -> Result<u64> {
+    let s = v.as_str().ok_or_else(|| anyhow::anyhow!("{} not string", what))?;
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u64::from_str_radix(s, 16).with_context(|| format!("parse {}", what))
+}
+
+async fn eth_block_number(client: &dyn RpcClient) -> Result<u64> {
+    let result = client.call("eth_blockNumber", json!([])).await?;
+    parse_quantity(&result, "blockNumber")
+}
+
+/// Number of the block with `hash`, or `None` if the node doesn't know it.
+async fn eth_block_number_by_hash(client: &dyn RpcClient, hash: &[u8; 32]) -> Result<Option<u64>> {
+    let block = client
+        .call("eth_getBlockByHash", json!([format!("0x{}", hex::encode(hash)), false]))
+        .await?;
+    if block.is_null() {
+        return Ok(None);
     }
-    let result = json
-        .get("result")
-        .cloned()
-        .ok_or_else(|| anyhow::anyhow!("Missing result"))?;
-    Ok(result)
+    parse_quantity(&block["number"], "block number").map(Some)
 }
 
-async fn eth_block_number(client: &reqwest::Client, http_url: &str) -> Result<u64> {
-    let result = http_json_rpc(client, http_url, "eth_blockNumber", json!([]), 1).await?;
-    let s = result.as_str().ok_or_else(|| anyhow::anyhow!("blockNumber not string"))?;
-    let s = s.strip_prefix("0x").unwrap_or(s);
-    u64::from_str_radix(s, 16).context("parse block number")
+async fn eth_chain_id(client: &dyn RpcClient) -> Result<u64> {
+    let result = client.call("eth_chainId", json!([])).await?;
+    parse_quantity(&result, "chainId")
+}
+
+/// Deployed bytecode at `address` as of `"latest"` (empty for an account without code).
+async fn eth_get_code(client: &dyn RpcClient, address: &[u8; 20]) -> Result<Vec<u8>> {
+    let result = client
+        .call("eth_getCode", json!([format!("0x{}", hex::encode(address)), "latest"]))
+        .await?;
+    let s = result.as_str().ok_or_else(|| anyhow::anyhow!("eth_getCode result not string"))?;
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).context("decode eth_getCode result")
+}
+
+/// Fail with [IndexerError::NoContractCode] if `address` is zero or has no deployed code.
+async fn check_contract_code(client: &dyn RpcClient, address: &[u8; 20]) -> Result<()> {
+    if *address == [0u8; 20] || eth_get_code(client, address).await?.is_empty() {
+        return Err(IndexerError::NoContractCode(*address).into());
+    }
+    Ok(())
+}
+
+/// `eth_call` against `block` (a tag such as `"latest"` or a hex block number).
+async fn eth_call(
+    client: &dyn RpcClient,
+    to: &[u8; 20],
+    calldata: &[u8],
+    block: &str,
+) -> Result<Vec<u8>> {
+    let params = json!([{
+        "to": format!("0x{}", hex::encode(to)),
+        "data": format!("0x{}", hex::encode(calldata)),
+    }, block]);
+    let result = client.call("eth_call", params).await?;
+    let s = result.as_str().ok_or_else(|| anyhow::anyhow!("eth_call result not string"))?;
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).context("decode eth_call result")
+}
+
+/// `Stem.head()` as of `block`; falls back to `"latest"` if the node rejects the historical
+/// call (no archival state).
+async fn head_at_block(
+    client: &dyn RpcClient,
+    contract_address: &[u8; 20],
+    block: u64,
+) -> Result<CurrentHead> {
+    let tag = format!("0x{:x}", block);
+    let data = match eth_call(client, contract_address, &HEAD_SELECTOR, &tag).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::debug!(reason = %e, block, "historical head() failed, using latest");
+            eth_call(client, contract_address, &HEAD_SELECTOR, "latest").await?
+        }
+    };
+    decode_head_return(&data)
+}
+
+/// Block tag resolved by [block_number_at].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockTag {
+    Latest,
+    Safe,
+    Finalized,
+}
+
+impl BlockTag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockTag::Latest => "latest",
+            BlockTag::Safe => "safe",
+            BlockTag::Finalized => "finalized",
+        }
+    }
+}
+
+/// Run `op` until it succeeds or `policy.max_attempts` is reached, sleeping `policy.backoff(n)`
+/// between attempts.
+async fn with_retry<T, F, Fut>(policy: &RetryConfig, what: &str, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut attempt = 0u32;
+    loop {
+        match op().await {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt + 1 < policy.max_attempts => {
+                tracing::debug!(reason = %e, attempt, "{} failed, retrying", what);
+                sleep(policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Block number for `tag`: `eth_blockNumber` for latest, `eth_getBlockByNumber(tag)` for
+/// safe/finalized. Retries failures per [RetryConfig::default].
+pub async fn block_number_at(client: &reqwest::Client, http_url: &str, tag: BlockTag) -> Result<u64> {
+    let client = &HttpRpcClient::with_client(client.clone(), http_url);
+    with_retry(&RetryConfig::default(), "block number", || async {
+        if tag == BlockTag::Latest {
+            return eth_block_number(client).await;
+        }
+        let block = client.call("eth_getBlockByNumber", json!([tag.as_str(), false])).await?;
+        if block.is_null() {
+            anyhow::bail!("node returned no {} block", tag.as_str());
+        }
+        parse_quantity(&block["number"], "block number")
+    })
+    .await
+}
+
+/// Like [current_block_number], reusing `client` and retrying transient failures.
+pub async fn current_block_number_with(client: &reqwest::Client, http_url: &str) -> Result<u64> {
+    block_number_at(client, http_url, BlockTag::Latest).await
 }
 
 /// Returns the current chain tip (latest block number) via JSON-RPC eth_blockNumber.
@@ -98,184 +220,890 @@ pub async fn current_block_number(http_url: &str) -> Result<u64> {
         .no_proxy()
         .build()
         .expect("reqwest client");
-    eth_block_number(&client, http_url).await
+    current_block_number_with(&client, http_url).await
+}
+
+/// One-shot read of the contract's current `head()` at `"latest"` via JSON-RPC eth_call,
+/// without a subscription or backfill.
+pub async fn fetch_current_head(http_url: &str, contract_address: &[u8; 20]) -> Result<CurrentHead> {
+    let client = HttpRpcClient::new(http_url);
+    let data = eth_call(&client, contract_address, &HEAD_SELECTOR, "latest").await?;
+    decode_head_return(&data)
 }
 
 async fn eth_get_logs(
-    client: &reqwest::Client,
-    http_url: &str,
+    client: &dyn RpcClient,
     filter: Value,
 ) -> Result<Vec<Value>> {
-    let result = http_json_rpc(client, http_url, "eth_getLogs", json!([filter]), 2).await?;
+    let result = client.call("eth_getLogs", json!([filter])).await?;
     let arr = result.as_array().ok_or_else(|| anyhow::anyhow!("getLogs not array"))?;
     Ok(arr.clone())
 }
 
+/// Why a connection attempt of [StemIndexer::run] ended. Public helpers still return
+/// [anyhow::Error]; backfill failures of this type can be recovered with
+/// `err.downcast_ref::<IndexerError>()`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IndexerError {
+    /// The WebSocket could not be opened, or failed / closed while following.
+    #[error("WS connect failed: {0}")]
+    WsConnect(String),
+    /// `eth_subscribe("logs")` was rejected or answered unexpectedly.
+    #[error("subscribe failed: {0}")]
+    Subscribe(String),
+    /// HTTP JSON-RPC failure (transport or `error` object) during backfill.
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    /// A subscription message or log could not be decoded.
+    #[error("decode error: {0}")]
+    Decode(String),
+    /// No subscription confirmation, or no keepalive pong, in time.
+    #[error("timeout: {0}")]
+    Timeout(String),
+    /// [StartFrom::Hash] names a block the node doesn't know (reorged out, or another chain).
+    #[error("start block 0x{} not found", hex::encode(.0))]
+    UnknownStartHash([u8; 32]),
+    /// [IndexerConfig::verify_contract_code]: the contract address is zero or has no code.
+    #[error("no contract deployed at 0x{}", hex::encode(.0))]
+    NoContractCode([u8; 20]),
+    /// One block holds at least [IndexerConfig::getlogs_max_results] HeadUpdated logs, so
+    /// eth_getLogs may have truncated it and there is no cursor within a block to page through.
+    #[error("block {block} has more logs than eth_getLogs returns in one response")]
+    BlockTooDense { block: u64 },
+    /// [StemIndexer::head_history] found a seq out of sequence: missing, repeated or reordered.
+    #[error("head history gap: expected seq {expected}, found {found}")]
+    SeqGap { expected: u64, found: u64 },
+}
+
+impl IndexerError {
+    /// Worth reconnecting promptly: connection, subscription, RPC and timeout failures. Decode,
+    /// unknown-start-hash and over-dense-block failures recur on every attempt.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            IndexerError::WsConnect(_)
+            | IndexerError::Subscribe(_)
+            | IndexerError::Rpc(_)
+            | IndexerError::Timeout(_) => true,
+            IndexerError::Decode(_)
+            | IndexerError::UnknownStartHash(_)
+            | IndexerError::NoContractCode(_)
+            | IndexerError::BlockTooDense { .. }
+            | IndexerError::SeqGap { .. } => false,
+        }
+    }
+
+    /// Classify a backfill failure: an [IndexerError] raised inside it, [IndexerError::Timeout]
+    /// for a timed-out RPC call, else [IndexerError::Rpc].
+    fn from_backfill(e: anyhow::Error) -> Self {
+        e.downcast::<IndexerError>().unwrap_or_else(|e| {
+            if e.chain().any(|c| matches!(c.downcast_ref(), Some(RpcError::Timeout(_)))) {
+                IndexerError::Timeout(format!("{:#}", e))
+            } else {
+                IndexerError::Rpc(format!("{:#}", e))
+            }
+        })
+    }
+}
+
+/// Delay before reconnecting after the `attempt`th consecutive failure: `backoff`'s next delay
+/// for transient failures; the max backoff for the rest.
+fn reconnect_backoff(
+    reconnection: &ReconnectionConfig,
+    backoff: &mut dyn BackoffStrategy,
+    attempt: u32,
+    err: &IndexerError,
+) -> Duration {
+    if err.is_transient() {
+        backoff.next_delay(attempt)
+    } else {
+        Duration::from_secs(reconnection.max_backoff_secs)
+    }
+}
+
+/// Indexer output on [StemIndexer::subscribe_events]: observed events plus cursor rewinds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexerEvent {
+    /// A HeadUpdated log was observed (same payload as [StemIndexer::subscribe]).
+    Head(HeadUpdatedObserved),
+    /// The cursor moved back from `from` to `to`; data for blocks `to + 1..=from` may be reverted.
+    Rewound { from: u64, to: u64 },
+    /// A HeadUpdated log re-emitted by [StemIndexer::replay] (never sent on
+    /// [StemIndexer::subscribe]).
+    Replayed(HeadUpdatedObserved),
+    /// A log of an extra event type, decoded by its [IndexerConfig::decoders] entry.
+    Log(DecodedLog),
+}
+
+/// Point-in-time snapshot of indexer counters and gauges (see [StemIndexer::metrics]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexerMetrics {
+    /// HeadUpdated events broadcast to subscribers (backfill and live; replays excluded).
+    pub events_emitted_total: u64,
+    /// Connection attempts after the first (successful or not).
+    pub reconnects_total: u64,
+    /// Cursor position: last block fully processed.
+    pub last_processed_block: u64,
+    /// Blocks covered by eth_getLogs backfill ranges.
+    pub backfill_blocks_total: u64,
+    /// Logs dropped because the pause buffer was full ([IndexerConfig::pause_buffer_capacity]).
+    pub pause_dropped_total: u64,
+}
+
+/// Where a [HeadSnapshot] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeadSource {
+    /// `head()` read via eth_call after backfill ([IndexerConfig::head_at_backfill_tip]).
+    EthCall,
+    /// A HeadUpdated log.
+    Event,
+}
+
+/// Current HEAD plus when it became current, returned by [StemIndexer::current_head_snapshot].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadSnapshot {
+    pub head: CurrentHead,
+    /// Block of the log, or the block `head()` was called at (the backfill tip; a node without
+    /// archival state answers for `"latest"`, which may be newer).
+    pub observed_block: u64,
+    pub source: HeadSource,
+}
+
+/// Indexer state for warm starts: see [StemIndexer::export_state] and
+/// [StemIndexer::import_state].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexerState {
+    /// Cursor position: last block fully processed.
+    pub last_processed_block: u64,
+    pub current_head: Option<HeadSnapshot>,
+}
+
+/// Backfill progress, reported to [StemIndexer::on_backfill_progress] after each chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackfillProgress {
+    /// First block of this backfill.
+    pub from: u64,
+    /// Last block of this backfill (the tip when it started).
+    pub to: u64,
+    /// Last block backfilled so far.
+    pub current: u64,
+    /// HeadUpdated logs fetched so far in this backfill.
+    pub events_so_far: u64,
+}
+
+/// Callback for [BackfillProgress].
+pub type BackfillProgressFn = Arc<dyn Fn(BackfillProgress) + Send + Sync>;
+
+/// Snapshot returned by [StemIndexer::sync_status].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SyncStatus {
+    pub synced: bool,
+    pub last_processed_block: u64,
+    pub current_head_seq: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    events_emitted_total: AtomicU64,
+    reconnects_total: AtomicU64,
+    last_processed_block: AtomicU64,
+    backfill_blocks_total: AtomicU64,
+    pause_dropped_total: AtomicU64,
+}
+
+/// Recently published logs remembered for dedup across the backfill/subscription overlap.
+const SEEN_LOGS_CAPACITY: usize = 1024;
+
+/// A tip behind the cursor must be reported this many times in a row, [REWIND_CONFIRM_INTERVAL]
+/// apart, before the cursor is rewound; a single lagging node doesn't count as a reorg.
+const REWIND_CONFIRM_POLLS: u32 = 3;
+const REWIND_CONFIRM_INTERVAL: Duration = Duration::from_millis(250);
+
+/// With [IndexerConfig::reorder_window], release all held logs once the WebSocket has been
+/// quiet this long.
+const REORDER_IDLE_FLUSH: Duration = Duration::from_millis(500);
+
+/// Live logs held for [IndexerConfig::reorder_window], keyed by `(block_number, log_index)`.
+struct ReorderBuffer {
+    window: usize,
+    logs: BTreeMap<(u64, u64), Fetched>,
+}
+
+impl ReorderBuffer {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            logs: BTreeMap::new(),
+        }
+    }
+
+    fn push(&mut self, fetched: Fetched) {
+        self.logs.insert(fetched.position(), fetched);
+    }
+
+    /// Remove and return, oldest first, the logs beyond the window (every log with `all`).
+    fn take_ready(&mut self, all: bool) -> Vec<Fetched> {
+        let keep = if all { 0 } else { self.window };
+        let mut ready = Vec::new();
+        while self.logs.len() > keep {
+            ready.extend(self.logs.pop_first().map(|(_, f)| f));
+        }
+        ready
+    }
+
+    /// Block of the oldest held log.
+    fn first_block(&self) -> Option<u64> {
+        self.logs.first_key_value().map(|((block, _), _)| *block)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.logs.is_empty()
+    }
+}
+
+/// Publish the logs `reorder` releases, advancing the cursor past each one but never past a
+/// block it still holds a log for (so a reconnect backfills those).
+async fn release_reordered(indexer: &StemIndexer, cursor: &mut Cursor, reorder: &mut ReorderBuffer, all: bool) {
+    let ready = reorder.take_ready(all);
+    let held_from = reorder.first_block().unwrap_or(u64::MAX);
+    for fetched in ready {
+        let processed = cursor.last_processed_block.max(fetched.position().0.min(held_from.saturating_sub(1)));
+        indexer.set_cursor(cursor, processed);
+        indexer.publish_fetched(fetched).await;
+    }
+}
+
+/// Bounded set of recently published `(tx_hash, log_index)` keys; the oldest entry is evicted
+/// once `capacity` is reached.
+#[derive(Debug)]
+struct SeenLogs {
+    capacity: usize,
+    blocks: HashMap<([u8; 32], u64), u64>,
+    order: VecDeque<([u8; 32], u64)>,
+}
+
+impl SeenLogs {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            blocks: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Record `observed`; returns false if it was already seen.
+    fn insert(&mut self, observed: &HeadUpdatedObserved) -> bool {
+        self.insert_key((observed.tx_hash, observed.log_index), observed.block_number)
+    }
+
+    /// Record the log at `(tx_hash, log_index)` in `block`; returns false if it was already seen.
+    fn insert_key(&mut self, key: ([u8; 32], u64), block: u64) -> bool {
+        if self.blocks.contains_key(&key) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.blocks.remove(&old);
+            }
+        }
+        self.blocks.insert(key, block);
+        self.order.push_back(key);
+        true
+    }
+
+    /// Forget logs above `block` so a re-included transaction is published again after a rewind.
+    fn forget_after(&mut self, block: u64) {
+        self.blocks.retain(|_, b| *b <= block);
+        let blocks = &self.blocks;
+        self.order.retain(|k| blocks.contains_key(k));
+    }
+}
+
 /// Stem indexer: follows HeadUpdated logs, backfills via HTTP, maintains current HEAD.
 pub struct StemIndexer {
     config: IndexerConfig,
     event_tx: broadcast::Sender<HeadUpdatedObserved>,
-    current_head: Arc<RwLock<Option<CurrentHead>>>,
+    indexer_event_tx: broadcast::Sender<IndexerEvent>,
+    head_preview_tx: broadcast::Sender<HeadUpdatedObserved>,
+    current_head: Arc<RwLock<Option<HeadSnapshot>>>,
+    metrics: Metrics,
+    seen: Mutex<SeenLogs>,
+    synced: AtomicBool,
+    /// Set once a [StartFrom::Hash] start has been resolved and the cursor placed before it.
+    start_resolved: AtomicBool,
+    /// Set after the first completed catch-up; [IndexerConfig::max_backfill_blocks] only caps
+    /// the one before it.
+    initial_backfill_done: AtomicBool,
+    /// Set by [Self::pause]; while set, logs are held in `paused_buffer` instead of published.
+    paused: AtomicBool,
+    paused_buffer: Mutex<VecDeque<Fetched>>,
+    /// Cursor from [Self::import_state], taken by the next [Self::run] in place of `start_block`.
+    imported_cursor: Mutex<Option<u64>>,
+    /// HTTP JSON-RPC transport.
+    rpc: Arc<dyn RpcClient>,
+    /// Next JSON-RPC id for WebSocket requests (`eth_subscribe`).
+    ws_request_id: AtomicU64,
+    /// Address `config.contract_name` resolved to, once resolved.
+    resolved_address: std::sync::OnceLock<[u8; 20]>,
+    on_backfill_progress: Option<BackfillProgressFn>,
 }
 
 impl StemIndexer {
     pub fn new(config: IndexerConfig) -> Self {
+        let http = config.http_client.clone().unwrap_or_else(|| http_client_for(&config));
+        let rpc = Arc::new(HttpRpcClient::with_client(http, config.http_url.clone()));
+        Self::with_rpc_client(config, rpc)
+    }
+
+    /// Like [Self::new], but send HTTP JSON-RPC (backfill, tip, `head()`) through `rpc` instead
+    /// of `config.http_url` (e.g. `MockRpcClient` from the `test-util` feature). The live subscription
+    /// still uses `config.ws_url`.
+    pub fn with_rpc_client(config: IndexerConfig, rpc: Arc<dyn RpcClient>) -> Self {
         let (event_tx, _) = broadcast::channel(256);
+        let (indexer_event_tx, _) = broadcast::channel(256);
+        let (head_preview_tx, _) = broadcast::channel(16);
         Self {
             config,
             event_tx,
+            indexer_event_tx,
+            head_preview_tx,
             current_head: Arc::new(RwLock::new(None)),
+            metrics: Metrics::default(),
+            seen: Mutex::new(SeenLogs::new(SEEN_LOGS_CAPACITY)),
+            synced: AtomicBool::new(false),
+            start_resolved: AtomicBool::new(false),
+            initial_backfill_done: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            paused_buffer: Mutex::new(VecDeque::new()),
+            imported_cursor: Mutex::new(None),
+            rpc,
+            ws_request_id: AtomicU64::new(1),
+            resolved_address: std::sync::OnceLock::new(),
+            on_backfill_progress: None,
         }
     }
 
+    /// Call `f` after each eth_getLogs chunk of every backfill (startup, reconnect and poll
+    /// catch-ups), e.g. to drive a progress bar. Runs inline, so keep it cheap.
+    pub fn on_backfill_progress(mut self, f: impl Fn(BackfillProgress) + Send + Sync + 'static) -> Self {
+        self.on_backfill_progress = Some(Arc::new(f));
+        self
+    }
+
     /// Subscribe to observed HeadUpdated events (ordered by block_number, log_index).
+    ///
+    /// Does not report rewinds; use [Self::subscribe_events] to also see [IndexerEvent::Rewound].
     pub fn subscribe(&self) -> broadcast::Receiver<HeadUpdatedObserved> {
         self.event_tx.subscribe()
     }
 
+    /// Subscribe to all indexer output: every observed event as [IndexerEvent::Head] and
+    /// cursor rewinds as [IndexerEvent::Rewound], in emission order.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<IndexerEvent> {
+        self.indexer_event_tx.subscribe()
+    }
+
+    /// Subscribe to head previews: with [EmitOrder::LatestFirst], the newest event near the tip,
+    /// sent before each catch-up backfill begins. The same event is delivered again, in order,
+    /// on [Self::subscribe] once backfill reaches it.
+    pub fn subscribe_head_preview(&self) -> broadcast::Receiver<HeadUpdatedObserved> {
+        self.head_preview_tx.subscribe()
+    }
+
+    /// Whether the indexer has caught up to the tip and is following live, with its cursor and
+    /// current HEAD seq. `synced` is false until the first backfill completes and again while
+    /// reconnecting.
+    pub async fn sync_status(&self) -> SyncStatus {
+        SyncStatus {
+            synced: self.synced.load(Ordering::Relaxed),
+            last_processed_block: self.metrics.last_processed_block.load(Ordering::Relaxed),
+            current_head_seq: self.current_head().await.map(|h| h.seq),
+        }
+    }
+
+    /// Snapshot of the indexer's counters and gauges.
+    pub fn metrics(&self) -> IndexerMetrics {
+        let m = &self.metrics;
+        IndexerMetrics {
+            events_emitted_total: m.events_emitted_total.load(Ordering::Relaxed),
+            reconnects_total: m.reconnects_total.load(Ordering::Relaxed),
+            last_processed_block: m.last_processed_block.load(Ordering::Relaxed),
+            backfill_blocks_total: m.backfill_blocks_total.load(Ordering::Relaxed),
+            pause_dropped_total: m.pause_dropped_total.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop delivering events without dropping the connection. The indexer keeps following the
+    /// chain and advancing its cursor, holding new logs until [Self::resume]. Past
+    /// [IndexerConfig::pause_buffer_capacity] the oldest held logs are dropped and lost (the
+    /// cursor is already past them); [IndexerMetrics::pause_dropped_total] counts them.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Deliver the logs held since [Self::pause], in order, then resume live delivery.
+    pub async fn resume(&self) {
+        loop {
+            let batch: Vec<Fetched> = {
+                let mut buffer = self.paused_buffer.lock().unwrap();
+                if buffer.is_empty() {
+                    self.paused.store(false, Ordering::Relaxed);
+                    return;
+                }
+                buffer.drain(..).collect()
+            };
+            for fetched in batch {
+                self.deliver(fetched).await;
+            }
+        }
+    }
+
+    /// Whether [Self::pause] is in effect.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    fn set_cursor(&self, cursor: &mut Cursor, block: u64) {
+        cursor.last_processed_block = block;
+        self.metrics.last_processed_block.store(block, Ordering::Relaxed);
+    }
+
+    /// Broadcast an observed event on both channels and advance current HEAD. Logs already
+    /// published (same `tx_hash` and `log_index`, e.g. delivered by both backfill and the live
+    /// subscription) are skipped, as are events failing [Self::accepts].
+    async fn publish(&self, observed: HeadUpdatedObserved) {
+        let observed = self.labeled(observed);
+        if !self.accepts(&observed) {
+            return;
+        }
+        if !self.seen.lock().unwrap().insert(&observed) {
+            tracing::debug!(block = observed.block_number, log_index = observed.log_index, "skipping duplicate log");
+            return;
+        }
+        self.metrics.events_emitted_total.fetch_add(1, Ordering::Relaxed);
+        let _ = self.event_tx.send(observed.clone());
+        let _ = self.indexer_event_tx.send(IndexerEvent::Head(observed.clone()));
+        set_current_head_if_newer(
+            &self.current_head,
+            HeadSnapshot {
+                head: CurrentHead {
+                    seq: observed.seq,
+                    cid: observed.cid,
+                },
+                observed_block: observed.block_number,
+                source: HeadSource::Event,
+            },
+        ).await;
+    }
+
+    /// Deliver a log of an extra event type on [Self::subscribe_events], once.
+    fn publish_log(&self, log: DecodedLog) {
+        if !self.seen.lock().unwrap().insert_key((log.tx_hash, log.log_index), log.block_number) {
+            tracing::debug!(block = log.block_number, log_index = log.log_index, "skipping duplicate log");
+            return;
+        }
+        let _ = self.indexer_event_tx.send(IndexerEvent::Log(log));
+    }
+
+    /// Publish a fetched log of either kind, or hold it while paused.
+    async fn publish_fetched(&self, fetched: Fetched) {
+        {
+            let mut buffer = self.paused_buffer.lock().unwrap();
+            if self.paused.load(Ordering::Relaxed) {
+                buffer.push_back(fetched);
+                while buffer.len() > self.config.pause_buffer_capacity {
+                    let (block, log_index) = buffer.pop_front().map(|f| f.position()).unwrap_or_default();
+                    self.metrics.pause_dropped_total.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(block, log_index, "pause buffer full; dropping oldest log");
+                }
+                return;
+            }
+        }
+        self.deliver(fetched).await;
+    }
+
+    async fn deliver(&self, fetched: Fetched) {
+        match fetched {
+            Fetched::Head(observed) => self.publish(observed).await,
+            Fetched::Log(log) => self.publish_log(log),
+        }
+    }
+
+    /// Stamp `observed` with [IndexerConfig::source_label].
+    fn labeled(&self, mut observed: HeadUpdatedObserved) -> HeadUpdatedObserved {
+        observed.source_label = self.config.source_label.clone();
+        observed
+    }
+
+    /// With [IndexerConfig::verify_cid_hash], reject (and warn about) events whose `cid_hash`
+    /// doesn't match their `cid`.
+    fn accepts(&self, observed: &HeadUpdatedObserved) -> bool {
+        if self.config.verify_cid_hash && !observed.cid_hash_matches() {
+            tracing::warn!(
+                seq = observed.seq,
+                block = observed.block_number,
+                log_index = observed.log_index,
+                "dropping event: cid_hash != keccak256(cid)"
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Re-fetch HeadUpdated logs in `[from, to]` and broadcast them again, in order, as
+    /// [IndexerEvent::Replayed] on [Self::subscribe_events] only. Runs alongside a live
+    /// [Self::run] without touching its cursor, dedup set, metrics or current HEAD.
+    pub async fn replay(&self, from: u64, to: u64) -> Result<()> {
+        let config = &self.resolved_config().await?;
+        let mut results = fetch_range(
+            self.rpc.as_ref(),
+            &config.contract_address,
+            from,
+            to,
+            config.getlogs_max_range,
+            config.getlogs_concurrency,
+            GetLogsOptions::from(config),
+        );
+        while let Some(chunk) = results.next().await {
+            let (_, _, fetched) = chunk?;
+            let observed = fetched.into_iter().filter_map(Fetched::into_head);
+            for o in observed.filter(|o| self.accepts(o)).map(|o| self.labeled(o)) {
+                let _ = self.indexer_event_tx.send(IndexerEvent::Replayed(o));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every head published in blocks `from..=to`, in seq order, without touching the cursor or
+    /// publishing anything. Fails with [IndexerError::SeqGap] unless each seq is exactly one more
+    /// than the previous, so a returned history has no missing (or repeated) updates.
+    pub async fn head_history(&self, from: u64, to: u64) -> Result<Vec<CurrentHead>> {
+        let config = &self.resolved_config().await?;
+        let mut results = fetch_range(
+            self.rpc.as_ref(),
+            &config.contract_address,
+            from,
+            to,
+            config.getlogs_max_range,
+            config.getlogs_concurrency,
+            GetLogsOptions::from(config),
+        );
+        let mut heads = Vec::new();
+        while let Some(chunk) = results.next().await {
+            let (_, _, fetched) = chunk?;
+            heads.extend(
+                fetched
+                    .into_iter()
+                    .filter_map(Fetched::into_head)
+                    .filter(|o| self.accepts(o))
+                    .map(|o| CurrentHead { seq: o.seq, cid: o.cid }),
+            );
+        }
+        heads.sort_by_key(|h| h.seq);
+        for pair in heads.windows(2) {
+            if pair[1].seq != pair[0].seq + 1 {
+                return Err(IndexerError::SeqGap { expected: pair[0].seq + 1, found: pair[1].seq }.into());
+            }
+        }
+        Ok(heads)
+    }
+
+    /// One-shot indexing: backfill from `start_block` to the current tip, publishing every event
+    /// in order as [Self::run] would, then return. Opens no WebSocket and doesn't retry; the first
+    /// failure is returned.
+    pub async fn backfill_once(&self) -> Result<()> {
+        let config = self.resolved_config().await?;
+        let start = match config.start_block {
+            StartFrom::Number(start) => start,
+            StartFrom::Hash(hash) => resolve_start_hash(self.rpc.as_ref(), hash).await?,
+        };
+        let mut cursor = Cursor::default();
+        self.set_cursor(&mut cursor, start.saturating_sub(1));
+        catch_up(self, self.rpc.as_ref(), &mut cursor, &config).await
+    }
+
+    /// The config with `contract_address` set from `contract_name`, resolving the name on first
+    /// use.
+    async fn resolved_config(&self) -> Result<IndexerConfig> {
+        let mut config = self.config.clone();
+        if let Some(name) = &self.config.contract_name {
+            config.contract_address = match self.resolved_address.get() {
+                Some(addr) => *addr,
+                None => {
+                    let addr = (name.resolver)(&name.name)
+                        .await
+                        .with_context(|| format!("failed to resolve contract name {:?}", name.name))?;
+                    tracing::info!(name = %name.name, address = %format!("0x{}", hex::encode(addr)), "resolved contract name");
+                    *self.resolved_address.get_or_init(|| addr)
+                }
+            };
+        }
+        Ok(config)
+    }
+
+    /// Move the cursor back to `to` and notify subscribers. No-op unless `to` is behind the cursor.
+    fn rewind(&self, cursor: &mut Cursor, to: u64) {
+        let from = cursor.last_processed_block;
+        if to >= from {
+            return;
+        }
+        tracing::warn!(from, to, "rewinding cursor");
+        self.set_cursor(cursor, to);
+        self.seen.lock().unwrap().forget_after(to);
+        let _ = self.indexer_event_tx.send(IndexerEvent::Rewound { from, to });
+    }
+
+    /// Chain id (`eth_chainId`) of the HTTP RPC the indexer backfills from.
+    pub async fn chain_id(&self) -> Result<u64> {
+        eth_chain_id(self.rpc.as_ref()).await
+    }
+
+    /// Call a view function on the Stem contract at `"latest"` through the indexer's RPC
+    /// client, e.g. `owner()`. Calldata is `selector` followed by the ABI-encoded `args`;
+    /// decoding the returned bytes is up to the caller.
+    pub async fn eth_call(&self, selector: &[u8], args: &[u8]) -> Result<Vec<u8>> {
+        let config = self.resolved_config().await?;
+        let calldata = [selector, args].concat();
+        eth_call(self.rpc.as_ref(), &config.contract_address, &calldata, "latest").await
+    }
+
     /// Current HEAD (from head() or latest event). None until first update.
     pub async fn current_head(&self) -> Option<CurrentHead> {
+        self.current_head.read().await.as_ref().map(|s| s.head.clone())
+    }
+
+    /// Current HEAD with the block it was observed at and its source. None until first update.
+    pub async fn current_head_snapshot(&self) -> Option<HeadSnapshot> {
         self.current_head.read().await.clone()
     }
 
+    /// Snapshot the cursor and current HEAD, e.g. to persist across deployments and restore
+    /// with [Self::import_state].
+    pub async fn export_state(&self) -> IndexerState {
+        IndexerState {
+            last_processed_block: self.metrics.last_processed_block.load(Ordering::Relaxed),
+            current_head: self.current_head_snapshot().await,
+        }
+    }
+
+    /// Seed the cursor and current HEAD from an [Self::export_state] snapshot. Call before
+    /// [Self::run]: it then resumes after `state.last_processed_block` instead of backfilling
+    /// from `start_block` (neither [StartFrom::Hash] nor [IndexerConfig::max_backfill_blocks]
+    /// applies).
+    pub async fn import_state(&self, state: IndexerState) {
+        self.metrics.last_processed_block.store(state.last_processed_block, Ordering::Relaxed);
+        *self.imported_cursor.lock().unwrap() = Some(state.last_processed_block);
+        self.start_resolved.store(true, Ordering::Relaxed);
+        self.initial_backfill_done.store(true, Ordering::Relaxed);
+        *self.current_head.write().await = state.current_head;
+    }
+
     /// Run the indexer (blocking on the async loop). Call from a spawned task.
+    ///
+    /// Reconnects on failure; returns `Err` only once `reconnection.max_attempts` consecutive
+    /// connections have failed (never, when unset), or if `contract_name` can't be resolved.
     pub async fn run(self: Arc<Self>) -> Result<()> {
-        let config = &self.config;
-        let http_client = reqwest::Client::builder()
-            .no_proxy()
-            .build()
-            .expect("reqwest client");
-        let mut cursor = Cursor::new(config.start_block.saturating_sub(1));
+        let config = &self.resolved_config().await?;
+        let http_client = Arc::clone(&self.rpc);
+        if config.verify_contract_code {
+            check_contract_code(http_client.as_ref(), &config.contract_address).await?;
+        }
+        let mut cursor = Cursor::default();
+        let imported = self.imported_cursor.lock().unwrap().take();
+        match (imported, config.start_block) {
+            (Some(block), _) => self.set_cursor(&mut cursor, block),
+            (None, StartFrom::Number(start)) => self.set_cursor(&mut cursor, start.saturating_sub(1)),
+            (None, StartFrom::Hash(_)) => {}
+        }
         let reconnection = config.reconnection.clone();
+        let mut backoff = config
+            .backoff
+            .clone()
+            .unwrap_or_else(|| Box::new(Exponential::from(&reconnection)));
+        let mut failures = 0u32;
+        // Every event from this indexer carries its contract and (once known) chain id.
+        let span = tracing::info_span!(
+            "stem_indexer",
+            contract = %format!("0x{}", hex::encode(config.contract_address)),
+            chain_id = tracing::field::Empty,
+        );
+        let mut chain_id = None;
 
         loop {
+            if chain_id.is_none() {
+                match eth_chain_id(http_client.as_ref()).instrument(span.clone()).await {
+                    Ok(id) => {
+                        span.record("chain_id", id);
+                        chain_id = Some(id);
+                    }
+                    Err(e) => tracing::debug!(parent: &span, reason = %e, "eth_chainId failed, retrying on reconnect"),
+                }
+            }
             match run_once(
                 Arc::clone(&self),
-                &http_client,
+                http_client.as_ref(),
                 &mut cursor,
                 config,
-            ).await {
+            ).instrument(span.clone()).await {
                 Ok(()) => {
+                    failures = 0;
+                    backoff.reset();
                     sleep(Duration::from_secs(reconnection.initial_backoff_secs)).await;
                 }
                 Err(e) => {
-                    tracing::warn!(reason = %e, "StemIndexer failed, reconnecting...");
-                    let base = std::cmp::min(
-                        Duration::from_secs(reconnection.initial_backoff_secs) * 2,
-                        Duration::from_secs(reconnection.max_backoff_secs),
-                    );
-                    let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..500));
-                    sleep(base + jitter).await;
+                    failures += 1;
+                    if reconnection.max_attempts.is_some_and(|max| failures >= max) {
+                        return Err(anyhow::Error::new(e)
+                            .context(format!("StemIndexer gave up after {} consecutive failures", failures)));
+                    }
+                    tracing::warn!(parent: &span, reason = %e, transient = e.is_transient(), "StemIndexer failed, reconnecting...");
+                    sleep(reconnect_backoff(&reconnection, backoff.as_mut(), failures, &e)).await;
                 }
             }
+            self.metrics.reconnects_total.fetch_add(1, Ordering::Relaxed);
         }
     }
 }
 
 async fn run_once(
     indexer: Arc<StemIndexer>,
-    http_client: &reqwest::Client,
+    http_client: &dyn RpcClient,
     cursor: &mut Cursor,
     config: &IndexerConfig,
-) -> Result<()> {
+) -> Result<(), IndexerError> {
+    indexer.synced.store(false, Ordering::Relaxed);
+    if let StartFrom::Hash(hash) = config.start_block {
+        if !indexer.start_resolved.load(Ordering::Relaxed) {
+            let start = resolve_start_hash(http_client, hash).await?;
+            indexer.set_cursor(cursor, start.saturating_sub(1));
+            indexer.start_resolved.store(true, Ordering::Relaxed);
+        }
+    }
+    if let TransportMode::HttpPoll { interval } = config.transport {
+        // No subscription: each tick backfills (cursor, latest], so ranges never overlap and
+        // events arrive in (block_number, log_index) order exactly as on the WS path.
+        loop {
+            sync_to_tip(&indexer, http_client, cursor, config)
+                .await
+                .map_err(IndexerError::from_backfill)?;
+            indexer.synced.store(true, Ordering::Relaxed);
+            tokio::time::sleep(interval).await;
+        }
+    }
+
     // Subscribe first so WS buffers events while backfill runs (no missed-event gap).
-    let ws_url = &config.ws_url;
-    let (ws_stream, _) = connect_async(ws_url).await.context("WS connect")?;
+    let ws_stream = connect_ws(config).await?;
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
-    let logs_id = 1u64;
-    let filter = build_logs_filter(
-        &config.contract_address,
-        Some(&HEAD_UPDATED_TOPIC0),
-        None,
+    // Nodes that reject the topic filter (e.g. Anvil) get an address-only filter, then an
+    // unfiltered subscription; both fall back to client-side filtering.
+    let ladder = [
+        Some(build_logs_filter(
+            &config.contract_address,
+            &GetLogsOptions::from(config).topics0(),
+            None,
+            None,
+        )),
+        Some(build_logs_filter_address_only(&config.contract_address, None, None)),
         None,
-    );
-    let sub_req = json!({
-        "jsonrpc": "2.0",
-        "id": logs_id,
-        "method": "eth_subscribe",
-        "params": ["logs", filter]
-    });
-    ws_sender
-        .send(Message::Text(serde_json::to_string(&sub_req)?))
-        .await
-        .map_err(|e| anyhow::anyhow!("send subscribe: {}", e))?;
-
-    let (sub_id, needs_client_filter) = match timeout(Duration::from_secs(10), ws_receiver.next()).await {
-        Ok(Some(Ok(Message::Text(text)))) => {
-            let v: Value = serde_json::from_str(&text).context("parse sub response")?;
-            if v.get("error").is_some() {
-                let err = v["error"].get("message").and_then(|m| m.as_str()).unwrap_or("");
-                if err.contains("data did not match") || err.contains("variant") {
-                    tracing::warn!("RPC does not support logs filter (Anvil?), using client-side filter");
-                    let sub_req_no_filter = json!({
-                        "jsonrpc": "2.0",
-                        "id": logs_id,
-                        "method": "eth_subscribe",
-                        "params": ["logs"]
-                    });
-                    ws_sender
-                        .send(Message::Text(serde_json::to_string(&sub_req_no_filter)?))
-                        .await
-                        .map_err(|e| anyhow::anyhow!("send subscribe: {}", e))?;
-                    let text2 = timeout(Duration::from_secs(10), ws_receiver.next())
-                        .await
-                        .map_err(|_| anyhow::anyhow!("subscribe timeout"))?
-                        .ok_or_else(|| anyhow::anyhow!("ws closed"))?
-                        .map_err(|e| anyhow::anyhow!("ws: {}", e))?;
-                    let msg = match text2 {
-                        Message::Text(t) => t,
-                        _ => anyhow::bail!("expected text"),
-                    };
-                    let v2: Value = serde_json::from_str(&msg)?;
-                    let id = v2["result"].as_str().ok_or_else(|| anyhow::anyhow!("no sub id"))?.to_string();
-                    (id, true)
-                } else {
-                    anyhow::bail!("subscribe error: {}", err);
-                }
+    ];
+    let mut subscribed = None;
+    for (rung, filter) in ladder.into_iter().enumerate() {
+        let id = indexer.ws_request_id.fetch_add(1, Ordering::Relaxed);
+        let params = match filter {
+            Some(filter) => json!(["logs", filter]),
+            None => json!(["logs"]),
+        };
+        let sub_req = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "eth_subscribe",
+            "params": params
+        });
+        ws_sender
+            .send(Message::Text(sub_req.to_string()))
+            .await
+            .map_err(|e| IndexerError::Subscribe(format!("send subscribe: {}", e)))?;
+        let v = await_ws_response(&mut ws_receiver, id, config.request_timeout).await?;
+        if let Some(error) = v.get("error") {
+            let err = error.get("message").and_then(|m| m.as_str()).unwrap_or("");
+            if !(err.contains("data did not match") || err.contains("variant")) {
+                return Err(IndexerError::Subscribe(err.to_string()));
+            }
+            if rung == 0 {
+                tracing::warn!("RPC does not support topic filter (Anvil?), trying address-only filter");
             } else {
-                let id = v["result"].as_str().ok_or_else(|| anyhow::anyhow!("no result"))?.to_string();
-                (id, false)
+                tracing::warn!("RPC does not support logs filter, using client-side filter");
             }
+            continue;
         }
-        Ok(Some(Ok(_))) => anyhow::bail!("unexpected message"),
-        Ok(Some(Err(e))) => return Err(anyhow::anyhow!("ws: {}", e)),
-        Ok(None) => anyhow::bail!("ws closed"),
-        Err(_) => anyhow::bail!("subscribe timeout"),
-    };
-    let _ = sub_id;
-
-    // Backfill after subscribe so the WS stream buffers any events arriving in between.
-    let from_block = cursor.last_processed_block + 1;
-    let tip = eth_block_number(http_client, &config.http_url).await?;
-    if from_block <= tip {
-        backfill(
-            http_client,
-            &config.http_url,
-            &config.contract_address,
-            from_block,
-            tip,
-            config.getlogs_max_range,
-            &indexer.event_tx,
-            &indexer.current_head,
-        ).await?;
-        cursor.last_processed_block = tip;
+        let sub = v["result"]
+            .as_str()
+            .ok_or_else(|| IndexerError::Subscribe("no sub id".into()))?
+            .to_string();
+        subscribed = Some((sub, rung > 0));
+        break;
     }
+    let (sub_id, needs_client_filter) =
+        subscribed.ok_or_else(|| IndexerError::Subscribe("no logs subscription accepted".into()))?;
+    // Backfill after subscribe so the WS stream buffers any events arriving in between.
+    sync_to_tip(&indexer, http_client, cursor, config)
+        .await
+        .map_err(IndexerError::from_backfill)?;
+    indexer.synced.store(true, Ordering::Relaxed);
+
+    // Keepalive: ping on an interval while idle; a missing pong means the connection is dead.
+    let keepalive = config.ping_interval_secs > 0;
+    let mut ping = tokio::time::interval(Duration::from_secs(config.ping_interval_secs.max(1)));
+    ping.tick().await;
+    let mut pong_deadline: Option<Instant> = None;
+    let mut reorder = ReorderBuffer::new(config.reorder_window);
 
-    while let Some(msg) = ws_receiver.next().await {
-        let text = match msg.map_err(|e| anyhow::anyhow!("ws: {}", e))? {
+    loop {
+        let msg = tokio::select! {
+            msg = ws_receiver.next() => match msg {
+                Some(m) => m,
+                None => break,
+            },
+            _ = sleep(REORDER_IDLE_FLUSH), if !reorder.is_empty() => {
+                release_reordered(&indexer, cursor, &mut reorder, true).await;
+                continue;
+            }
+            _ = ping.tick(), if keepalive && pong_deadline.is_none() => {
+                ws_sender
+                    .send(Message::Ping(Vec::new()))
+                    .await
+                    .map_err(|e| IndexerError::WsConnect(format!("send ping: {}", e)))?;
+                pong_deadline = Some(Instant::now() + Duration::from_secs(config.pong_timeout_secs));
+                continue;
+            }
+            _ = sleep_until(pong_deadline.unwrap_or_else(Instant::now)), if pong_deadline.is_some() => {
+                return Err(IndexerError::Timeout(format!("keepalive: no pong within {}s", config.pong_timeout_secs)));
+            }
+        };
+        let text = match msg.map_err(|e| IndexerError::WsConnect(e.to_string()))? {
             Message::Text(t) => t,
+            Message::Pong(_) => {
+                pong_deadline = None;
+                continue;
+            }
             Message::Close(_) => break,
             _ => continue,
         };
-        let v: Value = serde_json::from_str(&text).context("parse ws message")?;
+        let v: Value = serde_json::from_str(&text)
+            .map_err(|e| IndexerError::Decode(format!("parse ws message: {}", e)))?;
         if v.get("method").and_then(|m| m.as_str()) != Some("eth_subscription") {
             continue;
         }
+        // Notifications for another (e.g. the superseded filtered) subscription.
+        let subscription = v.get("params").and_then(|p| p.get("subscription")).and_then(|s| s.as_str());
+        if subscription != Some(sub_id.as_str()) {
+            tracing::debug!(?subscription, active = %sub_id, "ignoring notification for another subscription");
+            continue;
+        }
         let result = v
             .get("params")
             .and_then(|p| p.get("result"))
-            .ok_or_else(|| anyhow::anyhow!("no params.result"))?;
+            .ok_or_else(|| IndexerError::Decode("no params.result".into()))?;
         if needs_client_filter {
             let addr = match result.get("address").and_then(|a| a.as_str()) {
                 Some(a) => a,
@@ -290,140 +1118,2074 @@ async fn run_once(
             if addr_20 != config.contract_address {
                 continue;
             }
-            let topics = result.get("topics").and_then(|t| t.as_array());
-            let topic0 = match topics.and_then(|t| t.first()).and_then(|t| t.as_str()) {
-                Some(s) => hex::decode(s.strip_prefix("0x").unwrap_or(s)).ok(),
-                _ => continue,
-            };
-            let topic0_4 = match topic0.as_ref().filter(|b| b.len() >= 4) {
-                Some(b) => [b[0], b[1], b[2], b[3]],
-                _ => continue,
-            };
-            if topic0_4 != HEAD_UPDATED_TOPIC0 {
+            if !GetLogsOptions::from(config).follows(result) {
                 continue;
             }
         }
-        let observed = decode_log_to_observed(result).context("decode log")?;
-        cursor.last_processed_block = cursor.last_processed_block.max(observed.block_number);
-        let _ = indexer.event_tx.send(observed.clone());
-        set_current_head_if_newer(
-            &indexer.current_head,
-            CurrentHead {
-                seq: observed.seq,
-                cid: observed.cid,
-            },
-        ).await;
+        let fetched = match decode_fetched(result, GetLogsOptions::from(config)) {
+            Ok(fetched) => fetched,
+            // Mempool log: it is delivered again (with a block) once mined.
+            Err(e) if e.is::<PendingLog>() => {
+                tracing::trace!("ignoring pending log");
+                continue;
+            }
+            Err(e) if e.is::<CidIndexed>() => {
+                tracing::warn!(reason = %e, "skipping log");
+                continue;
+            }
+            Err(e) => return Err(IndexerError::Decode(format!("decode log: {:#}", e))),
+        };
+        reorder.push(fetched);
+        release_reordered(&indexer, cursor, &mut reorder, false).await;
     }
+    release_reordered(&indexer, cursor, &mut reorder, true).await;
     Ok(())
 }
 
-fn log_matches_head_updated(log: &Value) -> bool {
-    let topics = match log.get("topics").and_then(|t| t.as_array()) {
-        Some(t) if !t.is_empty() => t,
-        _ => return false,
-    };
-    let t0 = match topics[0].as_str() {
-        Some(s) => s,
-        None => return false,
-    };
-    let bytes = match hex::decode(t0.strip_prefix("0x").unwrap_or(t0)) {
-        Ok(b) if b.len() >= 4 => b,
-        _ => return false,
+/// Default HTTP client for `config`: no proxy, `request_timeout`, and
+/// [IndexerConfig::root_certificates] added to the trusted roots.
+fn http_client_for(config: &IndexerConfig) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder().no_proxy();
+    if !config.request_timeout.is_zero() {
+        builder = builder.timeout(config.request_timeout);
+    }
+    for pem in &config.root_certificates {
+        match reqwest::Certificate::from_pem(pem) {
+            Ok(cert) => builder = builder.add_root_certificate(cert),
+            Err(e) => tracing::warn!(reason = %e, "ignoring invalid root certificate"),
+        }
+    }
+    builder.build().expect("reqwest client")
+}
+
+/// Open `config.ws_url` within `request_timeout`; `wss://` also trusts
+/// [IndexerConfig::root_certificates].
+async fn connect_ws(config: &IndexerConfig) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, IndexerError> {
+    let connector = if config.root_certificates.is_empty() {
+        None
+    } else {
+        let mut tls = native_tls::TlsConnector::builder();
+        for pem in &config.root_certificates {
+            let cert = native_tls::Certificate::from_pem(pem)
+                .map_err(|e| IndexerError::WsConnect(format!("root certificate: {}", e)))?;
+            tls.add_root_certificate(cert);
+        }
+        let tls = tls.build().map_err(|e| IndexerError::WsConnect(format!("tls: {}", e)))?;
+        Some(Connector::NativeTls(tls))
     };
-    bytes[..4] == HEAD_UPDATED_TOPIC0
+    let (ws_stream, _) = timeout(
+        config.request_timeout,
+        connect_async_tls_with_config(&config.ws_url, None, false, connector),
+    )
+    .await
+    .map_err(|_| IndexerError::Timeout("connect".into()))?
+    .map_err(|e| IndexerError::WsConnect(e.to_string()))?;
+    Ok(ws_stream)
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn backfill(
-    client: &reqwest::Client,
-    http_url: &str,
-    contract_address: &[u8; 20],
-    from_block: u64,
-    to_block: u64,
-    max_range: u64,
-    event_tx: &broadcast::Sender<HeadUpdatedObserved>,
-    current_head: &Arc<RwLock<Option<CurrentHead>>>,
+/// Block number for a [StartFrom::Hash] start.
+async fn resolve_start_hash(client: &dyn RpcClient, hash: [u8; 32]) -> Result<u64, IndexerError> {
+    let start = eth_block_number_by_hash(client, &hash)
+        .await
+        .map_err(|e| IndexerError::Rpc(format!("{:#}", e)))?
+        .ok_or(IndexerError::UnknownStartHash(hash))?;
+    tracing::info!(start, "resolved start block hash");
+    Ok(start)
+}
+
+/// Advance the cursor to the tip: [skip_to_tip] on the first connect in [HeadMode::LiveOnly],
+/// [catch_up] otherwise, so a reconnect never skips events missed while disconnected.
+async fn sync_to_tip(
+    indexer: &StemIndexer,
+    http_client: &dyn RpcClient,
+    cursor: &mut Cursor,
+    config: &IndexerConfig,
 ) -> Result<()> {
-    let mut from = from_block;
-    while from <= to_block {
-        let to = (from + max_range - 1).min(to_block);
-        let filter = build_logs_filter(
-            contract_address,
-            Some(&HEAD_UPDATED_TOPIC0),
-            Some(from),
-            Some(to),
-        );
-        let logs = match eth_get_logs(client, http_url, filter).await {
-            Ok(l) => l,
-            Err(e) => {
-                tracing::debug!(reason = %e, "eth_getLogs with topic filter failed, trying address-only");
-                let fallback = build_logs_filter_address_only(
-                    contract_address,
-                    Some(from),
-                    Some(to),
-                );
-                let raw = eth_get_logs(client, http_url, fallback).await?;
-                raw.into_iter()
-                    .filter(log_matches_head_updated)
-                    .collect::<Vec<_>>()
+    if config.head_mode == HeadMode::LiveOnly && !indexer.initial_backfill_done.load(Ordering::Relaxed) {
+        skip_to_tip(indexer, http_client, cursor, config).await
+    } else {
+        catch_up(indexer, http_client, cursor, config).await
+    }
+}
+
+/// Backfill from the cursor (the first time, no further back than
+/// [IndexerConfig::max_backfill_blocks] below the tip) to the current tip and advance the
+/// cursor to it.
+async fn catch_up(
+    indexer: &StemIndexer,
+    http_client: &dyn RpcClient,
+    cursor: &mut Cursor,
+    config: &IndexerConfig,
+) -> Result<()> {
+    let mut tip = eth_block_number(http_client).await?;
+    // Tip behind the cursor: a node lagging behind (e.g. behind a load balancer), or the chain
+    // was reorged to a shorter fork (or reset). Only the latter persists; rewind to it then.
+    let mut polls = 1;
+    while tip < cursor.last_processed_block && polls < REWIND_CONFIRM_POLLS {
+        tracing::debug!(tip, cursor = cursor.last_processed_block, "tip behind cursor, re-polling");
+        tokio::time::sleep(REWIND_CONFIRM_INTERVAL).await;
+        tip = eth_block_number(http_client).await?;
+        polls += 1;
+    }
+    indexer.rewind(cursor, tip);
+    let mut from_block = cursor.last_processed_block + 1;
+    if let Some(max) = config.max_backfill_blocks {
+        if !indexer.initial_backfill_done.load(Ordering::Relaxed) && from_block < tip.saturating_sub(max) {
+            tracing::info!(start = from_block, capped = tip - max, "capping initial backfill");
+            from_block = tip - max;
+        }
+    }
+    if from_block <= tip {
+        if config.emit_order == EmitOrder::LatestFirst && !indexer.synced.load(Ordering::Relaxed) {
+            preview_latest(indexer, http_client, config, from_block, tip).await?;
+        }
+        backfill(
+            http_client,
+            &config.contract_address,
+            from_block,
+            tip,
+            config.getlogs_max_range,
+            config.getlogs_concurrency,
+            indexer,
+        ).await?;
+        indexer.set_cursor(cursor, tip);
+    }
+    indexer.initial_backfill_done.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// [HeadMode::LiveOnly]'s first connect, in place of [catch_up]: seed the current head from
+/// `head()` at the tip and move the cursor there without fetching or publishing any logs.
+/// A reverting `head()` (no head set yet) leaves the current head unset.
+async fn skip_to_tip(
+    indexer: &StemIndexer,
+    http_client: &dyn RpcClient,
+    cursor: &mut Cursor,
+    config: &IndexerConfig,
+) -> Result<()> {
+    let tip = eth_block_number(http_client).await?;
+    match head_at_block(http_client, &config.contract_address, tip).await {
+        Ok(head) => {
+            set_current_head_if_newer(
+                &indexer.current_head,
+                HeadSnapshot {
+                    head,
+                    observed_block: tip,
+                    source: HeadSource::EthCall,
+                },
+            ).await
+        }
+        Err(e) if e.is::<HeadReverted>() => {
+            tracing::info!(reason = %e, tip, "no head to seed, going live without one");
+        }
+        Err(e) => return Err(e),
+    }
+    indexer.set_cursor(cursor, tip.max(cursor.last_processed_block));
+    indexer.initial_backfill_done.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Read WebSocket messages until the JSON-RPC response with `id` arrives, skipping anything
+/// else a node may send first (pings, notifications, responses to other requests). Fails with
+/// [IndexerError::Timeout] if it takes longer than `limit` overall.
+async fn await_ws_response<S>(ws_receiver: &mut S, id: u64, limit: Duration) -> Result<Value, IndexerError>
+where
+    S: futures_util::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    let wait = async {
+        loop {
+            let msg = ws_receiver
+                .next()
+                .await
+                .ok_or_else(|| IndexerError::Subscribe("ws closed".into()))?
+                .map_err(|e| IndexerError::WsConnect(e.to_string()))?;
+            let text = match msg {
+                Message::Text(t) => t,
+                Message::Close(_) => return Err(IndexerError::Subscribe("ws closed".into())),
+                _ => continue,
+            };
+            let v: Value = serde_json::from_str(&text)
+                .map_err(|e| IndexerError::Decode(format!("parse sub response: {}", e)))?;
+            if v.get("id").and_then(|i| i.as_u64()) == Some(id) {
+                return Ok(v);
             }
-        };
-        // If topic filter returned empty, try address-only (some nodes ignore topic filter and return []).
-        let logs = if logs.is_empty() {
+            tracing::trace!(%text, "skipping message while awaiting subscribe response");
+        }
+    };
+    timeout(limit, wait)
+        .await
+        .map_err(|_| IndexerError::Timeout("subscribe".into()))?
+}
+
+/// Send the latest event in the newest chunk of `[from, tip]` on the head preview channel.
+async fn preview_latest(
+    indexer: &StemIndexer,
+    http_client: &dyn RpcClient,
+    config: &IndexerConfig,
+    from: u64,
+    tip: u64,
+) -> Result<()> {
+    let start = tip.saturating_sub(config.getlogs_max_range.max(1) - 1).max(from);
+    let fetched = fetch_chunk(
+        http_client,
+        &config.contract_address,
+        start,
+        tip,
+        GetLogsOptions::from(config),
+    )
+    .await?;
+    let observed = fetched.into_iter().filter_map(Fetched::into_head);
+    if let Some(latest) = observed.rev().find(|o| indexer.accepts(o)) {
+        tracing::debug!(seq = latest.seq, block = latest.block_number, "head preview");
+        let _ = indexer.head_preview_tx.send(indexer.labeled(latest));
+    }
+    Ok(())
+}
+
+/// How [fetch_chunk] treats eth_getLogs responses, from [IndexerConfig].
+#[derive(Debug, Clone, Copy)]
+struct GetLogsOptions<'a> {
+    /// [IndexerConfig::getlogs_max_results].
+    max_results: usize,
+    /// [IndexerConfig::keep_raw_logs].
+    keep_raw: bool,
+    /// [IndexerConfig::max_cid_len].
+    max_cid_len: usize,
+    /// [IndexerConfig::decoders].
+    decoders: &'a [TopicDecoder],
+}
+
+impl<'a> From<&'a IndexerConfig> for GetLogsOptions<'a> {
+    fn from(config: &'a IndexerConfig) -> Self {
+        Self {
+            max_results: config.getlogs_max_results,
+            keep_raw: config.keep_raw_logs,
+            max_cid_len: config.max_cid_len,
+            decoders: &config.decoders,
+        }
+    }
+}
+
+impl GetLogsOptions<'_> {
+    /// Topic0 of every followed event: HeadUpdated, then each decoder's.
+    fn topics0(&self) -> Vec<[u8; 4]> {
+        std::iter::once(HEAD_UPDATED_TOPIC0)
+            .chain(self.decoders.iter().map(|d| d.topic0).filter(|t| *t != HEAD_UPDATED_TOPIC0))
+            .collect()
+    }
+
+    /// Whether `log` is one of the followed events (by topic0).
+    fn follows(&self, log: &Value) -> bool {
+        log_topic0(log).is_some_and(|t| t == HEAD_UPDATED_TOPIC0 || self.decoder_for(t).is_some())
+    }
+
+    fn decoder_for(&self, topic0: [u8; 4]) -> Option<&TopicDecoder> {
+        self.decoders.iter().find(|d| d.topic0 == topic0)
+    }
+}
+
+/// A fetched log: HeadUpdated, or an event type decoded by one of [IndexerConfig::decoders].
+#[derive(Debug, Clone)]
+enum Fetched {
+    Head(HeadUpdatedObserved),
+    Log(DecodedLog),
+}
+
+impl Fetched {
+    /// `(block_number, log_index)`.
+    fn position(&self) -> (u64, u64) {
+        match self {
+            Fetched::Head(o) => (o.block_number, o.log_index),
+            Fetched::Log(l) => (l.block_number, l.log_index),
+        }
+    }
+
+    fn into_head(self) -> Option<HeadUpdatedObserved> {
+        match self {
+            Fetched::Head(o) => Some(o),
+            Fetched::Log(_) => None,
+        }
+    }
+}
+
+/// Decode `log` with the configured cid cap, attaching the log itself as `raw` when
+/// `keep_raw` is set.
+fn decode_log(log: &Value, opts: GetLogsOptions) -> Result<HeadUpdatedObserved> {
+    let mut observed = decode_log_to_observed_with_max_cid_len(log, opts.max_cid_len)?;
+    if opts.keep_raw {
+        observed.raw = Some(log.clone());
+    }
+    Ok(observed)
+}
+
+/// Decode `log` with the decoder registered for its topic0, or as HeadUpdated.
+fn decode_fetched(log: &Value, opts: GetLogsOptions) -> Result<Fetched> {
+    match log_topic0(log).filter(|t| *t != HEAD_UPDATED_TOPIC0).and_then(|t| opts.decoder_for(t)) {
+        Some(d) => decode_log_with(log, &d.decode).map(Fetched::Log),
+        None => decode_log(log, opts).map(Fetched::Head),
+    }
+}
+
+/// Fetch and decode followed logs in `[from, to]`, sorted by (block_number, log_index).
+/// A response with `opts.max_results` logs may be truncated: the range is halved and refetched, and a
+/// full single-block response fails with [IndexerError::BlockTooDense].
+async fn fetch_chunk(
+    client: &dyn RpcClient,
+    contract_address: &[u8; 20],
+    from: u64,
+    to: u64,
+    opts: GetLogsOptions<'_>,
+) -> Result<Vec<Fetched>> {
+    if let Some(fetched) = fetch_chunk_once(client, contract_address, from, to, opts).await? {
+        return Ok(fetched);
+    }
+    if from == to {
+        return Err(IndexerError::BlockTooDense { block: from }.into());
+    }
+    let mid = from + (to - from) / 2;
+    tracing::debug!(from, to, max_results = opts.max_results, "eth_getLogs response full, splitting range");
+    let mut fetched = Box::pin(fetch_chunk(client, contract_address, from, mid, opts)).await?;
+    fetched.extend(Box::pin(fetch_chunk(client, contract_address, mid + 1, to, opts)).await?);
+    Ok(fetched)
+}
+
+/// One pass of [fetch_chunk]; `None` if any eth_getLogs response was full.
+async fn fetch_chunk_once(
+    client: &dyn RpcClient,
+    contract_address: &[u8; 20],
+    from: u64,
+    to: u64,
+    opts: GetLogsOptions<'_>,
+) -> Result<Option<Vec<Fetched>>> {
+    let full = |logs: &[Value]| opts.max_results > 0 && logs.len() >= opts.max_results;
+    let filter = build_logs_filter(
+        contract_address,
+        &opts.topics0(),
+        Some(from),
+        Some(to),
+    );
+    let logs = match eth_get_logs(client, filter).await {
+        Ok(l) if full(&l) => return Ok(None),
+        Ok(l) => l,
+        Err(e) => {
+            tracing::debug!(reason = %e, "eth_getLogs with topic filter failed, trying address-only");
             let fallback = build_logs_filter_address_only(
                 contract_address,
                 Some(from),
                 Some(to),
             );
-            match eth_get_logs(client, http_url, fallback).await {
-                Ok(raw) => raw
-                    .into_iter()
-                    .filter(log_matches_head_updated)
-                    .collect::<Vec<_>>(),
-                Err(_) => logs,
+            let raw = eth_get_logs(client, fallback).await?;
+            if full(&raw) {
+                return Ok(None);
             }
-        } else {
-            logs
-        };
-        let mut observed: Vec<HeadUpdatedObserved> = logs
-            .iter()
-            .filter_map(|log| {
-                decode_log_to_observed(log).map_err(|e| tracing::debug!(%e, "decode log skipped")).ok()
-            })
-            .collect();
-        if !logs.is_empty() && observed.is_empty() {
-            tracing::warn!(raw_count = logs.len(), from, to, "backfill: logs received but none decoded");
-        } else if !observed.is_empty() {
-            tracing::debug!(count = observed.len(), from, to, "backfill: decoded events");
-        }
-        observed.sort_by_key(|o| (o.block_number, o.log_index));
-        for o in observed {
-            let _ = event_tx.send(o.clone());
-            set_current_head_if_newer(
-                current_head,
-                CurrentHead {
-                    seq: o.seq,
-                    cid: o.cid,
-                },
-            ).await;
+            raw.into_iter()
+                .filter(|log| opts.follows(log))
+                .collect::<Vec<_>>()
+        }
+    };
+    // If topic filter returned empty, try address-only (some nodes ignore topic filter and return []).
+    let logs = if logs.is_empty() {
+        let fallback = build_logs_filter_address_only(
+            contract_address,
+            Some(from),
+            Some(to),
+        );
+        match eth_get_logs(client, fallback).await {
+            Ok(raw) if full(&raw) => return Ok(None),
+            Ok(raw) => raw
+                .into_iter()
+                .filter(|log| opts.follows(log))
+                .collect::<Vec<_>>(),
+            Err(_) => logs,
+        }
+    } else {
+        logs
+    };
+    let mut fetched: Vec<Fetched> = logs
+        .iter()
+        .filter_map(|log| {
+            decode_fetched(log, opts)
+                .map_err(|e| {
+                    if e.is::<CidIndexed>() {
+                        tracing::warn!(reason = %e, "decode log skipped");
+                    } else {
+                        tracing::debug!(%e, "decode log skipped");
+                    }
+                })
+                .ok()
+        })
+        .collect();
+    if !logs.is_empty() && fetched.is_empty() {
+        tracing::warn!(raw_count = logs.len(), from, to, "backfill: logs received but none decoded");
+    } else if !fetched.is_empty() {
+        tracing::debug!(count = fetched.len(), from, to, "backfill: decoded events");
+    }
+    fetched.sort_by_key(Fetched::position);
+    Ok(Some(fetched))
+}
+
+/// Split `[from_block, to_block]` into `max_range`-sized chunks and fetch up to `concurrency`
+/// of them at once; yields `(from, to, events)` per chunk in block order.
+fn fetch_range<'a>(
+    client: &'a dyn RpcClient,
+    contract_address: &'a [u8; 20],
+    from_block: u64,
+    to_block: u64,
+    max_range: u64,
+    concurrency: usize,
+    opts: GetLogsOptions<'a>,
+) -> impl futures_util::Stream<Item = Result<(u64, u64, Vec<Fetched>)>> + 'a {
+    let max_range = max_range.max(1);
+    let mut chunks = Vec::new();
+    let mut from = from_block;
+    while from <= to_block {
+        let to = from.saturating_add(max_range - 1).min(to_block);
+        chunks.push((from, to));
+        if to == u64::MAX {
+            break;
         }
         from = to + 1;
     }
+    stream::iter(chunks)
+        .map(move |(from, to)| async move {
+            let fetched = fetch_chunk(client, contract_address, from, to, opts).await?;
+            Ok::<_, anyhow::Error>((from, to, fetched))
+        })
+        .buffered(concurrency.max(1))
+}
+
+/// Backfill `[from_block, to_block]` in chunks of `max_range` blocks, with up to `concurrency`
+/// chunk requests in flight. Chunks are emitted in block order regardless of completion order.
+async fn backfill(
+    client: &dyn RpcClient,
+    contract_address: &[u8; 20],
+    from_block: u64,
+    to_block: u64,
+    max_range: u64,
+    concurrency: usize,
+    indexer: &StemIndexer,
+) -> Result<()> {
+    let mut results = fetch_range(
+        client,
+        contract_address,
+        from_block,
+        to_block,
+        max_range,
+        concurrency,
+        GetLogsOptions::from(&indexer.config),
+    );
+    let mut events_so_far = 0;
+    while let Some(chunk) = results.next().await {
+        let (from, to, fetched) = chunk?;
+        indexer.metrics.backfill_blocks_total.fetch_add(to - from + 1, Ordering::Relaxed);
+        events_so_far += fetched.iter().filter(|f| matches!(f, Fetched::Head(_))).count() as u64;
+        for f in fetched {
+            indexer.publish_fetched(f).await;
+        }
+        if let Some(progress) = &indexer.on_backfill_progress {
+            progress(BackfillProgress {
+                from: from_block,
+                to: to_block,
+                current: to,
+                events_so_far,
+            });
+        }
+    }
+    if indexer.config.head_at_backfill_tip {
+        let head = head_at_block(client, contract_address, to_block).await?;
+        let snapshot = HeadSnapshot {
+            head,
+            observed_block: to_block,
+            source: HeadSource::EthCall,
+        };
+        set_current_head_if_newer(&indexer.current_head, snapshot).await;
+    }
     Ok(())
 }
 
 async fn set_current_head_if_newer(
-    current_head: &Arc<RwLock<Option<CurrentHead>>>,
-    new: CurrentHead,
+    current_head: &Arc<RwLock<Option<HeadSnapshot>>>,
+    new: HeadSnapshot,
 ) {
     let mut guard = current_head.write().await;
     let should_set = guard
         .as_ref()
-        .map(|h| new.seq >= h.seq)
+        .map(|s| new.head.seq >= s.head.seq)
         .unwrap_or(true);
     if should_set {
-        tracing::info!(seq = new.seq, "current HEAD updated");
+        tracing::info!(seq = new.head.seq, block = new.observed_block, "current HEAD updated");
         *guard = Some(new);
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::decode_log_to_observed;
+    use crate::test_support::{
+        head_return_hex, head_updated_log, spawn_http_rpc, spawn_json_rpc, spawn_stalled_http, MockRpc,
+    };
+    use std::sync::atomic::AtomicUsize;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Config for the mock RPCs below, which don't serve `eth_getCode`.
+    fn test_config(ws_url: String, http_url: String) -> IndexerConfig {
+        IndexerConfig::builder()
+            .ws_url(ws_url)
+            .http_url(http_url)
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .verify_contract_code(false)
+            .build()
+            .unwrap()
+    }
+
+    /// Local WS server that confirms the subscription, then reports every ping it reads.
+    /// With `answer_pings == false` it stops reading after the handshake, so no pong is sent.
+    async fn spawn_ws_server(answer_pings: bool) -> (String, mpsc::UnboundedReceiver<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (ping_tx, ping_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let _subscribe = ws.next().await;
+            ws.send(Message::Text(json!({"jsonrpc": "2.0", "id": 1, "result": "0xsub"}).to_string()))
+                .await
+                .unwrap();
+            if !answer_pings {
+                sleep(Duration::from_secs(60)).await;
+                return;
+            }
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Ping(_) = msg {
+                    let _ = ping_tx.send(());
+                }
+            }
+        });
+        (url, ping_rx)
+    }
+
+    #[tokio::test]
+    async fn run_gives_up_after_max_attempts() {
+        let mut config = test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into());
+        config.reconnection = ReconnectionConfig {
+            initial_backoff_secs: 0,
+            max_backoff_secs: 0,
+            max_attempts: Some(2),
+        };
+        let indexer = Arc::new(StemIndexer::new(config));
+        let res = timeout(Duration::from_secs(5), indexer.run())
+            .await
+            .expect("run should return instead of retrying forever");
+        let err = res.unwrap_err();
+        assert!(format!("{:#}", err).contains("gave up after 2 consecutive failures"), "{:#}", err);
+    }
+
+    /// [Exponential] that records each delay it hands out.
+    #[derive(Debug, Clone)]
+    struct RecordingBackoff(Exponential, Arc<Mutex<Vec<(u32, Duration)>>>);
+
+    impl BackoffStrategy for RecordingBackoff {
+        fn next_delay(&mut self, attempt: u32) -> Duration {
+            let delay = self.0.next_delay(attempt);
+            self.1.lock().unwrap().push((attempt, delay));
+            delay
+        }
+
+        fn clone_box(&self) -> Box<dyn BackoffStrategy> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn reconnect_delay_escalates_then_caps() {
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let mut config = poll_config("http://unused.invalid".into());
+        config.start_block = StartFrom::Number(1);
+        config.reconnection.max_attempts = Some(6);
+        config.backoff = Some(Box::new(RecordingBackoff(
+            Exponential {
+                initial: Duration::from_millis(1),
+                max: Duration::from_millis(8),
+                jitter: Duration::ZERO,
+            },
+            Arc::clone(&delays),
+        )));
+        // eth_blockNumber is unscripted, so every backfill fails with a transient RPC error.
+        let indexer = Arc::new(StemIndexer::with_rpc_client(config, Arc::new(crate::rpc::MockRpcClient::new())));
+        timeout(Duration::from_secs(5), indexer.run())
+            .await
+            .expect("run gives up")
+            .unwrap_err();
+        let ms: Vec<(u32, u128)> = delays.lock().unwrap().iter().map(|(a, d)| (*a, d.as_millis())).collect();
+        assert_eq!(ms, vec![(1, 2), (2, 4), (3, 8), (4, 8), (5, 8)]);
+    }
+
+    #[tokio::test]
+    async fn tip_behind_cursor_emits_rewound() {
+        let http_url = spawn_http_rpc(|_| json!("0x32")).await;
+        let (ws_url, _pings) = spawn_ws_server(true).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut events = indexer.subscribe_events();
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn(async move {
+            let client = HttpRpcClient::new(config.http_url.clone());
+            let mut cursor = Cursor::new(100);
+            run_once(indexer, &client, &mut cursor, &config).await
+        });
+        let ev = timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("rewind event")
+            .unwrap();
+        assert_eq!(ev, IndexerEvent::Rewound { from: 100, to: 50 });
+        assert!(heads.try_recv().is_err(), "legacy channel carries no rewinds");
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn lagging_tip_does_not_rewind() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x5"))
+            .push("eth_blockNumber", json!("0xc"))
+            .push("eth_getLogs", json!([]));
+        let config = test_config("ws://127.0.0.1:1".into(), "http://unused.invalid".into());
+        let indexer = StemIndexer::with_rpc_client(config.clone(), Arc::new(rpc.clone()));
+        let mut events = indexer.subscribe_events();
+        let mut cursor = Cursor::new(10);
+        catch_up(&indexer, &rpc, &mut cursor, &config).await.unwrap();
+        assert!(events.try_recv().is_err(), "no Rewound for a one-off lagging tip");
+        assert_eq!(cursor.last_processed_block, 12);
+        assert_eq!(rpc.requests_for("eth_blockNumber").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn metrics_count_emitted_events() {
+        let indexer = StemIndexer::new(test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into()));
+        assert_eq!(indexer.metrics(), IndexerMetrics::default());
+        for seq in 1..=3 {
+            indexer.publish(HeadUpdatedObserved {
+                seq,
+                writer: [0; 20],
+                cid: vec![seq as u8],
+                cid_hash: [0; 32],
+                block_number: seq,
+                tx_hash: [seq as u8; 32],
+                log_index: 0,
+                raw: None,
+                source_label: None,
+            }).await;
+        }
+        assert_eq!(indexer.metrics().events_emitted_total, 3);
+        assert_eq!(indexer.current_head().await.map(|h| h.seq), Some(3));
+    }
+
+    #[tokio::test]
+    async fn metrics_track_backfill_and_cursor() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0xa"),
+            _ => json!([]),
+        }).await;
+        let (ws_url, _pings) = spawn_ws_server(true).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let task = {
+            let indexer = Arc::clone(&indexer);
+            tokio::spawn(async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            })
+        };
+        timeout(Duration::from_secs(5), async {
+            while indexer.metrics().last_processed_block != 10 {
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("cursor reaches tip");
+        assert_eq!(indexer.metrics().backfill_blocks_total, 10);
+        task.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_backfill_preserves_order() {
+        // One log per block; later chunks answer faster so completion order is reversed.
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x8"),
+            Some("eth_getLogs") => {
+                let from = req["params"][0]["fromBlock"].as_str().unwrap();
+                let block = u64::from_str_radix(from.trim_start_matches("0x"), 16).unwrap();
+                std::thread::sleep(Duration::from_millis((9 - block) * 10));
+                json!([head_updated_log(block, block, 0)])
+            }
+            _ => Value::Null,
+        }).await;
+        let (ws_url, _pings) = spawn_ws_server(true).await;
+        let mut config = test_config(ws_url, http_url);
+        config.getlogs_max_range = 1;
+        config.getlogs_concurrency = 4;
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn(async move {
+            let client = HttpRpcClient::new(config.http_url.clone());
+            let mut cursor = Cursor::new(0);
+            run_once(indexer, &client, &mut cursor, &config).await
+        });
+        let mut blocks = Vec::new();
+        for _ in 0..8 {
+            let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+            blocks.push(ev.block_number);
+        }
+        assert_eq!(blocks, (1..=8).collect::<Vec<_>>());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn http_poll_follows_tip_without_subscription() {
+        // Tip advances by two blocks per poll; one log per block in the requested range.
+        let polls = Arc::new(AtomicUsize::new(0));
+        let polls_rpc = polls.clone();
+        let http_url = spawn_http_rpc(move |req| match req["method"].as_str() {
+            Some("eth_blockNumber") => {
+                let n = polls_rpc.fetch_add(1, Ordering::SeqCst) as u64 + 1;
+                json!(format!("0x{:x}", n * 2))
+            }
+            Some("eth_getLogs") => {
+                let q = |k: &str| {
+                    let s = req["params"][0][k].as_str().unwrap();
+                    u64::from_str_radix(s.trim_start_matches("0x"), 16).unwrap()
+                };
+                json!((q("fromBlock")..=q("toBlock")).map(|b| head_updated_log(b, b, 0)).collect::<Vec<_>>())
+            }
+            _ => Value::Null,
+        }).await;
+        let config = IndexerConfig::builder()
+            .http_url(http_url)
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .http_poll(Duration::from_millis(20))
+            .build()
+            .unwrap();
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn(async move {
+            let client = HttpRpcClient::new(config.http_url.clone());
+            let mut cursor = Cursor::new(0);
+            run_once(indexer, &client, &mut cursor, &config).await
+        });
+        let mut blocks = Vec::new();
+        for _ in 0..6 {
+            let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+            blocks.push(ev.block_number);
+        }
+        assert_eq!(blocks, (1..=6).collect::<Vec<_>>());
+        assert!(polls.load(Ordering::SeqCst) >= 3);
+        task.abort();
+    }
+
+    fn poll_config(http_url: String) -> IndexerConfig {
+        IndexerConfig::builder()
+            .http_url(http_url)
+            .contract_address([0x11; 20])
+            .start_block_hash([0xab; 32])
+            .http_poll(Duration::from_millis(10))
+            .verify_contract_code(false)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn exported_state_round_trips_and_resumes_from_cursor() {
+        let source = StemIndexer::new(test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into()));
+        source.publish(decode_log_to_observed(&head_updated_log(5, 40, 0)).unwrap()).await;
+        source.set_cursor(&mut Cursor::new(0), 42);
+        let state = source.export_state().await;
+        assert_eq!(state.last_processed_block, 42);
+        let blob = serde_json::to_string(&state).unwrap();
+
+        let rpc = MockRpc::new();
+        rpc.push("eth_blockNumber", json!("0x30")).push("eth_getLogs", json!([]));
+        let indexer = Arc::new(StemIndexer::new(poll_config(rpc.serve().await)));
+        indexer.import_state(serde_json::from_str(&blob).unwrap()).await;
+        assert_eq!(indexer.export_state().await, state);
+        assert_eq!(indexer.current_head().await.map(|h| h.seq), Some(5));
+
+        let task = tokio::spawn(Arc::clone(&indexer).run());
+        timeout(Duration::from_secs(5), async {
+            while !indexer.sync_status().await.synced {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("synced");
+        task.abort();
+        assert!(rpc.requests_for("eth_getBlockByHash").is_empty(), "imported cursor replaces start_block");
+        assert_eq!(rpc.requests_for("eth_getLogs")[0]["params"][0]["fromBlock"], "0x2b");
+        assert_eq!(indexer.metrics().last_processed_block, 0x30);
+    }
+
+    #[tokio::test]
+    async fn eth_call_invokes_arbitrary_view_function() {
+        use alloy::sol_types::{sol_data, SolType};
+        let rpc = crate::rpc::MockRpcClient::new();
+        let writer = alloy::primitives::Address::repeat_byte(0x22);
+        rpc.push("eth_call", json!(format!("0x{}", hex::encode(sol_data::Address::abi_encode(&writer)))));
+        let indexer = StemIndexer::with_rpc_client(
+            test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into()),
+            Arc::new(rpc.clone()),
+        );
+        // writerOf(uint64)
+        let selector = [0x12, 0x34, 0x56, 0x78];
+        let args = sol_data::Uint::<64>::abi_encode(&7u64);
+        let ret = indexer.eth_call(&selector, &args).await.unwrap();
+        assert_eq!(sol_data::Address::abi_decode(&ret, true).unwrap(), writer);
+
+        let call = &rpc.requests_for("eth_call")[0]["params"];
+        assert_eq!(call[0]["to"], format!("0x{}", hex::encode([0x11; 20])));
+        assert_eq!(call[0]["data"], format!("0x12345678{}", hex::encode(&args)));
+        assert_eq!(call[1], "latest");
+    }
+
+    #[tokio::test]
+    async fn indexer_uses_configured_http_client() {
+        let rpc = MockRpc::new();
+        rpc.push("eth_chainId", json!("0x7a69"));
+        // Only a client proxying through the mock can reach this host.
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::http(rpc.serve().await).unwrap())
+            .build()
+            .unwrap();
+        let config = IndexerConfig::builder()
+            .ws_url("ws://127.0.0.1:1")
+            .http_url("http://stem-rpc.invalid")
+            .http_client(client)
+            .contract_address([0x11; 20])
+            .build()
+            .unwrap();
+        let indexer = StemIndexer::new(config);
+        assert_eq!(indexer.chain_id().await.unwrap(), 31337);
+        assert_eq!(rpc.requests_for("eth_chainId").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn root_certificates_are_trusted_for_wss_and_https() {
+        use crate::test_support::{spawn_tls_json_rpc, tls_acceptor, TEST_CA_PEM};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("wss://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let acceptor = tls_acceptor();
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(stream) = acceptor.accept(stream).await else { continue };
+                if let Ok(ws) = tokio_tungstenite::accept_async(stream).await {
+                    held.push(ws);
+                }
+            }
+        });
+        let http_url = spawn_tls_json_rpc(|_| Ok(json!("0x7a69"))).await;
+
+        let untrusted = test_config(ws_url.clone(), http_url.clone());
+        assert!(matches!(connect_ws(&untrusted).await, Err(IndexerError::WsConnect(_))));
+        assert!(StemIndexer::new(untrusted).chain_id().await.is_err());
+
+        let mut trusted = test_config(ws_url, http_url);
+        trusted.root_certificates.push(TEST_CA_PEM.to_vec());
+        assert!(connect_ws(&trusted).await.is_ok());
+        assert_eq!(StemIndexer::new(trusted).chain_id().await.unwrap(), 31337);
+    }
+
+    #[tokio::test]
+    async fn start_hash_resolves_to_block_number() {
+        let rpc = MockRpc::new();
+        rpc.push("eth_getBlockByHash", json!({"number": "0x10"}))
+            .push("eth_blockNumber", json!("0x12"))
+            .push("eth_getLogs", json!([]));
+        let indexer = Arc::new(StemIndexer::new(poll_config(rpc.serve().await)));
+        let task = tokio::spawn(Arc::clone(&indexer).run());
+        timeout(Duration::from_secs(5), async {
+            while !indexer.sync_status().await.synced {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("synced");
+        task.abort();
+        let by_hash = rpc.requests_for("eth_getBlockByHash");
+        assert_eq!(by_hash.len(), 1);
+        assert_eq!(by_hash[0]["params"][0], format!("0x{}", hex::encode([0xab; 32])));
+        assert_eq!(rpc.requests_for("eth_getLogs")[0]["params"][0]["fromBlock"], "0x10");
+        assert_eq!(indexer.metrics().last_processed_block, 0x12);
+    }
+
+    #[tokio::test]
+    async fn unknown_start_hash_fails_run_once() {
+        let rpc = MockRpc::new();
+        rpc.push("eth_getBlockByHash", Value::Null);
+        let config = poll_config(rpc.serve().await);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let client = HttpRpcClient::new(config.http_url.clone());
+        let err = run_once(indexer, &client, &mut Cursor::new(0), &config).await.unwrap_err();
+        assert_eq!(err, IndexerError::UnknownStartHash([0xab; 32]));
+        assert!(rpc.requests_for("eth_getLogs").is_empty());
+    }
+
+    #[tokio::test]
+    async fn stalled_rpc_fails_run_once_with_timeout() {
+        let config = IndexerConfig::builder()
+            .http_url(spawn_stalled_http().await)
+            .contract_address([0x11; 20])
+            .http_poll(Duration::from_secs(1))
+            .request_timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let rpc = Arc::clone(&indexer.rpc);
+        let res = timeout(Duration::from_secs(5), run_once(indexer, rpc.as_ref(), &mut Cursor::new(0), &config))
+            .await
+            .expect("run_once hung past the request timeout");
+        assert!(matches!(res, Err(IndexerError::Timeout(_))), "{res:?}");
+    }
+
+    #[tokio::test]
+    async fn verify_cid_hash_drops_tampered_events() {
+        let mut genuine = head_updated_log(1, 3, 0);
+        genuine["topics"][3] = json!(format!("0x{}", hex::encode(crate::abi::cid_hash(b"cid-1"))));
+        let tampered = head_updated_log(2, 4, 0);
+        let rpc = MockRpc::new();
+        rpc.push("eth_getLogs", json!([genuine, tampered]));
+        let url = rpc.serve().await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), url.clone());
+        config.verify_cid_hash = true;
+        let indexer = StemIndexer::new(config);
+        let mut heads = indexer.subscribe();
+        let client = HttpRpcClient::new(url.clone());
+        backfill(&client, &[0x11; 20], 1, 10, 1000, 1, &indexer).await.unwrap();
+        assert_eq!(heads.try_recv().unwrap().seq, 1);
+        assert!(heads.try_recv().is_err());
+        assert_eq!(indexer.metrics().events_emitted_total, 1);
+    }
+
+    #[tokio::test]
+    async fn keep_raw_logs_attaches_log_json() {
+        for keep in [false, true] {
+            let rpc = crate::rpc::MockRpcClient::new();
+            rpc.push("eth_getLogs", json!([head_updated_log(1, 3, 0)]));
+            let mut config = test_config("ws://127.0.0.1:1".into(), "http://unused.invalid".into());
+            config.keep_raw_logs = keep;
+            let indexer = StemIndexer::with_rpc_client(config, Arc::new(rpc));
+            let mut heads = indexer.subscribe();
+            backfill(indexer.rpc.as_ref(), &[0x11; 20], 1, 10, 1000, 1, &indexer).await.unwrap();
+            let raw = heads.try_recv().unwrap().raw;
+            assert_eq!(raw, keep.then(|| head_updated_log(1, 3, 0)), "keep_raw_logs = {keep}");
+        }
+    }
+
+    fn name_config(resolve: fn(&str) -> anyhow::Result<[u8; 20]>) -> IndexerConfig {
+        IndexerConfig::builder()
+            .http_url("http://unused.invalid")
+            .http_poll(Duration::from_millis(10))
+            .contract_name("stem.eth", move |name| {
+                let res = resolve(name);
+                Box::pin(async move { res })
+            })
+            .verify_contract_code(false)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn run_resolves_contract_name() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x2")).push("eth_getLogs", json!([]));
+        let config = name_config(|name| match name {
+            "stem.eth" => Ok([0x22; 20]),
+            _ => anyhow::bail!("unknown name {name}"),
+        });
+        let indexer = Arc::new(StemIndexer::with_rpc_client(config, Arc::new(rpc.clone())));
+        let task = tokio::spawn(Arc::clone(&indexer).run());
+        timeout(Duration::from_secs(5), async {
+            while !indexer.sync_status().await.synced {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("synced");
+        task.abort();
+        let address = format!("0x{}", hex::encode([0x22; 20]));
+        assert_eq!(rpc.requests_for("eth_getLogs")[0]["params"][0]["address"], address);
+    }
+
+    #[tokio::test]
+    async fn run_fails_when_contract_name_does_not_resolve() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        let config = name_config(|_| anyhow::bail!("registry unavailable"));
+        let indexer = Arc::new(StemIndexer::with_rpc_client(config, Arc::new(rpc.clone())));
+        let err = timeout(Duration::from_secs(5), indexer.run())
+            .await
+            .expect("run returns instead of retrying")
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("registry unavailable"), "{:#}", err);
+        assert!(rpc.requests().is_empty(), "nothing indexed without an address");
+    }
+
+    fn code_check_config(address: [u8; 20]) -> IndexerConfig {
+        IndexerConfig::builder()
+            .http_url("http://unused.invalid")
+            .contract_address(address)
+            .start_block(1)
+            .http_poll(Duration::from_millis(10))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn run_fails_without_contract_code() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_getCode", json!("0x"));
+        let indexer = Arc::new(StemIndexer::with_rpc_client(code_check_config([0x11; 20]), Arc::new(rpc.clone())));
+        let err = timeout(Duration::from_secs(5), indexer.run())
+            .await
+            .expect("run returns instead of retrying")
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<IndexerError>(), Some(&IndexerError::NoContractCode([0x11; 20])));
+        assert!(rpc.requests_for("eth_getLogs").is_empty());
+
+        // The zero address is rejected without asking the node.
+        let rpc = crate::rpc::MockRpcClient::new();
+        let indexer = Arc::new(StemIndexer::with_rpc_client(code_check_config([0; 20]), Arc::new(rpc.clone())));
+        let err = indexer.run().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<IndexerError>(), Some(&IndexerError::NoContractCode([0; 20])));
+        assert!(rpc.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn run_proceeds_with_contract_code() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_getCode", json!("0x6080604052"))
+            .push("eth_blockNumber", json!("0x2"))
+            .push("eth_getLogs", json!([head_updated_log(1, 2, 0)]));
+        let indexer = Arc::new(StemIndexer::with_rpc_client(code_check_config([0x11; 20]), Arc::new(rpc.clone())));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn(Arc::clone(&indexer).run());
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+        assert_eq!(ev.seq, 1);
+        task.abort();
+        let code_req = &rpc.requests_for("eth_getCode")[0];
+        assert_eq!(code_req["params"], json!([format!("0x{}", hex::encode([0x11; 20])), "latest"]));
+    }
+
+    #[tokio::test]
+    async fn indexer_runs_against_mock_rpc_client() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x4"))
+            .push("eth_getLogs", json!([head_updated_log(1, 2, 0), head_updated_log(2, 4, 0)]));
+        let config = IndexerConfig::builder()
+            .http_url("http://unused.invalid")
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .http_poll(Duration::from_millis(10))
+            .verify_contract_code(false)
+            .build()
+            .unwrap();
+        let indexer = Arc::new(StemIndexer::with_rpc_client(config, Arc::new(rpc.clone())));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn(Arc::clone(&indexer).run());
+        for seq in 1..=2 {
+            let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+            assert_eq!(ev.seq, seq);
+        }
+        task.abort();
+        assert_eq!(rpc.requests_for("eth_getLogs")[0]["params"][0]["toBlock"], "0x4");
+    }
+
+    #[tokio::test]
+    async fn backfill_once_emits_history_and_returns() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x1e"))
+            .push("eth_getLogs", json!((1..=3).map(|seq| head_updated_log(seq, seq * 10, 0)).collect::<Vec<_>>()));
+        let mut config = test_config("ws://127.0.0.1:1".into(), "http://unused.invalid".into());
+        config.start_block = StartFrom::Number(5);
+        let indexer = StemIndexer::with_rpc_client(config, Arc::new(rpc.clone()));
+        let mut heads = indexer.subscribe();
+        timeout(Duration::from_secs(5), indexer.backfill_once())
+            .await
+            .expect("backfill_once completes")
+            .unwrap();
+        let seqs: Vec<_> = std::iter::from_fn(|| heads.try_recv().ok()).map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        assert_eq!(indexer.metrics().last_processed_block, 30);
+        let logs = rpc.requests_for("eth_getLogs");
+        assert_eq!((logs[0]["params"][0]["fromBlock"].as_str(), logs[0]["params"][0]["toBlock"].as_str()), (Some("0x5"), Some("0x1e")));
+    }
+
+    #[tokio::test]
+    async fn latest_first_previews_head_before_backfill() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x1e"))
+            .push("eth_getLogs", json!([head_updated_log(3, 28, 0)]))
+            .push("eth_getLogs", json!([head_updated_log(1, 5, 0)]))
+            .push("eth_getLogs", json!([head_updated_log(2, 15, 0)]))
+            .push("eth_getLogs", json!([head_updated_log(3, 28, 0)]));
+        let mut config = test_config("ws://127.0.0.1:1".into(), "http://unused.invalid".into());
+        config.getlogs_max_range = 10;
+        config.getlogs_concurrency = 1;
+        config.emit_order = EmitOrder::LatestFirst;
+        let indexer = StemIndexer::with_rpc_client(config, Arc::new(rpc.clone()));
+        let mut preview = indexer.subscribe_head_preview();
+        let mut heads = indexer.subscribe();
+        indexer.backfill_once().await.unwrap();
+
+        assert_eq!(preview.try_recv().unwrap().seq, 3);
+        assert!(preview.try_recv().is_err());
+        let seqs: Vec<_> = std::iter::from_fn(|| heads.try_recv().ok()).map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        let ranges: Vec<_> = rpc
+            .requests_for("eth_getLogs")
+            .iter()
+            .map(|r| (r["params"][0]["fromBlock"].clone(), r["params"][0]["toBlock"].clone()))
+            .collect();
+        assert_eq!(
+            ranges,
+            [("0x15", "0x1e"), ("0x1", "0xa"), ("0xb", "0x14"), ("0x15", "0x1e")]
+                .map(|(f, t)| (json!(f), json!(t)))
+        );
+    }
+
+    #[tokio::test]
+    async fn initial_backfill_is_capped_by_max_backfill_blocks() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x64"))
+            .push("eth_getLogs", json!([head_updated_log(1, 95, 0)]))
+            .push("eth_blockNumber", json!("0x66"))
+            .push("eth_getLogs", json!([head_updated_log(2, 101, 0)]));
+        let mut config = poll_config("http://unused.invalid".into());
+        config.start_block = StartFrom::Number(1);
+        config.max_backfill_blocks = Some(10);
+        let indexer = StemIndexer::with_rpc_client(config.clone(), Arc::new(rpc.clone()));
+        let mut cursor = Cursor::new(0);
+
+        catch_up(&indexer, &rpc, &mut cursor, &config).await.unwrap();
+        // The next catch-up (e.g. after a reconnect) isn't capped.
+        catch_up(&indexer, &rpc, &mut cursor, &config).await.unwrap();
+
+        let ranges: Vec<_> = rpc
+            .requests_for("eth_getLogs")
+            .iter()
+            .map(|r| (r["params"][0]["fromBlock"].clone(), r["params"][0]["toBlock"].clone()))
+            .collect();
+        assert_eq!(ranges, vec![(json!("0x5a"), json!("0x64")), (json!("0x65"), json!("0x66"))]);
+    }
+
+    #[tokio::test]
+    async fn extra_topic_logs_are_decoded_and_delivered_in_order() {
+        const HEAD_REVERTED: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];
+        let mut reverted = head_updated_log(0, 3, 1);
+        reverted["topics"][0] = json!(format!("0x{}{}", hex::encode(HEAD_REVERTED), "00".repeat(28)));
+        reverted["data"] = json!("0x07");
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x4"))
+            .push("eth_getLogs", json!([reverted, head_updated_log(1, 2, 0)]));
+        let config = IndexerConfig::builder()
+            .http_url("http://unused.invalid")
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .http_poll(Duration::from_millis(10))
+            .decoder(HEAD_REVERTED, |log| {
+                let data = log["data"].as_str().unwrap_or_default();
+                Ok(json!({"reverted_to": u64::from_str_radix(data.trim_start_matches("0x"), 16)?}))
+            })
+            .build()
+            .unwrap();
+        let indexer = StemIndexer::with_rpc_client(config, Arc::new(rpc.clone()));
+        let mut events = indexer.subscribe_events();
+
+        indexer.backfill_once().await.unwrap();
+
+        match events.try_recv().unwrap() {
+            IndexerEvent::Head(o) => assert_eq!((o.seq, o.block_number), (1, 2)),
+            other => panic!("expected HeadUpdated first, got {other:?}"),
+        }
+        match events.try_recv().unwrap() {
+            IndexerEvent::Log(log) => {
+                assert_eq!(log.topic0, HEAD_REVERTED);
+                assert_eq!((log.block_number, log.log_index), (3, 1));
+                assert_eq!(log.fields, json!({"reverted_to": 7}));
+            }
+            other => panic!("expected decoded log, got {other:?}"),
+        }
+        assert!(events.try_recv().is_err());
+        assert_eq!(indexer.metrics().events_emitted_total, 1, "only HeadUpdated counts as a head event");
+        let topics = &rpc.requests_for("eth_getLogs")[0]["params"][0]["topics"];
+        assert_eq!(
+            *topics,
+            json!([[format!("0x{}", hex::encode(HEAD_UPDATED_TOPIC0)), format!("0x{}", hex::encode(HEAD_REVERTED))]])
+        );
+    }
+
+    #[tokio::test]
+    async fn backfill_reports_progress_per_chunk() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_getLogs", json!([head_updated_log(1, 5, 0), head_updated_log(2, 8, 0)]))
+            .push("eth_getLogs", json!([]))
+            .push("eth_getLogs", json!([])) // address-only retry of the empty chunk
+            .push("eth_getLogs", json!([head_updated_log(3, 25, 0)]));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let indexer = StemIndexer::with_rpc_client(
+            test_config("ws://127.0.0.1:1".into(), "http://unused.invalid".into()),
+            Arc::new(rpc),
+        )
+        .on_backfill_progress({
+            let seen = Arc::clone(&seen);
+            move |p| seen.lock().unwrap().push(p)
+        });
+        backfill(indexer.rpc.as_ref(), &[0x11; 20], 1, 25, 10, 1, &indexer).await.unwrap();
+        let progress: Vec<_> = seen.lock().unwrap().iter().map(|p| (p.from, p.to, p.current, p.events_so_far)).collect();
+        assert_eq!(progress, vec![(1, 25, 10, 2), (1, 25, 20, 2), (1, 25, 25, 3)]);
+    }
+
+    #[tokio::test]
+    async fn requests_carry_increasing_ids() {
+        let ids = Arc::new(Mutex::new(Vec::new()));
+        let ids_rpc = ids.clone();
+        let http_url = spawn_http_rpc(move |req| {
+            ids_rpc.lock().unwrap().push(req["id"].as_u64().expect("numeric id"));
+            match req["method"].as_str() {
+                Some("eth_chainId") => json!("0x1"),
+                Some("eth_blockNumber") => json!("0x5"),
+                Some("eth_getLogs") => json!([]),
+                _ => Value::Null,
+            }
+        }).await;
+        let config = IndexerConfig::builder()
+            .http_url(http_url)
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .http_poll(Duration::from_millis(10))
+            .verify_contract_code(false)
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(StemIndexer::new(config)).run());
+        timeout(Duration::from_secs(5), async {
+            while ids.lock().unwrap().len() < 6 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("requests sent");
+        task.abort();
+        let ids = ids.lock().unwrap().clone();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]), "ids not increasing: {ids:?}");
+    }
+
+    async fn tagged_block_rpc() -> String {
+        spawn_http_rpc(|req| match (req["method"].as_str(), req["params"][0].as_str()) {
+            (Some("eth_blockNumber"), _) => json!("0x64"),
+            (Some("eth_getBlockByNumber"), Some("safe")) => json!({"number": "0x60"}),
+            (Some("eth_getBlockByNumber"), Some("finalized")) => json!({"number": "0x5a"}),
+            _ => Value::Null,
+        }).await
+    }
+
+    #[tokio::test]
+    async fn block_number_at_each_tag() {
+        let url = tagged_block_rpc().await;
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        assert_eq!(block_number_at(&client, &url, BlockTag::Latest).await.unwrap(), 100);
+        assert_eq!(block_number_at(&client, &url, BlockTag::Safe).await.unwrap(), 96);
+        assert_eq!(block_number_at(&client, &url, BlockTag::Finalized).await.unwrap(), 90);
+        assert_eq!(current_block_number_with(&client, &url).await.unwrap(), 100);
+    }
+
+    #[tokio::test]
+    async fn current_block_number_recovers_from_transient_failure() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let seen = Arc::clone(&calls);
+        let url = spawn_json_rpc(move |_| {
+            if seen.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(json!({"code": -32603, "message": "internal error"}))
+            } else {
+                Ok(json!("0x2a"))
+            }
+        }).await;
+        assert_eq!(current_block_number(&url).await.unwrap(), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn head_at_block_passes_block_tag() {
+        let url = spawn_http_rpc(|req| {
+            assert_eq!(req["method"], "eth_call");
+            assert_eq!(req["params"][1], "0xa", "historical call targets the requested block");
+            json!(head_return_hex(5, b"cid-5"))
+        }).await;
+        let client = HttpRpcClient::new(url.clone());
+        let head = head_at_block(&client, &[0x11; 20], 10).await.unwrap();
+        assert_eq!((head.seq, head.cid), (5, b"cid-5".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn head_at_block_falls_back_to_latest() {
+        let url = spawn_json_rpc(|req| match req["params"][1].as_str() {
+            Some("latest") => Ok(json!(head_return_hex(7, b"cid-7"))),
+            _ => Err(json!({"code": -32000, "message": "missing trie node"})),
+        }).await;
+        let client = HttpRpcClient::new(url.clone());
+        let head = head_at_block(&client, &[0x11; 20], 10).await.unwrap();
+        assert_eq!(head.seq, 7);
+    }
+
+    /// `io::Write` into a shared buffer, for capturing formatted tracing output.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn tracing_events_carry_contract_and_chain_id() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_chainId") => json!("0x7a69"),
+            _ => Value::Null,
+        }).await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), http_url);
+        config.reconnection = ReconnectionConfig {
+            initial_backoff_secs: 0,
+            max_backoff_secs: 0,
+            max_attempts: Some(2),
+        };
+        let indexer = Arc::new(StemIndexer::new(config));
+        assert!(indexer.run().await.is_err());
+
+        let out = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = out
+            .lines()
+            .find(|l| l.contains("StemIndexer failed, reconnecting"))
+            .expect("reconnect warning");
+        assert!(line.contains(&format!("contract=0x{}", hex::encode([0x11u8; 20]))), "{line}");
+        assert!(line.contains("chain_id=31337"), "{line}");
+    }
+
+    #[tokio::test]
+    async fn fetch_current_head_decodes_latest() {
+        let url = spawn_http_rpc(|req| {
+            assert_eq!(req["method"], "eth_call");
+            assert_eq!(req["params"][0]["to"], format!("0x{}", hex::encode([0x11u8; 20])));
+            assert_eq!(req["params"][1], "latest");
+            json!(head_return_hex(9, b"ipfs://nine"))
+        }).await;
+        let head = fetch_current_head(&url, &[0x11; 20]).await.unwrap();
+        assert_eq!(head, CurrentHead { seq: 9, cid: b"ipfs://nine".to_vec() });
+    }
+
+    #[tokio::test]
+    async fn backfill_sets_head_at_tip_when_enabled() {
+        let url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_getLogs") => json!([]),
+            Some("eth_call") if req["params"][1] == "0x14" => json!(head_return_hex(3, b"cid-3")),
+            _ => Value::Null,
+        }).await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), url.clone());
+        config.head_at_backfill_tip = true;
+        let indexer = StemIndexer::new(config);
+        let client = HttpRpcClient::new(url.clone());
+        backfill(&client, &[0x11; 20], 1, 20, 1000, 1, &indexer).await.unwrap();
+        assert_eq!(indexer.current_head().await.map(|h| h.seq), Some(3));
+    }
+
+    #[tokio::test]
+    async fn head_snapshot_records_block_and_source() {
+        let url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_getLogs") => json!([head_updated_log(2, 7, 0)]),
+            _ => Value::Null,
+        }).await;
+        let indexer = StemIndexer::new(test_config("ws://127.0.0.1:1".into(), url.clone()));
+        backfill(indexer.rpc.as_ref(), &[0x11; 20], 1, 20, 1000, 1, &indexer).await.unwrap();
+        let snapshot = indexer.current_head_snapshot().await.unwrap();
+        assert_eq!(snapshot.head.seq, 2);
+        assert_eq!(snapshot.observed_block, 7);
+        assert_eq!(snapshot.source, HeadSource::Event);
+
+        let url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_getLogs") => json!([]),
+            Some("eth_call") => json!(head_return_hex(3, b"cid-3")),
+            _ => Value::Null,
+        }).await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), url.clone());
+        config.head_at_backfill_tip = true;
+        let indexer = StemIndexer::new(config);
+        backfill(indexer.rpc.as_ref(), &[0x11; 20], 1, 20, 1000, 1, &indexer).await.unwrap();
+        let snapshot = indexer.current_head_snapshot().await.unwrap();
+        assert_eq!(snapshot.head, CurrentHead { seq: 3, cid: b"cid-3".to_vec() });
+        assert_eq!(snapshot.observed_block, 20);
+        assert_eq!(snapshot.source, HeadSource::EthCall);
+    }
+
+    /// RPC whose eth_getLogs returns `per_block[b]` logs for each block `b` in the range.
+    async fn logs_per_block_rpc(per_block: &'static [(u64, u64)]) -> String {
+        spawn_http_rpc(move |req| {
+            let q = |k: &str| {
+                let s = req["params"][0][k].as_str().unwrap();
+                u64::from_str_radix(s.trim_start_matches("0x"), 16).unwrap()
+            };
+            let (from, to) = (q("fromBlock"), q("toBlock"));
+            json!(per_block
+                .iter()
+                .filter(|(b, _)| (from..=to).contains(b))
+                .flat_map(|&(b, n)| (0..n).map(move |i| head_updated_log(b * 10 + i, b, i)))
+                .collect::<Vec<_>>())
+        }).await
+    }
+
+    #[tokio::test]
+    async fn full_getlogs_response_splits_range() {
+        let url = logs_per_block_rpc(&[(2, 2), (3, 1)]).await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), url.clone());
+        config.getlogs_max_results = 3;
+        let indexer = StemIndexer::new(config);
+        let mut heads = indexer.subscribe();
+        let client = HttpRpcClient::new(url.clone());
+        backfill(&client, &[0x11; 20], 1, 4, 1000, 1, &indexer).await.unwrap();
+        let mut seen = Vec::new();
+        while let Ok(ev) = heads.try_recv() {
+            seen.push((ev.block_number, ev.log_index));
+        }
+        assert_eq!(seen, vec![(2, 0), (2, 1), (3, 0)]);
+    }
+
+    #[tokio::test]
+    async fn full_single_block_response_is_block_too_dense() {
+        let url = logs_per_block_rpc(&[(3, 3)]).await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), url.clone());
+        config.getlogs_max_results = 3;
+        let indexer = StemIndexer::new(config);
+        let client = HttpRpcClient::new(url.clone());
+        let err = backfill(&client, &[0x11; 20], 1, 4, 1000, 1, &indexer).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<IndexerError>(), Some(&IndexerError::BlockTooDense { block: 3 }));
+    }
+
+    /// Local WS server that confirms the subscription, pushes `logs` as subscription
+    /// notifications, then holds the connection open.
+    async fn spawn_ws_notifier(logs: Vec<Value>) -> String {
+        spawn_ws_notifier_with(Vec::new(), logs).await
+    }
+
+    /// Like [spawn_ws_notifier], but sends `preamble` between reading the subscribe request
+    /// and confirming it.
+    async fn spawn_ws_notifier_with(preamble: Vec<Message>, logs: Vec<Value>) -> String {
+        spawn_ws_notifier_for(preamble, logs.into_iter().map(|log| ("0xsub", log)).collect()).await
+    }
+
+    /// Like [spawn_ws_notifier_with], with each log's notification carrying its own
+    /// subscription id (the confirmed one is `0xsub`).
+    async fn spawn_ws_notifier_for(preamble: Vec<Message>, logs: Vec<(&'static str, Value)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let _subscribe = ws.next().await;
+            for msg in preamble {
+                ws.send(msg).await.unwrap();
+            }
+            ws.send(Message::Text(json!({"jsonrpc": "2.0", "id": 1, "result": "0xsub"}).to_string()))
+                .await
+                .unwrap();
+            for (subscription, log) in logs {
+                let note = json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscription",
+                    "params": {"subscription": subscription, "result": log}
+                });
+                ws.send(Message::Text(note.to_string())).await.unwrap();
+            }
+            sleep(Duration::from_secs(60)).await;
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn overlap_between_backfill_and_subscription_is_delivered_once() {
+        // Block 3 is both backfilled and pushed live; block 4 only arrives live.
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x3"),
+            Some("eth_getLogs") => json!((1..=3).map(|b| head_updated_log(b, b, 0)).collect::<Vec<_>>()),
+            _ => Value::Null,
+        }).await;
+        let ws_url = spawn_ws_notifier(vec![head_updated_log(3, 3, 0), head_updated_log(4, 4, 0)]).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            }
+        });
+        let mut seqs = Vec::new();
+        for _ in 0..4 {
+            let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+            seqs.push(ev.seq);
+        }
+        assert_eq!(seqs, vec![1, 2, 3, 4]);
+        assert!(timeout(Duration::from_millis(200), heads.recv()).await.is_err(), "duplicate delivered");
+        assert_eq!(indexer.metrics().events_emitted_total, 4);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn live_only_seeds_head_and_skips_history() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x3"),
+            Some("eth_getLogs") => panic!("LiveOnly must not backfill"),
+            Some("eth_call") => json!(head_return_hex(3, b"ipfs://three")),
+            _ => Value::Null,
+        }).await;
+        let ws_url = spawn_ws_notifier(vec![head_updated_log(4, 4, 0)]).await;
+        let mut config = test_config(ws_url, http_url);
+        config.head_mode = HeadMode::LiveOnly;
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            }
+        });
+
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+        assert_eq!(ev.seq, 4, "only the live event is published");
+        assert!(timeout(Duration::from_millis(200), heads.recv()).await.is_err());
+        let metrics = indexer.metrics();
+        assert_eq!(metrics.events_emitted_total, 1);
+        assert_eq!(metrics.backfill_blocks_total, 0);
+        assert_eq!(metrics.last_processed_block, 4);
+        assert_eq!(indexer.current_head().await.map(|h| h.seq), Some(4));
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn live_only_goes_live_when_head_reverts() {
+        use alloy::sol_types::SolType;
+        let mut revert = crate::abi::ERROR_SELECTOR.to_vec();
+        revert.extend(alloy::sol_types::sol_data::String::abi_encode("no head"));
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x3"))
+            .push("eth_call", json!(format!("0x{}", hex::encode(revert))));
+        let mut config = test_config("ws://127.0.0.1:1".into(), "http://unused.invalid".into());
+        config.head_mode = HeadMode::LiveOnly;
+        let indexer = StemIndexer::with_rpc_client(config.clone(), Arc::new(rpc.clone()));
+        let mut cursor = Cursor::new(0);
+        skip_to_tip(&indexer, &rpc, &mut cursor, &config).await.unwrap();
+        assert_eq!(cursor.last_processed_block, 3);
+        assert!(indexer.current_head().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn live_only_backfills_missed_events_after_reconnect() {
+        let rpc = crate::rpc::MockRpcClient::new();
+        rpc.push("eth_blockNumber", json!("0x3"))
+            .push_error("eth_blockNumber", json!({"code": -32000, "message": "connection reset"}))
+            .push("eth_blockNumber", json!("0x5"))
+            .push("eth_call", json!(head_return_hex(3, b"cid-3")))
+            .push("eth_getLogs", json!([head_updated_log(4, 5, 0)]));
+        let config = IndexerConfig::builder()
+            .http_url("http://unused.invalid")
+            .contract_address([0x11; 20])
+            .start_block(1)
+            .http_poll(Duration::from_millis(10))
+            .head_mode(HeadMode::LiveOnly)
+            .verify_contract_code(false)
+            .build()
+            .unwrap();
+        let indexer = Arc::new(StemIndexer::with_rpc_client(config.clone(), Arc::new(rpc.clone())));
+        let mut heads = indexer.subscribe();
+        let mut cursor = Cursor::new(0);
+        let first = run_once(Arc::clone(&indexer), &rpc, &mut cursor, &config).await;
+        assert!(first.is_err(), "second tip poll fails");
+        assert_eq!(cursor.last_processed_block, 3);
+        assert!(rpc.requests_for("eth_getLogs").is_empty(), "initial history skipped");
+
+        let task = tokio::spawn({
+            let indexer = Arc::clone(&indexer);
+            let rpc = rpc.clone();
+            async move { run_once(indexer, &rpc, &mut cursor, &config).await }
+        });
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+        assert_eq!(ev.seq, 4, "event missed while disconnected is backfilled");
+        assert_eq!(rpc.requests_for("eth_call").len(), 1, "head() only seeded once");
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn live_log_with_indexed_cid_is_skipped() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x0"),
+            _ => Value::Null,
+        }).await;
+        let mut indexed = head_updated_log(1, 1, 0);
+        indexed["data"] = json!("0x");
+        let ws_url = spawn_ws_notifier(vec![indexed, head_updated_log(2, 2, 0)]).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            }
+        });
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+        assert_eq!(ev.seq, 2, "the malformed log is skipped without dropping the connection");
+        assert_eq!(indexer.metrics().reconnects_total, 0);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn subscribe_falls_back_to_address_only_filter() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let subscribes = Arc::new(Mutex::new(Vec::new()));
+        tokio::spawn({
+            let subscribes = Arc::clone(&subscribes);
+            async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(Message::Text(text))) = ws.next().await {
+                    let req: Value = serde_json::from_str(&text).unwrap();
+                    subscribes.lock().unwrap().push(req["params"].clone());
+                    if req["params"][1].get("topics").is_some() {
+                        let message = "data did not match any variant of untagged enum";
+                        let reply = json!({"jsonrpc": "2.0", "id": req["id"], "error": {"code": -32602, "message": message}});
+                        ws.send(Message::Text(reply.to_string())).await.unwrap();
+                        continue;
+                    }
+                    let reply = json!({"jsonrpc": "2.0", "id": req["id"], "result": "0xsub"});
+                    ws.send(Message::Text(reply.to_string())).await.unwrap();
+                    let note = json!({
+                        "jsonrpc": "2.0",
+                        "method": "eth_subscription",
+                        "params": {"subscription": "0xsub", "result": head_updated_log(1, 1, 0)}
+                    });
+                    ws.send(Message::Text(note.to_string())).await.unwrap();
+                }
+            }
+        });
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x0"),
+            _ => Value::Null,
+        }).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            }
+        });
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+        assert_eq!(ev.seq, 1);
+        let subscribes = subscribes.lock().unwrap().clone();
+        assert_eq!(subscribes.len(), 2, "stops at the address-only rung: {subscribes:?}");
+        assert_eq!(subscribes[1], json!(["logs", {"address": format!("0x{}", hex::encode([0x11; 20]))}]));
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn reorder_window_sorts_shuffled_notifications() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x0"),
+            _ => Value::Null,
+        }).await;
+        let shuffled = [(3, 0), (1, 1), (2, 0), (1, 0), (5, 0), (4, 0)];
+        let ws_url = spawn_ws_notifier(
+            shuffled.iter().enumerate().map(|(i, &(block, log_index))| head_updated_log(i as u64, block, log_index)).collect(),
+        ).await;
+        let mut config = test_config(ws_url, http_url);
+        config.reorder_window = 3;
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            }
+        });
+        let mut order = Vec::new();
+        for _ in 0..shuffled.len() {
+            let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+            order.push((ev.block_number, ev.log_index));
+        }
+        assert_eq!(order, vec![(1, 0), (1, 1), (2, 0), (3, 0), (4, 0), (5, 0)]);
+        assert_eq!(indexer.metrics().last_processed_block, 5);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn paused_indexer_buffers_logs_until_resumed() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x0"),
+            _ => Value::Null,
+        }).await;
+        let ws_url = spawn_ws_notifier(vec![head_updated_log(1, 1, 0), head_updated_log(2, 2, 0)]).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        indexer.pause();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            }
+        });
+
+        timeout(Duration::from_secs(5), async {
+            while indexer.metrics().last_processed_block < 2 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("cursor advances while paused");
+        assert!(heads.try_recv().is_err(), "nothing delivered while paused");
+        assert_eq!(indexer.metrics().events_emitted_total, 0);
+
+        indexer.resume().await;
+        assert!(!indexer.is_paused());
+        let seqs: Vec<u64> = (0..2).map(|_| heads.try_recv().unwrap().seq).collect();
+        assert_eq!(seqs, vec![1, 2]);
+        assert_eq!(indexer.current_head().await.map(|h| h.seq), Some(2));
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn full_pause_buffer_drops_oldest_and_counts() {
+        let mut config = test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into());
+        config.pause_buffer_capacity = 2;
+        let indexer = StemIndexer::new(config);
+        let mut heads = indexer.subscribe();
+        indexer.pause();
+        for seq in 1..=3 {
+            let observed = decode_log_to_observed(&head_updated_log(seq, seq, 0)).unwrap();
+            indexer.publish_fetched(Fetched::Head(observed)).await;
+        }
+        assert_eq!(indexer.metrics().pause_dropped_total, 1);
+        indexer.resume().await;
+        let seqs: Vec<u64> = std::iter::from_fn(|| heads.try_recv().ok()).map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+    }
+
+    #[tokio::test]
+    async fn replay_re_emits_range_without_moving_cursor() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_getLogs") => {
+                let q = |k: &str| {
+                    let s = req["params"][0][k].as_str().unwrap();
+                    u64::from_str_radix(s.trim_start_matches("0x"), 16).unwrap()
+                };
+                json!((q("fromBlock")..=q("toBlock")).map(|b| head_updated_log(b, b, 0)).collect::<Vec<_>>())
+            }
+            _ => Value::Null,
+        }).await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), http_url);
+        config.getlogs_max_range = 2;
+        let indexer = StemIndexer::new(config);
+        let mut cursor = Cursor::default();
+        indexer.set_cursor(&mut cursor, 50);
+        // Already published live: replay must not be suppressed by the dedup set.
+        indexer.publish(decode_log_to_observed(&head_updated_log(3, 3, 0)).unwrap()).await;
+        let mut heads = indexer.subscribe();
+        let mut events = indexer.subscribe_events();
+
+        indexer.replay(2, 6).await.unwrap();
+
+        let mut replayed = Vec::new();
+        while let Ok(ev) = events.try_recv() {
+            match ev {
+                IndexerEvent::Replayed(o) => replayed.push(o.block_number),
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert_eq!(replayed, vec![2, 3, 4, 5, 6]);
+        assert!(heads.try_recv().is_err(), "replays stay off subscribe()");
+        let metrics = indexer.metrics();
+        assert_eq!(metrics.last_processed_block, 50);
+        assert_eq!(metrics.events_emitted_total, 1);
+        assert_eq!(indexer.current_head().await.map(|h| h.seq), Some(3));
+    }
+
+    async fn history_indexer(seqs: &'static [u64]) -> StemIndexer {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_getLogs") => {
+                let q = |k: &str| {
+                    let s = req["params"][0][k].as_str().unwrap();
+                    u64::from_str_radix(s.trim_start_matches("0x"), 16).unwrap()
+                };
+                let (lo, hi) = (q("fromBlock"), q("toBlock"));
+                // One head per block, seq taken from `seqs` by block number (block 1 = seqs[0]).
+                json!((lo..=hi)
+                    .filter_map(|b| seqs.get(b as usize - 1).map(|&seq| head_updated_log(seq, b, 0)))
+                    .collect::<Vec<_>>())
+            }
+            _ => Value::Null,
+        }).await;
+        let mut config = test_config("ws://127.0.0.1:1".into(), http_url);
+        config.getlogs_max_range = 2;
+        StemIndexer::new(config)
+    }
+
+    #[tokio::test]
+    async fn head_history_returns_contiguous_seqs_in_order() {
+        let indexer = history_indexer(&[10, 11, 12, 13, 14]).await;
+        let mut heads = indexer.subscribe();
+
+        let history = indexer.head_history(1, 5).await.unwrap();
+
+        assert_eq!(history.iter().map(|h| h.seq).collect::<Vec<_>>(), vec![10, 11, 12, 13, 14]);
+        assert!(heads.try_recv().is_err(), "head_history must not publish");
+        assert_eq!(indexer.current_head().await, None);
+    }
+
+    #[tokio::test]
+    async fn head_history_rejects_gap() {
+        let indexer = history_indexer(&[10, 11, 13, 14]).await;
+
+        let err = indexer.head_history(1, 4).await.unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<IndexerError>(),
+            Some(&IndexerError::SeqGap { expected: 12, found: 13 })
+        );
+    }
+
+    #[tokio::test]
+    async fn subscription_ignores_pending_logs() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x0"),
+            _ => json!([]),
+        }).await;
+        let mut pending = head_updated_log(1, 1, 0);
+        pending["blockNumber"] = Value::Null;
+        pending["logIndex"] = Value::Null;
+        pending["transactionHash"] = Value::Null;
+        let ws_url = spawn_ws_notifier(vec![pending, head_updated_log(1, 1, 0)]).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                let mut cursor = Cursor::new(0);
+                run_once(indexer, &client, &mut cursor, &config).await
+            }
+        });
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("mined event").unwrap();
+        assert_eq!((ev.seq, ev.block_number), (1, 1));
+        assert!(!task.is_finished(), "pending log is not an error");
+        assert_eq!(indexer.metrics().events_emitted_total, 1);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn notifications_for_other_subscriptions_are_ignored() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x0"),
+            _ => json!([]),
+        }).await;
+        let ws_url = spawn_ws_notifier_for(
+            Vec::new(),
+            vec![("0xstale", head_updated_log(1, 1, 0)), ("0xsub", head_updated_log(2, 2, 0))],
+        )
+        .await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                run_once(indexer, &client, &mut Cursor::new(0), &config).await
+            }
+        });
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+        assert_eq!(ev.seq, 2, "stale subscription's event skipped");
+        assert_eq!(indexer.metrics().events_emitted_total, 1);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn subscribe_skips_messages_before_confirmation() {
+        let http_url = spawn_http_rpc(|req| match req["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x0"),
+            _ => json!([]),
+        }).await;
+        let preamble = vec![
+            Message::Ping(Vec::new()),
+            Message::Text(
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscription",
+                    "params": {"subscription": "0xother", "result": "0x1"}
+                })
+                .to_string(),
+            ),
+            Message::Text(json!({"jsonrpc": "2.0", "id": 99, "result": "0xstale"}).to_string()),
+        ];
+        let ws_url = spawn_ws_notifier_with(preamble, vec![head_updated_log(1, 1, 0)]).await;
+        let config = test_config(ws_url, http_url);
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let mut heads = indexer.subscribe();
+        let task = tokio::spawn({
+            let indexer = indexer.clone();
+            async move {
+                let client = HttpRpcClient::new(config.http_url.clone());
+                run_once(indexer, &client, &mut Cursor::new(0), &config).await
+            }
+        });
+        let ev = timeout(Duration::from_secs(5), heads.recv()).await.expect("event").unwrap();
+        assert_eq!(ev.seq, 1);
+        assert!(!task.is_finished(), "handshake succeeded: {:?}", task);
+        task.abort();
+    }
+
+    #[test]
+    fn rewind_forgets_seen_logs_above_target() {
+        let indexer = StemIndexer::new(test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into()));
+        let ev = decode_log_to_observed(&head_updated_log(7, 7, 0)).unwrap();
+        assert!(indexer.seen.lock().unwrap().insert(&ev));
+        assert!(!indexer.seen.lock().unwrap().insert(&ev));
+        let mut cursor = Cursor::new(7);
+        indexer.rewind(&mut cursor, 5);
+        assert!(indexer.seen.lock().unwrap().insert(&ev));
+    }
+
+    #[test]
+    fn rewind_ignores_forward_moves() {
+        let indexer = StemIndexer::new(test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into()));
+        let mut events = indexer.subscribe_events();
+        let mut cursor = Cursor::new(10);
+        indexer.rewind(&mut cursor, 10);
+        indexer.rewind(&mut cursor, 20);
+        assert_eq!(cursor.last_processed_block, 10);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn keepalive_sends_pings() {
+        let http_url = spawn_http_rpc(|_| json!("0x0")).await;
+        let (ws_url, mut pings) = spawn_ws_server(true).await;
+        let mut config = test_config(ws_url, http_url);
+        config.ping_interval_secs = 1;
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let task = tokio::spawn(async move {
+            let client = HttpRpcClient::new(config.http_url.clone());
+            let mut cursor = Cursor::new(0);
+            run_once(indexer, &client, &mut cursor, &config).await
+        });
+        for _ in 0..2 {
+            timeout(Duration::from_secs(5), pings.recv())
+                .await
+                .expect("ping within interval")
+                .expect("server alive");
+        }
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn keepalive_missing_pong_fails_run_once() {
+        let http_url = spawn_http_rpc(|_| json!("0x0")).await;
+        let (ws_url, _pings) = spawn_ws_server(false).await;
+        let mut config = test_config(ws_url, http_url);
+        config.ping_interval_secs = 1;
+        config.pong_timeout_secs = 1;
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let client = HttpRpcClient::new(config.http_url.clone());
+        let mut cursor = Cursor::new(0);
+        let res = timeout(Duration::from_secs(5), run_once(indexer, &client, &mut cursor, &config))
+            .await
+            .expect("run_once should give up on a dead connection");
+        let err = res.unwrap_err();
+        assert!(matches!(err, IndexerError::Timeout(_)), "{err:?}");
+        assert!(err.to_string().contains("no pong"));
+    }
+
+    #[tokio::test]
+    async fn run_once_reports_ws_connect_failure() {
+        let config = test_config("ws://127.0.0.1:1".into(), "http://127.0.0.1:1".into());
+        let indexer = Arc::new(StemIndexer::new(config.clone()));
+        let client = HttpRpcClient::new(config.http_url.clone());
+        let err = run_once(indexer, &client, &mut Cursor::new(0), &config).await.unwrap_err();
+        assert!(matches!(err, IndexerError::WsConnect(_)), "{err:?}");
+    }
+
+    #[test]
+    fn reconnect_backoff_by_error_kind() {
+        let reconnection = ReconnectionConfig {
+            initial_backoff_secs: 1,
+            max_backoff_secs: 30,
+            max_attempts: None,
+        };
+        let transient = [
+            IndexerError::WsConnect("refused".into()),
+            IndexerError::Subscribe("rejected".into()),
+            IndexerError::Rpc("-32603".into()),
+            IndexerError::Timeout("subscribe".into()),
+        ];
+        let mut backoff = Exponential { jitter: Duration::ZERO, ..Exponential::from(&reconnection) };
+        for err in &transient {
+            assert!(err.is_transient(), "{err}");
+            assert_eq!(reconnect_backoff(&reconnection, &mut backoff, 1, err), Duration::from_secs(2), "{err}");
+        }
+        let fatal = [
+            IndexerError::Decode("bad log".into()),
+            IndexerError::UnknownStartHash([0xab; 32]),
+            IndexerError::BlockTooDense { block: 7 },
+            IndexerError::SeqGap { expected: 4, found: 5 },
+        ];
+        for err in &fatal {
+            assert!(!err.is_transient(), "{err}");
+            assert_eq!(reconnect_backoff(&reconnection, &mut backoff, 1, err), Duration::from_secs(30), "{err}");
+        }
+    }
+
+    #[test]
+    fn backfill_errors_keep_their_kind() {
+        let dense = anyhow::Error::new(IndexerError::BlockTooDense { block: 3 }).context("backfill");
+        assert_eq!(IndexerError::from_backfill(dense), IndexerError::BlockTooDense { block: 3 });
+        let other = IndexerError::from_backfill(anyhow::anyhow!("HTTP request failed"));
+        assert!(matches!(other, IndexerError::Rpc(ref m) if m.contains("HTTP request failed")));
+    }
+}
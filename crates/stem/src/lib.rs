@@ -5,8 +5,11 @@
 //! - **Finalizer**: consumes indexer output and emits only events that are eligible per a
 //!   configurable [Strategy] (e.g. [ConfirmationDepth]) and pass the canonical cross-check
 //!   (`Stem.head()`), giving reorg-safe finalized output.
+//! - **Membrane** (`membrane` feature, on by default): epoch-scoped Cap'n Proto capabilities
+//!   over finalized heads. Build with `--no-default-features` to drop capnp entirely.
 
-#[allow(unused_parens)] // generated capnp code
+#[cfg(feature = "membrane")]
+#[allow(unused_parens, clippy::extra_unused_type_parameters)] // generated capnp code
 pub mod stem_capnp {
     include!(concat!(env!("OUT_DIR"), "/capnp/stem_capnp.rs"));
 }
@@ -15,20 +18,48 @@ pub mod abi;
 pub mod config;
 pub mod cursor;
 pub mod finalizer;
+#[cfg(feature = "health")]
+pub mod health;
 pub mod indexer;
+#[cfg(feature = "membrane")]
 pub mod membrane;
+pub mod rpc;
+pub mod sink;
+#[cfg(test)]
+mod test_support;
 
-pub use abi::{CurrentHead, HeadUpdatedObserved};
-pub use config::{IndexerConfig, ReconnectionConfig};
-pub use cursor::Cursor;
+pub use abi::{
+    cid_codec, cid_equivalent, cid_hash, cid_string, decode_log_with, CompactCid, CurrentHead, DecodedLog, HeadUpdatedObserved,
+    LogDecoder,
+};
+#[cfg(feature = "test-util")]
+pub use abi::HeadUpdatedObservedBuilder;
+pub use config::{
+    AddressResolver, BackoffStrategy, ConfigError, ContractName, EmitOrder, Exponential, Fixed, HeadMode, IndexerConfig, IndexerConfigBuilder,
+    ReconnectionConfig, RetryConfig, StartFrom, TopicDecoder, TransportMode,
+};
+pub use cursor::{read_cursor_file, write_cursor_file, Cursor};
 pub use finalizer::{
-    ConfirmationDepth, FinalizedEvent, Finalizer, FinalizerBuilder, FinalizerError, Strategy,
+    ConfirmationDepth, Debounced, DedupKey, FinalityContext, FinalizedEvent, FinalizedTag, Finalizer,
+    FinalizerBuilder, FinalizerError, ImmediateCanonical, OverflowPolicy, PendingStatus, PerWriterDepth, RootValidator, SafeTag, Strategy,
+    TipStrategy, assert_same_chain, wait_for_seq,
+};
+pub use indexer::{
+    block_number_at, current_block_number, current_block_number_with, fetch_current_head,
+    BackfillProgress, BackfillProgressFn, BlockTag, HeadSnapshot, HeadSource, IndexerError, IndexerEvent, IndexerMetrics, IndexerState, StemIndexer, SyncStatus,
 };
-pub use indexer::{current_block_number, StemIndexer};
+#[cfg(feature = "membrane")]
 pub use membrane::{
-    membrane_client, Epoch, EpochGuard, MembraneServer, NoExtension,
-    SessionExtensionBuilder, StatusPollerServer, fill_epoch_builder,
+    graft_challenge, graft_challenge_message, membrane_client, Epoch, EpochGuard, EpochMatch,
+    MembraneServer, NoExtension, SessionExtensionBuilder, SessionRevocations, StatusPollerServer,
+    connect_membrane, fill_epoch_builder, serve_membrane, GRAFT_DOMAIN,
 };
+#[cfg(feature = "health")]
+pub use health::serve_health;
+pub use rpc::{HttpRpcClient, RpcClient, RpcError};
+#[cfg(feature = "test-util")]
+pub use rpc::MockRpcClient;
+pub use sink::{FinalizedSink, FsyncPolicy, JsonlFileSink, SinkError, StdoutJsonSink};
 
 /// Current head state (alias for ABI CurrentHead).
 pub type Head = CurrentHead;
@@ -1,20 +1,54 @@
-//! Pure-Rust Membrane server: epoch validity via seq equality (Approach A),
-//! backed by `watch::Receiver<Epoch>`, exposed over capnp-rpc.
+//! Pure-Rust Membrane server: epoch validity via seq equality (Approach A) or, optionally,
+//! seq and head equality (Approach B; see [EpochMatch]), backed by `watch::Receiver<Epoch>`,
+//! exposed over capnp-rpc.
 
+use crate::abi::HeadUpdatedObserved;
+use crate::finalizer::FinalizedEvent;
 use crate::stem_capnp;
 use capnp::capability::Promise;
 use capnp::Error;
-use capnp_rpc::new_client;
+use capnp_rpc::{new_client, pry, rpc_twoparty_capnp, twoparty, RpcSystem};
+use k256::ecdsa::signature::Verifier;
+use k256::ecdsa::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
 use tokio::sync::watch;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
 /// Epoch value used by the membrane (matches capnp struct Epoch).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Epoch {
     pub seq: u64,
+    #[serde(with = "crate::abi::hex_serde::vec")]
     pub head: Vec<u8>,
     pub adopted_block: u64,
 }
 
+impl From<&FinalizedEvent> for Epoch {
+    fn from(ev: &FinalizedEvent) -> Self {
+        Self {
+            seq: ev.seq,
+            head: ev.cid.clone(),
+            adopted_block: ev.block_number,
+        }
+    }
+}
+
+impl From<&HeadUpdatedObserved> for Epoch {
+    fn from(ev: &HeadUpdatedObserved) -> Self {
+        Self {
+            seq: ev.seq,
+            head: ev.cid.clone(),
+            adopted_block: ev.block_number,
+        }
+    }
+}
+
 pub fn fill_epoch_builder(
     builder: &mut stem_capnp::epoch::Builder<'_>,
     epoch: &Epoch,
@@ -26,22 +60,156 @@ pub fn fill_epoch_builder(
     Ok(())
 }
 
+/// Domain string passed to `Signer.sign` when the membrane challenges a graft.
+pub const GRAFT_DOMAIN: &str = "stem.membrane.graft.v0";
+
+/// Graft challenge nonce for `epoch`: first 8 bytes (big-endian) of `keccak256(seq_be || head)`.
+pub fn graft_challenge(epoch: &Epoch) -> u64 {
+    let mut hasher = Keccak256::new();
+    hasher.update(epoch.seq.to_be_bytes());
+    hasher.update(&epoch.head);
+    let digest = hasher.finalize();
+    u64::from_be_bytes(digest[..8].try_into().expect("8 bytes"))
+}
+
+/// Message a signer must sign (ECDSA/secp256k1 over SHA-256, 64-byte `r || s`) to answer
+/// a graft challenge: `domain || nonce_be`.
+pub fn graft_challenge_message(domain: &str, nonce: u64) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(domain.len() + 8);
+    msg.extend_from_slice(domain.as_bytes());
+    msg.extend_from_slice(&nonce.to_be_bytes());
+    msg
+}
+
+/// How an [EpochGuard] decides that the current epoch is still the one a session was issued under.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EpochMatch {
+    /// Approach A: same `seq`.
+    #[default]
+    Seq,
+    /// Approach B: same `seq` and same `head` bytes, so a head re-set at an unchanged seq
+    /// (never expected on-chain, but possible in tests or with a buggy contract) is stale.
+    SeqAndHead,
+}
+
 /// Guard that checks whether the epoch under which a capability was issued is
 /// still current. Shared by all session-scoped capability servers so that
 /// every RPC hard-fails once the epoch advances.
+///
+/// With a grace period, a session exactly one epoch behind stays valid until `grace`
+/// has elapsed since the advance was first observed (by any clone of the guard), giving
+/// clients time to re-graft. Sessions two or more epochs behind are always stale.
 #[derive(Clone)]
 pub struct EpochGuard {
     pub issued_seq: u64,
     pub receiver: watch::Receiver<Epoch>,
+    grace: Duration,
+    advanced_at: Arc<OnceLock<Instant>>,
+    issued_head: Option<Vec<u8>>,
 }
 
 impl EpochGuard {
+    pub fn new(issued_seq: u64, receiver: watch::Receiver<Epoch>) -> Self {
+        Self {
+            issued_seq,
+            receiver,
+            grace: Duration::ZERO,
+            advanced_at: Arc::new(OnceLock::new()),
+            issued_head: None,
+        }
+    }
+
+    /// Also require the current head to equal `head` ([EpochMatch::SeqAndHead]).
+    pub fn with_issued_head(mut self, head: Vec<u8>) -> Self {
+        self.issued_head = Some(head);
+        self
+    }
+
+    /// Keep accepting calls for `grace` after the epoch advances by one (default: zero).
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
     pub fn check(&self) -> Result<(), Error> {
-        let current = self.receiver.borrow();
-        if current.seq != self.issued_seq {
-            return Err(Error::failed("staleEpoch: session epoch no longer current".to_string()));
+        let (current, same_head) = {
+            let epoch = self.receiver.borrow();
+            let same_head = self.issued_head.as_ref().is_none_or(|head| *head == epoch.head);
+            (epoch.seq, same_head)
+        };
+        if current == self.issued_seq {
+            if same_head {
+                return Ok(());
+            }
+            return Err(Error::failed("staleEpoch: head changed under the session's seq".to_string()));
         }
-        Ok(())
+        if !self.grace.is_zero() && current == self.issued_seq.wrapping_add(1) {
+            let advanced_at = self.advanced_at.get_or_init(Instant::now);
+            if advanced_at.elapsed() < self.grace {
+                return Ok(());
+            }
+        }
+        Err(Error::failed("staleEpoch: session epoch no longer current".to_string()))
+    }
+
+    /// True once the epoch sender has been dropped (no further epochs will be adopted).
+    pub fn is_closed(&self) -> bool {
+        self.receiver.has_changed().is_err()
+    }
+}
+
+/// Bounded history of adopted epochs, oldest evicted first.
+#[derive(Clone)]
+struct EpochHistory {
+    capacity: usize,
+    epochs: Arc<Mutex<VecDeque<Epoch>>>,
+}
+
+impl EpochHistory {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            epochs: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    fn push(&self, epoch: Epoch) {
+        let mut epochs = self.epochs.lock().expect("epoch history lock");
+        if epochs.back().is_some_and(|last| last.seq == epoch.seq) {
+            return;
+        }
+        if epochs.len() == self.capacity {
+            epochs.pop_front();
+        }
+        epochs.push_back(epoch);
+    }
+
+    fn get(&self, seq: u64) -> Option<Epoch> {
+        let epochs = self.epochs.lock().expect("epoch history lock");
+        epochs.iter().rev().find(|e| e.seq == seq).cloned()
+    }
+
+    /// Record every epoch observed on `receiver` until its sender is dropped.
+    async fn follow(self, mut receiver: watch::Receiver<Epoch>) {
+        while receiver.changed().await.is_ok() {
+            let epoch = receiver.borrow_and_update().clone();
+            self.push(epoch);
+        }
+    }
+}
+
+/// Shared set of individually revoked session ids (see [MembraneServer::session_revocations]).
+#[derive(Clone, Default)]
+pub struct SessionRevocations(Arc<Mutex<HashSet<u64>>>);
+
+impl SessionRevocations {
+    /// Revoke the session with this id; its poller reports `unauthorized` from now on.
+    pub fn revoke(&self, session_id: u64) {
+        self.0.lock().expect("session revocations lock").insert(session_id);
+    }
+
+    pub fn is_revoked(&self, session_id: u64) -> bool {
+        self.0.lock().expect("session revocations lock").contains(&session_id)
     }
 }
 
@@ -85,6 +253,13 @@ where
 {
     receiver: watch::Receiver<Epoch>,
     ext_builder: F,
+    authority: Option<VerifyingKey>,
+    stale_grace: Duration,
+    epoch_match: EpochMatch,
+    revoked: Arc<AtomicBool>,
+    revoked_sessions: SessionRevocations,
+    next_session_id: AtomicU64,
+    history: Option<EpochHistory>,
     _phantom: std::marker::PhantomData<SessionExt>,
 }
 
@@ -97,13 +272,115 @@ where
         Self {
             receiver,
             ext_builder,
+            authority: None,
+            stale_grace: Duration::ZERO,
+            epoch_match: EpochMatch::default(),
+            revoked: Arc::new(AtomicBool::new(false)),
+            revoked_sessions: SessionRevocations::default(),
+            next_session_id: AtomicU64::new(1),
+            history: None,
             _phantom: std::marker::PhantomData,
         }
     }
 
+    /// Like [Self::new], but also retains the last `capacity` adopted epochs for `epochBySeq`.
+    ///
+    /// Spawns a task (requires a tokio runtime) that records each epoch seen on `receiver`;
+    /// like any `watch` consumer it may skip epochs superseded before it observes them.
+    pub fn with_history(receiver: watch::Receiver<Epoch>, ext_builder: F, capacity: usize) -> Self {
+        let mut server = Self::new(receiver.clone(), ext_builder);
+        if capacity > 0 {
+            let history = EpochHistory::new(capacity);
+            history.push(receiver.borrow().clone());
+            tokio::spawn(history.clone().follow(receiver));
+            server.history = Some(history);
+        }
+        server
+    }
+
+    /// Require grafting signers to answer a challenge over the current epoch with a signature
+    /// that verifies under `key` (see [graft_challenge_message]); otherwise graft fails with
+    /// an `unauthorized` error. Without an authority, sessions are issued unconditionally.
+    pub fn with_authority(mut self, key: VerifyingKey) -> Self {
+        self.authority = Some(key);
+        self
+    }
+
+    /// Grace period for sessions one epoch behind (see [EpochGuard::with_grace]).
+    pub fn with_stale_grace(mut self, grace: Duration) -> Self {
+        self.stale_grace = grace;
+        self
+    }
+
+    /// How issued sessions detect a stale epoch (default [EpochMatch::Seq]).
+    pub fn with_epoch_match(mut self, epoch_match: EpochMatch) -> Self {
+        self.epoch_match = epoch_match;
+        self
+    }
+
+    /// Share a revocation flag with every issued session: once set, their pollers report
+    /// `Status::Unauthorized` (e.g. when the authority's signer has been revoked).
+    pub fn with_revocation(mut self, revoked: Arc<AtomicBool>) -> Self {
+        self.revoked = revoked;
+        self
+    }
+
+    /// Handle for revoking individual sessions by the `id` returned from graft.
+    pub fn session_revocations(&self) -> SessionRevocations {
+        self.revoked_sessions.clone()
+    }
+
+    /// Revoke one issued session by id, leaving other sessions under the same epoch intact.
+    pub fn revoke_session(&self, session_id: u64) {
+        self.revoked_sessions.revoke(session_id);
+    }
+
     fn get_current_epoch(&self) -> Epoch {
         self.receiver.borrow().clone()
     }
+
+    /// Fail once the epoch sender has been dropped: the current epoch is then frozen, and
+    /// serving it would hide that the pipeline feeding epochs is gone.
+    fn check_epoch_source(&self) -> Result<(), Error> {
+        if self.receiver.has_changed().is_err() {
+            tracing::warn!("epoch sender dropped; refusing membrane call");
+            return Err(Error::failed("internalError: epoch source closed".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Current epoch if `seq` matches it, else the retained epoch with that seq (if any).
+    pub fn find_epoch(&self, seq: u64) -> Option<Epoch> {
+        let current = self.get_current_epoch();
+        if current.seq == seq {
+            return Some(current);
+        }
+        self.history.as_ref().and_then(|h| h.get(seq))
+    }
+
+    fn issue_session(
+        &self,
+        epoch: &Epoch,
+        results: &mut stem_capnp::membrane::GraftResults<SessionExt>,
+    ) -> Result<(), Error> {
+        let mut session_builder = results.get().init_session();
+        fill_epoch_builder(&mut session_builder.reborrow().init_issued_epoch(), epoch)
+            .map_err(|_| Error::failed("fill issued epoch".to_string()))?;
+        let session_id = self.next_session_id.fetch_add(1, Ordering::Relaxed);
+        session_builder.set_id(session_id);
+        let mut guard = EpochGuard::new(epoch.seq, self.receiver.clone()).with_grace(self.stale_grace);
+        if self.epoch_match == EpochMatch::SeqAndHead {
+            guard = guard.with_issued_head(epoch.head.clone());
+        }
+        let poller = StatusPollerServer {
+            guard: guard.clone(),
+            revoked: Arc::clone(&self.revoked),
+            session_id,
+            revoked_sessions: self.revoked_sessions.clone(),
+        };
+        session_builder.reborrow().set_status_poller(new_client(poller));
+        self.ext_builder.build(&guard, session_builder.reborrow().init_extension())
+    }
 }
 
 #[allow(refining_impl_trait)]
@@ -114,33 +391,87 @@ where
 {
     fn graft(
         self: capnp::capability::Rc<Self>,
-        _params: stem_capnp::membrane::GraftParams<SessionExt>,
+        params: stem_capnp::membrane::GraftParams<SessionExt>,
         mut results: stem_capnp::membrane::GraftResults<SessionExt>,
     ) -> Promise<(), Error> {
+        pry!(self.check_epoch_source());
         let epoch = self.get_current_epoch();
-        let mut session_builder = results.get().init_session();
-        if fill_epoch_builder(&mut session_builder.reborrow().init_issued_epoch(), &epoch).is_err() {
-            return Promise::err(Error::failed("fill issued epoch".to_string()));
-        }
-        let guard = EpochGuard {
-            issued_seq: epoch.seq,
-            receiver: self.receiver.clone(),
+        let Some(authority) = self.authority else {
+            return match self.issue_session(&epoch, &mut results) {
+                Ok(()) => Promise::ok(()),
+                Err(e) => Promise::err(e),
+            };
         };
-        let poller = StatusPollerServer { guard: guard.clone() };
-        session_builder.reborrow().set_status_poller(new_client(poller));
 
-        if let Err(e) = self.ext_builder.build(&guard, session_builder.reborrow().init_extension()) {
-            return Promise::err(e);
-        }
+        // Challenge the signer over the epoch being issued; only a valid signature gets a session.
+        let signer = pry!(pry!(params.get()).get_signer());
+        let nonce = graft_challenge(&epoch);
+        let mut req = signer.sign_request();
+        req.get().set_domain(GRAFT_DOMAIN);
+        req.get().set_nonce(nonce);
+        Promise::from_future(async move {
+            let response = req.send().promise.await?;
+            let sig = response.get()?.get_sig()?;
+            let msg = graft_challenge_message(GRAFT_DOMAIN, nonce);
+            let verified = Signature::from_slice(sig)
+                .map(|sig| authority.verify(&msg, &sig).is_ok())
+                .unwrap_or(false);
+            if !verified {
+                return Err(Error::failed("unauthorized: signer failed graft challenge".to_string()));
+            }
+            self.issue_session(&epoch, &mut results)
+        })
+    }
 
-        Promise::ok(())
+    fn epoch_by_seq(
+        self: capnp::capability::Rc<Self>,
+        params: stem_capnp::membrane::EpochBySeqParams<SessionExt>,
+        mut results: stem_capnp::membrane::EpochBySeqResults<SessionExt>,
+    ) -> Promise<(), Error> {
+        pry!(self.check_epoch_source());
+        let seq = pry!(params.get()).get_seq();
+        match self.find_epoch(seq) {
+            Some(epoch) => {
+                pry!(fill_epoch_builder(&mut results.get().init_epoch(), &epoch));
+                Promise::ok(())
+            }
+            None => Promise::err(Error::failed(format!("epochNotFound: no retained epoch with seq {seq}"))),
+        }
     }
 }
 
 /// StatusPoller server: epoch-scoped; pollStatus returns an RPC error when the
 /// epoch has advanced past the one under which this capability was issued.
+///
+/// Otherwise reports `unauthorized` once `revoked` is set or `session_id` is in
+/// `revoked_sessions`, and `internalError` once the epoch channel is closed (sender dropped).
 pub struct StatusPollerServer {
     pub guard: EpochGuard,
+    pub revoked: Arc<AtomicBool>,
+    pub session_id: u64,
+    pub revoked_sessions: SessionRevocations,
+}
+
+impl StatusPollerServer {
+    pub fn new(guard: EpochGuard) -> Self {
+        Self {
+            guard,
+            revoked: Arc::new(AtomicBool::new(false)),
+            session_id: 0,
+            revoked_sessions: SessionRevocations::default(),
+        }
+    }
+
+    fn status(&self) -> Result<stem_capnp::Status, Error> {
+        if self.revoked.load(Ordering::Acquire) || self.revoked_sessions.is_revoked(self.session_id) {
+            return Ok(stem_capnp::Status::Unauthorized);
+        }
+        if self.guard.is_closed() {
+            return Ok(stem_capnp::Status::InternalError);
+        }
+        self.guard.check()?;
+        Ok(stem_capnp::Status::Ok)
+    }
 }
 
 #[allow(refining_impl_trait)]
@@ -150,11 +481,13 @@ impl stem_capnp::status_poller::Server for StatusPollerServer {
         _: stem_capnp::status_poller::PollStatusParams,
         mut results: stem_capnp::status_poller::PollStatusResults,
     ) -> Promise<(), Error> {
-        if let Err(e) = self.guard.check() {
-            return Promise::err(e);
+        match self.status() {
+            Ok(status) => {
+                results.get().set_status(status);
+                Promise::ok(())
+            }
+            Err(e) => Promise::err(e),
         }
-        results.get().set_status(stem_capnp::Status::Ok);
-        Promise::ok(())
     }
 }
 
@@ -167,6 +500,53 @@ pub fn membrane_client(receiver: watch::Receiver<Epoch>) -> stem_capnp::membrane
     new_client(MembraneServer::new(receiver, NoExtension))
 }
 
+/// Client side of a remote Membrane: builds an [RpcSystem] over `network` and bootstraps the
+/// Membrane capability the peer (e.g. [serve_membrane]) exports.
+///
+/// The caller drives the returned `RpcSystem` on its own runtime (it is `!Send`, so typically
+/// `tokio::task::spawn_local` inside a `LocalSet`); calls on the client make progress only while
+/// it runs.
+pub fn connect_membrane(
+    network: impl capnp_rpc::VatNetwork<rpc_twoparty_capnp::Side> + 'static,
+) -> (
+    RpcSystem<rpc_twoparty_capnp::Side>,
+    stem_capnp::membrane::Client<capnp::any_pointer::Owned>,
+) {
+    let mut rpc_system = RpcSystem::new(Box::new(network), None);
+    let membrane = rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+    (rpc_system, membrane)
+}
+
+/// Serves the Membrane over capnp-rpc (two-party) on every connection accepted from `listener`.
+///
+/// Each connection is bootstrapped with a `MembraneServer` (using `NoExtension`) backed by
+/// `receiver`. RPC systems are `!Send`, so this must run inside a `tokio::task::LocalSet`.
+/// Returns only if accepting fails.
+pub async fn serve_membrane(
+    receiver: watch::Receiver<Epoch>,
+    listener: TcpListener,
+) -> std::io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        stream.set_nodelay(true)?;
+        let (reader, writer) = stream.into_split();
+        let network = twoparty::VatNetwork::new(
+            reader.compat(),
+            writer.compat_write(),
+            rpc_twoparty_capnp::Side::Server,
+            Default::default(),
+        );
+        let client = membrane_client(receiver.clone());
+        let rpc_system = RpcSystem::new(Box::new(network), Some(client.client));
+        tracing::debug!(%peer, "membrane connection accepted");
+        tokio::task::spawn_local(async move {
+            if let Err(e) = rpc_system.await {
+                tracing::debug!(%peer, reason = %e, "membrane connection closed");
+            }
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,14 +562,301 @@ mod tests {
     #[tokio::test]
     async fn status_poller_check_epoch_fails_when_seq_differs() {
         let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
-        let guard = EpochGuard {
-            issued_seq: 1,
-            receiver: rx.clone(),
-        };
+        let guard = EpochGuard::new(1, rx.clone());
         assert!(guard.check().is_ok());
         tx.send(epoch(2, b"head2", 101)).unwrap();
         let res = guard.check();
         assert!(res.is_err());
         assert!(res.unwrap_err().to_string().contains("staleEpoch"));
     }
+
+    #[test]
+    fn grace_keeps_previous_epoch_valid_until_expiry() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let guard = EpochGuard::new(1, rx).with_grace(Duration::from_millis(100));
+        tx.send(epoch(2, b"head2", 101)).unwrap();
+        assert!(guard.check().is_ok(), "within grace");
+        assert!(guard.clone().check().is_ok(), "clones share the advance timestamp");
+        std::thread::sleep(Duration::from_millis(150));
+        let res = guard.check();
+        assert!(res.unwrap_err().to_string().contains("staleEpoch"), "past grace");
+    }
+
+    #[test]
+    fn grace_never_covers_two_epochs_behind() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let guard = EpochGuard::new(1, rx).with_grace(Duration::from_secs(60));
+        tx.send(epoch(3, b"head3", 102)).unwrap();
+        assert!(guard.check().unwrap_err().to_string().contains("staleEpoch"));
+    }
+
+    #[test]
+    fn seq_match_ignores_head_changes() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let guard = EpochGuard::new(1, rx);
+        tx.send(epoch(1, b"head1", 100)).unwrap();
+        assert!(guard.check().is_ok(), "same seq, same head");
+        tx.send(epoch(1, b"other", 100)).unwrap();
+        assert!(guard.check().is_ok(), "Approach A only compares seq");
+    }
+
+    #[test]
+    fn seq_and_head_match_rejects_head_reset_at_same_seq() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let guard = EpochGuard::new(1, rx).with_issued_head(b"head1".to_vec());
+        tx.send(epoch(1, b"head1", 100)).unwrap();
+        assert!(guard.check().is_ok(), "same seq, same head");
+        tx.send(epoch(1, b"other", 100)).unwrap();
+        assert!(guard.check().unwrap_err().to_string().contains("staleEpoch"));
+    }
+
+    #[tokio::test]
+    async fn membrane_epoch_match_applies_to_issued_sessions() {
+        for (mode, expect_ok) in [(EpochMatch::Seq, true), (EpochMatch::SeqAndHead, false)] {
+            let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
+            let membrane: stem_capnp::membrane::Client<capnp::any_pointer::Owned> =
+                new_client(MembraneServer::new(rx, NoExtension).with_epoch_match(mode));
+            let response = membrane.graft_request().send().promise.await.unwrap();
+            let poller = response.get().unwrap().get_session().unwrap().get_status_poller().unwrap();
+            tx.send(epoch(1, b"head1-reset", 100)).unwrap();
+            let res = poller.poll_status_request().send().promise.await;
+            assert_eq!(res.is_ok(), expect_ok, "{mode:?}");
+        }
+    }
+
+    async fn poll(poller: StatusPollerServer) -> Result<stem_capnp::Status, Error> {
+        let client: stem_capnp::status_poller::Client = new_client(poller);
+        let response = client.poll_status_request().send().promise.await?;
+        Ok(response.get()?.get_status()?)
+    }
+
+    #[tokio::test]
+    async fn poll_status_ok_under_current_epoch() {
+        let (_tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let poller = StatusPollerServer::new(EpochGuard::new(1, rx));
+        assert_eq!(poll(poller).await.unwrap(), stem_capnp::Status::Ok);
+    }
+
+    #[tokio::test]
+    async fn poll_status_unauthorized_when_revoked() {
+        let (_tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let poller = StatusPollerServer::new(EpochGuard::new(1, rx));
+        poller.revoked.store(true, Ordering::Release);
+        assert_eq!(poll(poller).await.unwrap(), stem_capnp::Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn poll_status_internal_error_when_channel_closed() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let poller = StatusPollerServer::new(EpochGuard::new(1, rx));
+        drop(tx);
+        assert_eq!(poll(poller).await.unwrap(), stem_capnp::Status::InternalError);
+    }
+
+    #[tokio::test]
+    async fn poll_status_stale_epoch_is_rpc_error() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let poller = StatusPollerServer::new(EpochGuard::new(1, rx));
+        tx.send(epoch(2, b"head2", 101)).unwrap();
+        let err = poll(poller).await.unwrap_err();
+        assert!(err.to_string().contains("staleEpoch"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn membrane_revocation_reaches_issued_sessions() {
+        let (_tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let revoked = Arc::new(AtomicBool::new(false));
+        let membrane: stem_capnp::membrane::Client<capnp::any_pointer::Owned> =
+            new_client(MembraneServer::new(rx, NoExtension).with_revocation(Arc::clone(&revoked)));
+        let response = membrane.graft_request().send().promise.await.unwrap();
+        let poller = response.get().unwrap().get_session().unwrap().get_status_poller().unwrap();
+        revoked.store(true, Ordering::Release);
+        let status = poller.poll_status_request().send().promise.await.unwrap();
+        assert_eq!(status.get().unwrap().get_status().unwrap(), stem_capnp::Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn revoke_session_only_affects_that_session() {
+        let (_tx, rx) = watch::channel(epoch(1, b"head1", 100));
+        let server = MembraneServer::new(rx, NoExtension);
+        let revocations = server.session_revocations();
+        let membrane: stem_capnp::membrane::Client<capnp::any_pointer::Owned> = new_client(server);
+
+        let mut sessions = Vec::new();
+        for _ in 0..2 {
+            let response = membrane.graft_request().send().promise.await.unwrap();
+            let session = response.get().unwrap().get_session().unwrap();
+            sessions.push((session.get_id(), session.get_status_poller().unwrap()));
+        }
+        let (revoked_id, revoked_poller) = &sessions[0];
+        let (other_id, other_poller) = &sessions[1];
+        assert_ne!(revoked_id, other_id);
+
+        let status = |poller: &stem_capnp::status_poller::Client| {
+            let req = poller.poll_status_request();
+            async move { req.send().promise.await.unwrap().get().unwrap().get_status().unwrap() }
+        };
+        assert_eq!(status(revoked_poller).await, stem_capnp::Status::Ok);
+        revocations.revoke(*revoked_id);
+        assert_eq!(status(revoked_poller).await, stem_capnp::Status::Unauthorized);
+        assert_eq!(status(other_poller).await, stem_capnp::Status::Ok);
+    }
+
+    async fn epoch_by_seq(
+        membrane: &stem_capnp::membrane::Client<capnp::any_pointer::Owned>,
+        seq: u64,
+    ) -> Result<u64, Error> {
+        let mut req = membrane.epoch_by_seq_request();
+        req.get().set_seq(seq);
+        let response = req.send().promise.await?;
+        Ok(response.get()?.get_epoch()?.get_seq())
+    }
+
+    /// Publish an epoch and let the history task record it.
+    async fn advance(tx: &watch::Sender<Epoch>, seq: u64) {
+        tx.send(epoch(seq, format!("head{seq}").as_bytes(), 100 + seq)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    #[tokio::test]
+    async fn epoch_by_seq_returns_superseded_epoch() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 101));
+        let membrane: stem_capnp::membrane::Client<capnp::any_pointer::Owned> =
+            new_client(MembraneServer::with_history(rx, NoExtension, 2));
+        advance(&tx, 2).await;
+        assert_eq!(epoch_by_seq(&membrane, 1).await.unwrap(), 1);
+        assert_eq!(epoch_by_seq(&membrane, 2).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn epoch_by_seq_evicts_oldest() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 101));
+        let membrane: stem_capnp::membrane::Client<capnp::any_pointer::Owned> =
+            new_client(MembraneServer::with_history(rx, NoExtension, 2));
+        for seq in 2..=4 {
+            advance(&tx, seq).await;
+        }
+        assert_eq!(epoch_by_seq(&membrane, 3).await.unwrap(), 3);
+        let err = epoch_by_seq(&membrane, 1).await.unwrap_err();
+        assert!(err.to_string().contains("epochNotFound"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn epoch_by_seq_without_history_only_knows_current() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 101));
+        let membrane = membrane_client(rx);
+        advance(&tx, 2).await;
+        assert_eq!(epoch_by_seq(&membrane, 2).await.unwrap(), 2);
+        assert!(epoch_by_seq(&membrane, 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn calls_fail_once_epoch_sender_is_dropped() {
+        let (tx, rx) = watch::channel(epoch(1, b"head1", 101));
+        let membrane = membrane_client(rx);
+        assert!(membrane.graft_request().send().promise.await.is_ok());
+        drop(tx);
+        let err = membrane.graft_request().send().promise.await.err().expect("graft fails");
+        assert!(err.to_string().contains("internalError"), "{err}");
+        let err = epoch_by_seq(&membrane, 1).await.unwrap_err();
+        assert!(err.to_string().contains("internalError"), "{err}");
+    }
+
+    /// Signer answering graft challenges with a real secp256k1 key.
+    struct KeySigner(k256::ecdsa::SigningKey);
+
+    #[allow(refining_impl_trait)]
+    impl stem_capnp::signer::Server for KeySigner {
+        fn sign(
+            self: capnp::capability::Rc<Self>,
+            params: stem_capnp::signer::SignParams,
+            mut results: stem_capnp::signer::SignResults,
+        ) -> Promise<(), Error> {
+            use k256::ecdsa::signature::Signer;
+            let params = pry!(params.get());
+            let domain = pry!(pry!(params.get_domain()).to_str());
+            let sig: Signature = self.0.sign(&graft_challenge_message(domain, params.get_nonce()));
+            results.get().set_sig(&sig.to_bytes());
+            Promise::ok(())
+        }
+    }
+
+    fn signing_key(byte: u8) -> k256::ecdsa::SigningKey {
+        k256::ecdsa::SigningKey::from_slice(&[byte; 32]).unwrap()
+    }
+
+    async fn graft_with(authority: &k256::ecdsa::SigningKey, signer: k256::ecdsa::SigningKey) -> Result<u64, Error> {
+        let (_tx, rx) = watch::channel(epoch(7, b"head7", 100));
+        let server = MembraneServer::new(rx, NoExtension).with_authority(*authority.verifying_key());
+        let membrane: stem_capnp::membrane::Client<capnp::any_pointer::Owned> = new_client(server);
+        let mut req = membrane.graft_request();
+        req.get().set_signer(new_client(KeySigner(signer)));
+        let response = req.send().promise.await?;
+        Ok(response.get()?.get_session()?.get_issued_epoch()?.get_seq())
+    }
+
+    #[tokio::test]
+    async fn graft_with_authority_accepts_matching_signer() {
+        let key = signing_key(1);
+        assert_eq!(graft_with(&key, key.clone()).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn graft_with_authority_rejects_wrong_key() {
+        let err = graft_with(&signing_key(1), signing_key(2)).await.unwrap_err();
+        assert!(err.to_string().contains("unauthorized"), "{err}");
+    }
+
+    #[test]
+    fn graft_challenge_depends_on_seq_and_head() {
+        let base = graft_challenge(&epoch(1, b"a", 0));
+        assert_eq!(base, graft_challenge(&epoch(1, b"a", 99)));
+        assert_ne!(base, graft_challenge(&epoch(2, b"a", 0)));
+        assert_ne!(base, graft_challenge(&epoch(1, b"b", 0)));
+    }
+
+    fn observed_event() -> HeadUpdatedObserved {
+        HeadUpdatedObserved {
+            seq: 4,
+            writer: [0xaa; 20],
+            cid: b"ipfs://four".to_vec(),
+            cid_hash: [0xbb; 32],
+            block_number: 321,
+            tx_hash: [0xcc; 32],
+            log_index: 2,
+            raw: None,
+            source_label: None,
+        }
+    }
+
+    #[test]
+    fn epoch_from_observed_maps_fields() {
+        assert_eq!(Epoch::from(&observed_event()), epoch(4, b"ipfs://four", 321));
+    }
+
+    #[test]
+    fn epoch_from_finalized_maps_fields() {
+        let finalized = FinalizedEvent {
+            seq: 4,
+            cid: b"ipfs://four".to_vec(),
+            cid_str: hex::encode(b"ipfs://four"),
+            cid_hash_hex: hex::encode([0xbb; 32]),
+            block_number: 321,
+            tx_hash_hex: hex::encode([0xcc; 32]),
+            log_index: 2,
+            writer: hex::encode([0xaa; 20]),
+            source_label: None,
+        };
+        assert_eq!(Epoch::from(&finalized), epoch(4, b"ipfs://four", 321));
+    }
+
+    #[test]
+    fn epoch_serde_roundtrip() {
+        let e = epoch(5, b"head5", 200);
+        let json = serde_json::to_value(&e).unwrap();
+        assert_eq!(json["head"], hex::encode(b"head5"));
+        assert_eq!(json["adopted_block"], 200);
+        let back: Epoch = serde_json::from_value(json).unwrap();
+        assert_eq!(back, e);
+    }
 }
@@ -0,0 +1,247 @@
+//! JSON-RPC transport shared by the indexer and finalizer.
+//!
+//! [RpcClient] is the single seam both use for HTTP JSON-RPC: [HttpRpcClient] talks to a node,
+//! and [MockRpcClient] (`test-util` feature) answers from scripted responses so pipelines can be
+//! tested without one.
+
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+#[cfg(any(test, feature = "test-util"))]
+use std::collections::{HashMap, VecDeque};
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::{Arc, Mutex};
+
+/// Failure of one JSON-RPC call.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RpcError {
+    /// Transport failure; the underlying error is stringified so the enum stays `Clone`.
+    #[error("HTTP request failed: {0}")]
+    Transport(String),
+    /// JSON-RPC error object (`code` per the JSON-RPC spec, e.g. -32601 method not found).
+    #[error("RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    /// The response was not a JSON-RPC response.
+    #[error("decode error: {0}")]
+    Decode(String),
+    /// No response within the client's request timeout.
+    #[error("request timed out: {0}")]
+    Timeout(String),
+}
+
+impl RpcError {
+    /// Build an [RpcError::Rpc] from a JSON-RPC `error` object. Missing fields fall back to
+    /// code 0 and the raw JSON as the message.
+    pub fn from_error_object(err: &Value) -> Self {
+        RpcError::Rpc {
+            code: err.get("code").and_then(|c| c.as_i64()).unwrap_or(0),
+            message: err
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| err.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RpcError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return RpcError::Timeout(e.to_string());
+        }
+        RpcError::Transport(e.to_string())
+    }
+}
+
+/// Extract `result` from a JSON-RPC response, mapping an `error` object to [RpcError::Rpc].
+pub(crate) fn rpc_result(json: Value) -> Result<Value, RpcError> {
+    if let Some(err) = json.get("error") {
+        return Err(RpcError::from_error_object(err));
+    }
+    json.get("result")
+        .cloned()
+        .ok_or_else(|| RpcError::Decode("Missing result".into()))
+}
+
+/// JSON-RPC transport: one request in, its `result` (or error) out.
+pub trait RpcClient: Send + Sync {
+    fn call<'a>(&'a self, method: &'a str, params: Value) -> BoxFuture<'a, Result<Value, RpcError>>;
+}
+
+/// [RpcClient] over HTTP. Each request carries a fresh, increasing `id`, so requests from one
+/// client never collide (proxies and multiplexed connections correlate responses by it).
+#[derive(Debug)]
+pub struct HttpRpcClient {
+    http: reqwest::Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+impl HttpRpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::with_client(
+            reqwest::Client::builder()
+                .no_proxy()
+                .build()
+                .expect("reqwest client"),
+            url,
+        )
+    }
+
+    /// Like [Self::new], but fail any request not answered within `timeout` with
+    /// [RpcError::Timeout] (`Duration::ZERO` means no timeout).
+    pub fn with_timeout(url: impl Into<String>, timeout: Duration) -> Self {
+        let mut builder = reqwest::Client::builder().no_proxy();
+        if !timeout.is_zero() {
+            builder = builder.timeout(timeout);
+        }
+        Self::with_client(builder.build().expect("reqwest client"), url)
+    }
+
+    /// Reuse an existing `reqwest::Client` (connection pool, timeouts, proxy settings).
+    pub fn with_client(http: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            http,
+            url: url.into(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl RpcClient for HttpRpcClient {
+    fn call<'a>(&'a self, method: &'a str, params: Value) -> BoxFuture<'a, Result<Value, RpcError>> {
+        Box::pin(async move {
+            let body = json!({
+                "jsonrpc": "2.0",
+                "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+                "method": method,
+                "params": params
+            });
+            let resp = self.http.post(&self.url).json(&body).send().await?;
+            let json: Value = resp.json().await?;
+            rpc_result(json)
+        })
+    }
+}
+
+/// In-memory [RpcClient] for tests (`test-util` feature). Each method answers from its own queue
+/// of scripted responses (the last one repeats once the rest are used up); unscripted methods get
+/// a -32601 error. Every request is recorded, with a sequential `id`, for assertions. Clones share
+/// their script and log.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone, Default)]
+pub struct MockRpcClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+struct MockState {
+    responses: HashMap<String, VecDeque<Result<Value, Value>>>,
+    requests: Vec<Value>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl MockRpcClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a `result` for `method`.
+    pub fn push(&self, method: &str, result: Value) -> &Self {
+        self.script(method, Ok(result))
+    }
+
+    /// Queue a JSON-RPC `error` object for `method`.
+    pub fn push_error(&self, method: &str, error: Value) -> &Self {
+        self.script(method, Err(error))
+    }
+
+    fn script(&self, method: &str, response: Result<Value, Value>) -> &Self {
+        let mut state = self.state.lock().unwrap();
+        state.responses.entry(method.to_string()).or_default().push_back(response);
+        self
+    }
+
+    /// Every request received so far (`{"id", "method", "params"}`), in arrival order.
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Requests received so far for `method`.
+    pub fn requests_for(&self, method: &str) -> Vec<Value> {
+        self.requests().into_iter().filter(|r| r["method"] == method).collect()
+    }
+
+    /// Record `req` and pick its scripted response (`Err` holds a JSON-RPC error object).
+    pub(crate) fn respond(&self, req: &Value) -> Result<Value, Value> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(req.clone());
+        let queue = req["method"].as_str().and_then(|m| state.responses.get_mut(m));
+        match queue {
+            Some(q) if q.len() > 1 => q.pop_front().unwrap(),
+            Some(q) if !q.is_empty() => q[0].clone(),
+            _ => Err(json!({"code": -32601, "message": "method not found"})),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl RpcClient for MockRpcClient {
+    fn call<'a>(&'a self, method: &'a str, params: Value) -> BoxFuture<'a, Result<Value, RpcError>> {
+        let id = self.state.lock().unwrap().requests.len() + 1;
+        let req = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let res = self.respond(&req).map_err(|e| RpcError::from_error_object(&e));
+        Box::pin(async move { res })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{spawn_json_rpc, spawn_stalled_http};
+
+    #[tokio::test]
+    async fn http_client_sends_increasing_ids() {
+        let mock = MockRpcClient::new();
+        mock.push("eth_blockNumber", json!("0x1"));
+        let responder = mock.clone();
+        let client = HttpRpcClient::new(spawn_json_rpc(move |req| responder.respond(req)).await);
+        for _ in 0..3 {
+            assert_eq!(client.call("eth_blockNumber", json!([])).await.unwrap(), json!("0x1"));
+        }
+        let ids: Vec<_> = mock.requests().iter().map(|r| r["id"].as_u64().unwrap()).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn http_client_times_out_on_stalled_server() {
+        let client = HttpRpcClient::with_timeout(spawn_stalled_http().await, Duration::from_millis(200));
+        let res = tokio::time::timeout(Duration::from_secs(5), client.call("eth_call", json!([])))
+            .await
+            .expect("request hung past its timeout");
+        assert!(matches!(res, Err(RpcError::Timeout(_))), "{res:?}");
+    }
+
+    #[tokio::test]
+    async fn mock_client_scripts_results_and_errors() {
+        let mock = MockRpcClient::new();
+        mock.push_error("eth_call", json!({"code": -32000, "message": "execution reverted"}))
+            .push("eth_call", json!("0x"));
+        let err = mock.call("eth_call", json!([])).await.unwrap_err();
+        assert_eq!(err, RpcError::Rpc { code: -32000, message: "execution reverted".into() });
+        assert_eq!(mock.call("eth_call", json!([])).await.unwrap(), json!("0x"));
+        assert_eq!(mock.call("eth_call", json!([])).await.unwrap(), json!("0x"));
+        assert!(matches!(
+            mock.call("eth_getLogs", json!([])).await,
+            Err(RpcError::Rpc { code: -32601, .. })
+        ));
+        assert_eq!(mock.requests_for("eth_call").len(), 3);
+    }
+}
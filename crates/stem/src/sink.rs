@@ -0,0 +1,237 @@
+//! Sinks for finalized events.
+//!
+//! A [FinalizedSink] receives each event emitted by [crate::Finalizer::run_to_sink], in order.
+//! [StdoutJsonSink] and the durable [JsonlFileSink] ship here; message-bus sinks belong in the
+//! crates that own those clients.
+
+use crate::finalizer::FinalizedEvent;
+use futures_util::future::BoxFuture;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Error returned by a sink (boxed so sinks can surface their own client errors).
+pub type SinkError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Destination for finalized events.
+pub trait FinalizedSink: Send + Sync {
+    /// Publish one event. An error stops [crate::Finalizer::run_to_sink].
+    fn publish<'a>(&'a self, ev: &'a FinalizedEvent) -> BoxFuture<'a, Result<(), SinkError>>;
+}
+
+/// Prints one-line JSON per event to stdout (the `finalizer` example's output format).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutJsonSink;
+
+impl FinalizedSink for StdoutJsonSink {
+    fn publish<'a>(&'a self, ev: &'a FinalizedEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            println!("{}", serde_json::to_string(ev)?);
+            Ok(())
+        })
+    }
+}
+
+/// When [JsonlFileSink] fsyncs the active file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// After every event (an event is durable once [FinalizedSink::publish] returns).
+    PerWrite,
+    /// After every `n` events, and before rotating.
+    Batch(usize),
+}
+
+/// Default size at which [JsonlFileSink] rotates the active file.
+pub const DEFAULT_JSONL_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Default number of rotated files [JsonlFileSink] keeps.
+pub const DEFAULT_JSONL_KEEP_FILES: usize = 5;
+
+/// Appends one JSON line per event to `path`. When a write would take the file past
+/// `max_bytes`, it is renamed to `path.1` (older files shift to `path.2`, ...) and a new file is
+/// started; only the newest `keep_files` rotated files are kept.
+pub struct JsonlFileSink {
+    state: Arc<Mutex<JsonlState>>,
+}
+
+struct JsonlState {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    max_bytes: u64,
+    keep_files: usize,
+    fsync: FsyncPolicy,
+    unsynced: usize,
+}
+
+impl JsonlFileSink {
+    /// Open (or create) `path` for appending, with [DEFAULT_JSONL_MAX_BYTES],
+    /// [DEFAULT_JSONL_KEEP_FILES] and [FsyncPolicy::PerWrite].
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let file = open_append(&path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            state: Arc::new(Mutex::new(JsonlState {
+                path,
+                file,
+                len,
+                max_bytes: DEFAULT_JSONL_MAX_BYTES,
+                keep_files: DEFAULT_JSONL_KEEP_FILES,
+                fsync: FsyncPolicy::PerWrite,
+                unsynced: 0,
+            })),
+        })
+    }
+
+    /// Rotate once the active file would exceed `max_bytes`.
+    pub fn with_max_bytes(self, max_bytes: u64) -> Self {
+        self.state.lock().unwrap().max_bytes = max_bytes;
+        self
+    }
+
+    /// Keep the newest `keep_files` rotated files (0 deletes the active file on rotation).
+    pub fn with_keep_files(self, keep_files: usize) -> Self {
+        self.state.lock().unwrap().keep_files = keep_files;
+        self
+    }
+
+    pub fn with_fsync(self, fsync: FsyncPolicy) -> Self {
+        self.state.lock().unwrap().fsync = fsync;
+        self
+    }
+
+    /// Fsync any events written since the last sync (e.g. on shutdown with [FsyncPolicy::Batch]).
+    pub fn sync(&self) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.file.sync_data()?;
+        state.unsynced = 0;
+        Ok(())
+    }
+}
+
+impl FinalizedSink for JsonlFileSink {
+    fn publish<'a>(&'a self, ev: &'a FinalizedEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(ev)?;
+            line.push(b'\n');
+            let state = Arc::clone(&self.state);
+            tokio::task::spawn_blocking(move || state.lock().unwrap().append(&line)).await??;
+            Ok(())
+        })
+    }
+}
+
+impl JsonlState {
+    fn append(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.len > 0 && self.len + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.len += line.len() as u64;
+        self.unsynced += 1;
+        let due = match self.fsync {
+            FsyncPolicy::PerWrite => true,
+            FsyncPolicy::Batch(n) => self.unsynced >= n.max(1),
+        };
+        if due {
+            self.file.sync_data()?;
+            self.unsynced = 0;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        let _ = std::fs::remove_file(rotated_path(&self.path, self.keep_files));
+        for n in (1..self.keep_files).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                std::fs::rename(from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        if self.keep_files > 0 {
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        } else {
+            std::fs::remove_file(&self.path)?;
+        }
+        self.file = open_append(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// `path.n` (`n >= 1`), the n-th newest rotated file. `n == 0` is `path` itself.
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    if n == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn event(seq: u64) -> FinalizedEvent {
+        FinalizedEvent {
+            seq,
+            cid: format!("cid-{seq}").into_bytes(),
+            cid_str: hex::encode(format!("cid-{seq}")),
+            cid_hash_hex: hex::encode([0xcc; 32]),
+            block_number: seq,
+            tx_hash_hex: hex::encode([seq as u8; 32]),
+            log_index: 0,
+            writer: hex::encode([0u8; 20]),
+            source_label: None,
+        }
+    }
+
+    fn seqs(path: &Path) -> Vec<u64> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["seq"].as_u64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn jsonl_sink_rotates_by_size() {
+        let dir = scratch_dir("jsonl-rotate");
+        let path = dir.join("finalized.jsonl");
+        let line_len = serde_json::to_vec(&event(1)).unwrap().len() as u64 + 1;
+        let sink = JsonlFileSink::open(&path)
+            .unwrap()
+            .with_max_bytes(2 * line_len)
+            .with_keep_files(1)
+            .with_fsync(FsyncPolicy::Batch(2));
+        for seq in 1..=3 {
+            sink.publish(&event(seq)).await.unwrap();
+        }
+        sink.sync().unwrap();
+
+        assert_eq!(seqs(&rotated_path(&path, 1)), vec![1, 2]);
+        assert_eq!(seqs(&path), vec![3]);
+        let mut files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        files.sort();
+        assert_eq!(files, ["finalized.jsonl", "finalized.jsonl.1"]);
+
+        // A second rotation drops the oldest file (keep_files = 1).
+        for seq in 4..=5 {
+            sink.publish(&event(seq)).await.unwrap();
+        }
+        assert_eq!(seqs(&rotated_path(&path, 1)), vec![3, 4]);
+        assert_eq!(seqs(&path), vec![5]);
+        assert!(!rotated_path(&path, 2).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
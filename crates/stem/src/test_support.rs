@@ -0,0 +1,194 @@
+//! Test-only helpers shared by the unit tests in this crate.
+
+use crate::abi::HEAD_UPDATED_TOPIC0;
+use alloy::sol_types::SolType;
+use crate::rpc::MockRpcClient;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Loopback HTTP JSON-RPC stub: answers every request with `{"result": respond(request)}`.
+pub(crate) async fn spawn_http_rpc<F>(respond: F) -> String
+where
+    F: Fn(&Value) -> Value + Send + Sync + 'static,
+{
+    spawn_json_rpc(move |req| Ok(respond(req))).await
+}
+
+/// Like [spawn_http_rpc], but `Err(error)` answers with a JSON-RPC `{"error": error}` object.
+pub(crate) async fn spawn_json_rpc<F>(respond: F) -> String
+where
+    F: Fn(&Value) -> Result<Value, Value> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let respond = Arc::new(respond);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve_json_rpc_request(stream, Arc::clone(&respond)));
+        }
+    });
+    url
+}
+
+/// CA that signed the `127.0.0.1` certificate served by [tls_acceptor] (PEM).
+pub(crate) const TEST_CA_PEM: &[u8] = include_bytes!("../tests/fixtures/tls/ca.pem");
+
+/// TLS acceptor presenting a `127.0.0.1`/`localhost` certificate signed by [TEST_CA_PEM].
+pub(crate) fn tls_acceptor() -> tokio_native_tls::TlsAcceptor {
+    let identity = native_tls::Identity::from_pkcs8(
+        include_bytes!("../tests/fixtures/tls/server.pem"),
+        include_bytes!("../tests/fixtures/tls/server.key"),
+    )
+    .unwrap();
+    native_tls::TlsAcceptor::new(identity).unwrap().into()
+}
+
+/// Like [spawn_json_rpc], but over TLS (see [tls_acceptor]); returns the `https://` URL.
+/// Connections whose TLS handshake fails are dropped.
+pub(crate) async fn spawn_tls_json_rpc<F>(respond: F) -> String
+where
+    F: Fn(&Value) -> Result<Value, Value> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("https://{}", listener.local_addr().unwrap());
+    let respond = Arc::new(respond);
+    let acceptor = Arc::new(tls_acceptor());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (respond, acceptor) = (Arc::clone(&respond), Arc::clone(&acceptor));
+            tokio::spawn(async move {
+                if let Ok(stream) = acceptor.accept(stream).await {
+                    serve_json_rpc_request(stream, respond).await;
+                }
+            });
+        }
+    });
+    url
+}
+
+/// Read one HTTP JSON-RPC request from `stream` and answer it per `respond`.
+async fn serve_json_rpc_request<S, F>(mut stream: S, respond: Arc<F>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: Fn(&Value) -> Result<Value, Value>,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let (header_end, content_len) = loop {
+        let n = stream.read(&mut chunk).await.unwrap_or(0);
+        if n == 0 {
+            return;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&buf[..pos]).to_ascii_lowercase();
+            let len = headers
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            break (pos + 4, len);
+        }
+    };
+    while buf.len() < header_end + content_len {
+        let n = stream.read(&mut chunk).await.unwrap_or(0);
+        if n == 0 {
+            return;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let req: Value = serde_json::from_slice(&buf[header_end..header_end + content_len])
+        .unwrap_or(Value::Null);
+    let body = match respond(&req) {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": req["id"], "result": result}),
+        Err(error) => json!({"jsonrpc": "2.0", "id": req["id"], "error": error}),
+    }
+    .to_string();
+    let resp = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(resp.as_bytes()).await;
+}
+
+/// Loopback server that accepts connections but never answers, for timeout tests.
+pub(crate) async fn spawn_stalled_http() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+    url
+}
+
+/// [MockRpcClient] served over loopback HTTP, for code paths that take a URL.
+#[derive(Clone, Default)]
+pub(crate) struct MockRpc(MockRpcClient);
+
+impl std::ops::Deref for MockRpc {
+    type Target = MockRpcClient;
+
+    fn deref(&self) -> &MockRpcClient {
+        &self.0
+    }
+}
+
+impl MockRpc {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start serving; returns the `http://` URL.
+    pub(crate) async fn serve(&self) -> String {
+        let client = self.0.clone();
+        spawn_json_rpc(move |req| client.respond(req)).await
+    }
+}
+
+/// JSON-RPC log object for a `HeadUpdated(seq, writer, cid, cid_hash)` event, as returned by
+/// `eth_getLogs` / `eth_subscribe("logs")`. Only `seq`, block and log index vary.
+pub(crate) fn head_updated_log(seq: u64, block_number: u64, log_index: u64) -> Value {
+    let mut topic0 = [0u8; 32];
+    topic0[..4].copy_from_slice(&HEAD_UPDATED_TOPIC0);
+    let mut seq_topic = [0u8; 32];
+    seq_topic[24..].copy_from_slice(&seq.to_be_bytes());
+    let cid = format!("cid-{seq}").into_bytes();
+    let data = alloy::sol_types::sol_data::Bytes::abi_encode(&alloy::primitives::Bytes::from(cid));
+    json!({
+        "address": format!("0x{}", hex::encode([0x11u8; 20])),
+        "topics": [
+            format!("0x{}", hex::encode(topic0)),
+            format!("0x{}", hex::encode(seq_topic)),
+            format!("0x{}", hex::encode([0u8; 32])),
+            format!("0x{}", hex::encode([0xccu8; 32])),
+        ],
+        "data": format!("0x{}", hex::encode(data)),
+        "blockNumber": format!("0x{:x}", block_number),
+        "logIndex": format!("0x{:x}", log_index),
+        "transactionHash": format!("0x{}", hex::encode([seq as u8; 32])),
+    })
+}
+
+/// Hex-encoded `head()` return data `(uint64 seq, bytes cid)`, as an `eth_call` result.
+pub(crate) fn head_return_hex(seq: u64, cid: &[u8]) -> String {
+    type HeadReturn = (alloy::sol_types::sol_data::Uint<64>, alloy::sol_types::sol_data::Bytes);
+    format!(
+        "0x{}",
+        hex::encode(HeadReturn::abi_encode(&(seq, alloy::primitives::Bytes::from(cid.to_vec()))))
+    )
+}
+
+/// Fresh, empty directory under the system temp dir, unique per `name` and test process.
+pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("stem-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -338,6 +338,115 @@ pub async fn send_raw_transaction(http_url: &str, to: &str, calldata: &[u8]) ->
     Ok(())
 }
 
+/// EIP-1559 fees for test transactions: 2 gwei tip, 50 gwei cap (well above Anvil's base fee).
+const EIP1559_MAX_PRIORITY_FEE_PER_GAS: u64 = 2_000_000_000;
+const EIP1559_MAX_FEE_PER_GAS: u64 = 50_000_000_000;
+
+/// Build a signed EIP-1559 (type-2) transaction with an empty access list, signed with the Anvil
+/// default key: `0x02 || rlp([chain_id, nonce, max_priority_fee, max_fee, gas_limit, to, value,
+/// data, access_list, y_parity, r, s])`, signature over `keccak256(0x02 || rlp(unsigned fields))`.
+#[allow(clippy::too_many_arguments)]
+pub fn build_signed_1559_tx(
+    chain_id: u64,
+    nonce: u64,
+    max_priority_fee_per_gas: u64,
+    max_fee_per_gas: u64,
+    gas_limit: u64,
+    to: &[u8; 20],
+    value: u64,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    use k256::ecdsa::SigningKey;
+    use rlp::RlpStream;
+    use sha3::{Digest, Keccak256};
+
+    let append_fields = |s: &mut RlpStream| {
+        s.append(&chain_id);
+        s.append(&nonce);
+        s.append(&max_priority_fee_per_gas);
+        s.append(&max_fee_per_gas);
+        s.append(&gas_limit);
+        let to_slice: &[u8] = to;
+        s.append(&to_slice);
+        s.append(&value);
+        s.append(&data);
+        s.begin_list(0); // access list
+    };
+
+    let mut unsigned = RlpStream::new();
+    unsigned.begin_list(9);
+    append_fields(&mut unsigned);
+    let mut preimage = vec![0x02u8];
+    preimage.extend_from_slice(&unsigned.out());
+
+    let signing_key = SigningKey::from_bytes((&ANVIL_DEFAULT_PRIVATE_KEY).into())
+        .map_err(|e| anyhow::anyhow!("invalid signing key: {}", e))?;
+    let (signature, recovery_id) = signing_key
+        .sign_digest_recoverable(Keccak256::new_with_prefix(&preimage))
+        .map_err(|e| anyhow::anyhow!("sign failed: {}", e))?;
+    let sig_bytes = signature.to_bytes();
+    let r: [u8; 32] = sig_bytes[0..32].try_into().unwrap();
+    let s: [u8; 32] = sig_bytes[32..64].try_into().unwrap();
+
+    let mut signed = RlpStream::new();
+    signed.begin_list(12);
+    append_fields(&mut signed);
+    signed.append(&u64::from(recovery_id.is_y_odd()));
+    signed.append(&trim_leading_zeros(&r));
+    signed.append(&trim_leading_zeros(&s));
+    let mut raw_tx = vec![0x02u8];
+    raw_tx.extend_from_slice(&signed.out());
+    Ok(raw_tx)
+}
+
+/// Send a raw EIP-1559 (type-2) transaction via eth_sendRawTransaction, signed in-process with the
+/// Anvil default key. For nodes whose mempool only accepts typed transactions.
+pub async fn send_raw_transaction_1559(http_url: &str, to: &str, calldata: &[u8]) -> Result<()> {
+    let to = to.strip_prefix("0x").unwrap_or(to);
+    let to_bytes = hex::decode(to).context("decode to address")?;
+    let to_arr: [u8; 20] = to_bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("to address must be 20 bytes"))?;
+
+    let nonce = eth_get_transaction_count(http_url, ANVIL_DEFAULT_FROM).await?;
+    let raw_tx = build_signed_1559_tx(
+        ANVIL_CHAIN_ID,
+        nonce,
+        EIP1559_MAX_PRIORITY_FEE_PER_GAS,
+        EIP1559_MAX_FEE_PER_GAS,
+        0x30d40,
+        &to_arr,
+        0,
+        calldata,
+    )?;
+
+    let client = http_client();
+    let params = json!([format!("0x{}", hex::encode(&raw_tx))]);
+    let tx_hash_value = http_json_rpc(&client, http_url, "eth_sendRawTransaction", params, 22).await?;
+    let _tx_hash = tx_hash_value.as_str().ok_or_else(|| anyhow::anyhow!("tx hash not string"))?;
+    Ok(())
+}
+
+/// Transaction envelope used by [set_head_bytes].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxType {
+    /// EIP-155 legacy transaction ([send_raw_transaction]).
+    Legacy,
+    /// EIP-1559 type-2 transaction ([send_raw_transaction_1559]).
+    Eip1559,
+}
+
+impl TxType {
+    /// `STEM_TEST_TX_TYPE=1559` (or `eip1559`) selects type-2 transactions; anything else is legacy.
+    pub fn from_env() -> Self {
+        match std::env::var("STEM_TEST_TX_TYPE").as_deref() {
+            Ok("1559") | Ok("eip1559") => TxType::Eip1559,
+            _ => TxType::Legacy,
+        }
+    }
+}
+
 /// Send a transaction using eth_sendTransaction (kept for reference; set_head_bytes uses send_raw_transaction). Anvil signs for its default unlocked account (ANVIL_DEFAULT_FROM).
 /// Calldata is built in Rust so encoding is exact; no cast subprocess involved.
 pub async fn send_transaction(http_url: &str, to: &str, calldata: &[u8]) -> Result<()> {
@@ -371,7 +480,8 @@ pub async fn send_transaction(http_url: &str, to: &str, calldata: &[u8]) -> Resu
 }
 
 /// Call setHead with raw CID bytes. Builds calldata in Rust (see build_set_head_bytes_calldata) and sends via eth_sendRawTransaction
-/// with in-process EIP-155 signing so encoding is exact. Anvil auto-mines the tx immediately.
+/// with in-process signing so encoding is exact. Anvil auto-mines the tx immediately.
+/// Transaction type follows [TxType::from_env] (legacy unless `STEM_TEST_TX_TYPE=1559`).
 pub async fn set_head_bytes(
     _repo_root: &std::path::Path,
     rpc_url: &str,
@@ -380,13 +490,19 @@ pub async fn set_head_bytes(
     cid_bytes: &[u8],
     _cid_kind: Option<u8>,
 ) -> Result<()> {
-    let calldata = if signature == "setHead(bytes)" {
-        build_set_head_bytes_calldata(cid_bytes)
-    } else {
+    if signature != "setHead(bytes)" {
         anyhow::bail!("set_head_bytes only supports setHead(bytes) for now");
-    };
-    send_raw_transaction(rpc_url, contract, &calldata).await?;
-    Ok(())
+    }
+    set_head_bytes_with(rpc_url, contract, cid_bytes, TxType::from_env()).await
+}
+
+/// setHead(bytes) with an explicit transaction type.
+pub async fn set_head_bytes_with(rpc_url: &str, contract: &str, cid_bytes: &[u8], tx_type: TxType) -> Result<()> {
+    let calldata = build_set_head_bytes_calldata(cid_bytes);
+    match tx_type {
+        TxType::Legacy => send_raw_transaction(rpc_url, contract, &calldata).await,
+        TxType::Eip1559 => send_raw_transaction_1559(rpc_url, contract, &calldata).await,
+    }
 }
 
 /// Same as set_head but takes hex string without 0x (avoids cast mis-parsing).
@@ -58,14 +58,13 @@ async fn test_finalizer_confirmation_depth_gates_and_adopts() {
 
     let current_block = eth_block_number(&rpc_url).await.expect("eth_block_number");
     let ws_url = rpc_url.replace("http://", "ws://").replace("https://", "wss://");
-    let config = IndexerConfig {
-        ws_url: ws_url.clone(),
-        http_url: rpc_url.clone(),
-        contract_address,
-        start_block: current_block,
-        getlogs_max_range: 1000,
-        reconnection: Default::default(),
-    };
+    let config = IndexerConfig::builder()
+        .ws_url(&ws_url)
+        .http_url(&rpc_url)
+        .contract_address(contract_address)
+        .start_block(current_block)
+        .build()
+        .expect("indexer config");
     let indexer = Arc::new(StemIndexer::new(config));
     let mut recv = indexer.subscribe();
     let indexer_clone = Arc::clone(&indexer);
@@ -115,7 +114,7 @@ async fn test_finalizer_confirmation_depth_gates_and_adopts() {
         .contract_address(contract_address)
         .build()
         .expect("finalizer build");
-    finalizer.feed(ev);
+    finalizer.feed(ev).expect("feed");
 
 
     let tip = finalizer.current_tip().await.expect("current_tip");
@@ -33,14 +33,12 @@ async fn test_indexer_against_anvil() {
     set_head(repo_root, &rpc_url, &contract_addr, "setHead(bytes)", "0x626c6f622f2f7468697264", None).expect("setHead 3");
 
     let ws_url = rpc_url.replace("http://", "ws://").replace("https://", "wss://");
-    let config = IndexerConfig {
-        ws_url: ws_url.clone(),
-        http_url: rpc_url.clone(),
-        contract_address,
-        start_block: 0,
-        getlogs_max_range: 1000,
-        reconnection: Default::default(),
-    };
+    let config = IndexerConfig::builder()
+        .ws_url(&ws_url)
+        .http_url(&rpc_url)
+        .contract_address(contract_address)
+        .build()
+        .expect("indexer config");
     let indexer = Arc::new(StemIndexer::new(config));
     let mut recv = indexer.subscribe();
     let indexer_clone = Arc::clone(&indexer);
@@ -76,3 +74,113 @@ async fn test_indexer_against_anvil() {
         );
     }
 }
+
+/// HTTP poll mode: no `eth_subscribe`, events (backfilled and live) still arrive in order.
+#[tokio::test]
+async fn test_indexer_http_poll_against_anvil() {
+    if !common::foundry_available() {
+        eprintln!("skipping test_indexer_http_poll_against_anvil: anvil/forge/cast not in PATH");
+        return;
+    }
+    let repo_root = Path::new(env!("CARGO_MANIFEST_DIR")).ancestors().nth(2).unwrap();
+    let (mut anvil_process, rpc_url) = spawn_anvil().await.expect("spawn anvil");
+    let contract_addr = deploy_stem(repo_root, &rpc_url).expect("deploy Stem");
+    let addr_bytes = hex::decode(contract_addr.strip_prefix("0x").unwrap_or(&contract_addr)).expect("hex");
+    let mut contract_address = [0u8; 20];
+    contract_address.copy_from_slice(&addr_bytes);
+
+    set_head(repo_root, &rpc_url, &contract_addr, "setHead(bytes)", "0x697066732f2f6669727374", None).expect("setHead 1");
+
+    let config = IndexerConfig::builder()
+        .http_url(&rpc_url)
+        .contract_address(contract_address)
+        .http_poll(Duration::from_millis(200))
+        .build()
+        .expect("indexer config");
+    let indexer = Arc::new(StemIndexer::new(config));
+    let mut recv = indexer.subscribe();
+    let indexer_clone = Arc::clone(&indexer);
+    let task = tokio::spawn(async move {
+        let _ = indexer_clone.run().await;
+    });
+
+    let first = timeout(Duration::from_secs(10), recv.recv()).await.expect("backfilled event").expect("recv");
+    set_head(repo_root, &rpc_url, &contract_addr, "setHead(bytes)", "0x69706c642f2f7365636f6e64", None).expect("setHead 2");
+    let second = timeout(Duration::from_secs(10), recv.recv()).await.expect("polled event").expect("recv");
+
+    task.abort();
+    let _ = anvil_process.kill();
+
+    assert_eq!((first.seq, second.seq), (1, 2));
+    assert!((first.block_number, first.log_index) < (second.block_number, second.log_index));
+}
+
+/// EIP-1559 (type-2) setHead is accepted and mined by Anvil.
+#[tokio::test]
+async fn test_eip1559_set_head_accepted_by_anvil() {
+    if !common::foundry_available() {
+        eprintln!("skipping test_eip1559_set_head_accepted_by_anvil: anvil/forge/cast not in PATH");
+        return;
+    }
+    let repo_root = Path::new(env!("CARGO_MANIFEST_DIR")).ancestors().nth(2).unwrap();
+    let (mut anvil_process, rpc_url) = spawn_anvil().await.expect("spawn anvil");
+    let contract_addr = deploy_stem(repo_root, &rpc_url).expect("deploy Stem");
+    let addr_bytes = hex::decode(contract_addr.strip_prefix("0x").unwrap_or(&contract_addr)).expect("hex");
+    let mut contract_address = [0u8; 20];
+    contract_address.copy_from_slice(&addr_bytes);
+
+    let before = common::stem_head_http(&rpc_url, &contract_address).await.expect("head before");
+    common::set_head_bytes_with(&rpc_url, &contract_addr, b"ipfs://eip1559", common::TxType::Eip1559)
+        .await
+        .expect("type-2 setHead");
+    let after = common::stem_head_http(&rpc_url, &contract_address).await.expect("head after");
+    let _ = anvil_process.kill();
+
+    assert_eq!(after.seq, before.seq + 1);
+    assert_eq!(after.cid, b"ipfs://eip1559".to_vec());
+}
+
+/// Offline check of the type-2 envelope: 12-field RLP list behind 0x02 whose signature recovers
+/// to the Anvil default account.
+#[test]
+fn test_eip1559_envelope_recovers_sender() {
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    use sha3::{Digest, Keccak256};
+
+    let to = [0x22u8; 20];
+    let raw = common::build_signed_1559_tx(31337, 7, 2, 50, 100_000, &to, 0, b"\x01\x02").unwrap();
+    assert_eq!(raw[0], 0x02);
+    let list = rlp::Rlp::new(&raw[1..]);
+    assert_eq!(list.item_count().unwrap(), 12);
+    assert_eq!(list.val_at::<u64>(0).unwrap(), 31337);
+    assert_eq!(list.val_at::<u64>(1).unwrap(), 7);
+    assert_eq!(list.at(5).unwrap().data().unwrap(), &to);
+    assert_eq!(list.at(8).unwrap().item_count().unwrap(), 0, "empty access list");
+
+    // Re-encode the 9 unsigned fields to get the signing preimage.
+    let mut unsigned = rlp::RlpStream::new_list(9);
+    for i in 0..9 {
+        unsigned.append_raw(list.at(i).unwrap().as_raw(), 1);
+    }
+    let mut preimage = vec![0x02u8];
+    preimage.extend_from_slice(&unsigned.out());
+
+    let y_parity: u8 = list.val_at(9).unwrap();
+    let mut sig = [0u8; 64];
+    let r = list.at(10).unwrap().data().unwrap();
+    let s = list.at(11).unwrap().data().unwrap();
+    sig[32 - r.len()..32].copy_from_slice(r);
+    sig[64 - s.len()..].copy_from_slice(s);
+    let key = VerifyingKey::recover_from_digest(
+        Keccak256::new_with_prefix(&preimage),
+        &Signature::from_slice(&sig).unwrap(),
+        RecoveryId::from_byte(y_parity).unwrap(),
+    )
+    .unwrap();
+    let pubkey = key.to_encoded_point(false);
+    let address = &Keccak256::digest(&pubkey.as_bytes()[1..])[12..];
+    assert_eq!(
+        format!("0x{}", hex::encode(address)),
+        "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_lowercase()
+    );
+}
@@ -1,16 +1,19 @@
 //! Integration test: Anvil → indexer → Epoch → Membrane server → client → graft → Session → pollStatus.
 //! All local: membrane server and client are in-process (capnp-rpc local dispatch).
+#![cfg(feature = "membrane")]
 
 mod common;
 
-use capnp_rpc::new_client;
+use capnp_rpc::{new_client, rpc_twoparty_capnp, twoparty, RpcSystem};
 use common::{deploy_stem, set_head, spawn_anvil};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 use stem::stem_capnp;
-use stem::{membrane_client, Epoch, IndexerConfig, StemIndexer};
+use stem::{connect_membrane, membrane_client, serve_membrane, Epoch, IndexerConfig, StemIndexer};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::watch;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 use tokio::time::timeout;
 use tracing_subscriber::EnvFilter;
 
@@ -29,14 +32,6 @@ impl stem_capnp::signer::Server for StubSigner {
     }
 }
 
-fn observed_to_epoch(ev: &stem::HeadUpdatedObserved) -> Epoch {
-    Epoch {
-        seq: ev.seq,
-        head: ev.cid.clone(),
-        adopted_block: ev.block_number,
-    }
-}
-
 #[tokio::test]
 async fn test_membrane_graft_poll_status_against_anvil() {
     if !common::foundry_available() {
@@ -58,14 +53,12 @@ async fn test_membrane_graft_poll_status_against_anvil() {
     set_head(repo_root, &rpc_url, &contract_addr, "setHead(bytes)", "0x697066732f2f6669727374", None).expect("setHead 1");
 
     let ws_url = rpc_url.replace("http://", "ws://").replace("https://", "wss://");
-    let config = IndexerConfig {
-        ws_url: ws_url.clone(),
-        http_url: rpc_url.clone(),
-        contract_address,
-        start_block: 0,
-        getlogs_max_range: 1000,
-        reconnection: Default::default(),
-    };
+    let config = IndexerConfig::builder()
+        .ws_url(&ws_url)
+        .http_url(&rpc_url)
+        .contract_address(contract_address)
+        .build()
+        .expect("indexer config");
     let indexer = Arc::new(StemIndexer::new(config));
     let mut recv = indexer.subscribe();
     let indexer_clone = Arc::clone(&indexer);
@@ -89,7 +82,7 @@ async fn test_membrane_graft_poll_status_against_anvil() {
     indexer_task.abort();
     let _ = anvil_process.kill();
 
-    let epoch1 = observed_to_epoch(&first_ev);
+    let epoch1 = Epoch::from(&first_ev);
     let epoch2 = Epoch {
         seq: first_ev.seq + 1,
         head: b"next_head".to_vec(),
@@ -178,3 +171,103 @@ async fn test_membrane_stale_epoch_then_recovery_no_chain() {
     };
     assert_eq!(status3, stem_capnp::Status::Ok, "re-graft session should be ok");
 }
+
+/// No-chain test over a real socket: serve_membrane on loopback TCP, graft, poll, then stale after advance.
+#[tokio::test]
+async fn test_membrane_over_tcp_graft_poll_status() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let (tx, rx) = watch::channel(Epoch {
+                seq: 1,
+                head: b"head1".to_vec(),
+                adopted_block: 100,
+            });
+            let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+            let addr = listener.local_addr().unwrap();
+            tokio::task::spawn_local(serve_membrane(rx, listener));
+
+            let stream = TcpStream::connect(addr).await.expect("connect");
+            stream.set_nodelay(true).unwrap();
+            let (reader, writer) = stream.into_split();
+            let network = twoparty::VatNetwork::new(
+                reader.compat(),
+                writer.compat_write(),
+                rpc_twoparty_capnp::Side::Client,
+                Default::default(),
+            );
+            let mut rpc_system = RpcSystem::new(Box::new(network), None);
+            let membrane: stem_capnp::membrane::Client<capnp::any_pointer::Owned> =
+                rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+            tokio::task::spawn_local(rpc_system);
+
+            let mut graft_req = membrane.graft_request();
+            graft_req.get().set_signer(new_client(StubSigner));
+            let graft_rpc_response = timeout(Duration::from_secs(5), graft_req.send().promise)
+                .await
+                .expect("graft timeout")
+                .expect("graft RPC");
+            let session = graft_rpc_response.get().expect("graft results").get_session().expect("session");
+            assert_eq!(session.get_issued_epoch().expect("issued_epoch").get_seq(), 1);
+
+            let poller = session.get_status_poller().expect("status_poller");
+            let r = poller.poll_status_request().send().promise.await.expect("poll_status RPC");
+            assert_eq!(r.get().expect("poll_status results").get_status().expect("status"), stem_capnp::Status::Ok);
+
+            tx.send(Epoch {
+                seq: 2,
+                head: b"head2".to_vec(),
+                adopted_block: 101,
+            })
+            .unwrap();
+            match poller.poll_status_request().send().promise.await {
+                Ok(_) => panic!("poll_status should fail with RPC error after epoch advance"),
+                Err(e) => assert!(e.to_string().contains("staleEpoch"), "error should mention staleEpoch, got: {e}"),
+            }
+        })
+        .await;
+}
+
+/// connect_membrane over an in-memory loopback pair: the caller drives both RPC systems.
+#[tokio::test]
+async fn test_connect_membrane_over_loopback_pair() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let (_tx, rx) = watch::channel(Epoch {
+                seq: 7,
+                head: b"head7".to_vec(),
+                adopted_block: 70,
+            });
+            let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+
+            let (reader, writer) = tokio::io::split(server_end);
+            let server_network = twoparty::VatNetwork::new(
+                reader.compat(),
+                writer.compat_write(),
+                rpc_twoparty_capnp::Side::Server,
+                Default::default(),
+            );
+            let server = RpcSystem::new(Box::new(server_network), Some(membrane_client(rx).client));
+            tokio::task::spawn_local(server);
+
+            let (reader, writer) = tokio::io::split(client_end);
+            let (rpc_system, membrane) = connect_membrane(twoparty::VatNetwork::new(
+                reader.compat(),
+                writer.compat_write(),
+                rpc_twoparty_capnp::Side::Client,
+                Default::default(),
+            ));
+            tokio::task::spawn_local(rpc_system);
+
+            let mut graft_req = membrane.graft_request();
+            graft_req.get().set_signer(new_client(StubSigner));
+            let response = timeout(Duration::from_secs(5), graft_req.send().promise)
+                .await
+                .expect("graft timeout")
+                .expect("graft RPC");
+            let session = response.get().expect("graft results").get_session().expect("session");
+            assert_eq!(session.get_issued_epoch().expect("issued_epoch").get_seq(), 7);
+        })
+        .await;
+}
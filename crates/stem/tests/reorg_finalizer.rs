@@ -51,14 +51,13 @@ async fn test_reorg_indexer_false_positive_finalizer_filters() {
 
     let current_block = eth_block_number(&rpc_url).await.expect("eth_block_number");
     let ws_url = rpc_url.replace("http://", "ws://").replace("https://", "wss://");
-    let config = IndexerConfig {
-        ws_url: ws_url.clone(),
-        http_url: rpc_url.clone(),
-        contract_address,
-        start_block: current_block,
-        getlogs_max_range: 1000,
-        reconnection: Default::default(),
-    };
+    let config = IndexerConfig::builder()
+        .ws_url(&ws_url)
+        .http_url(&rpc_url)
+        .contract_address(contract_address)
+        .start_block(current_block)
+        .build()
+        .expect("indexer config");
     let indexer = Arc::new(StemIndexer::new(config));
     let mut recv = indexer.subscribe();
     let indexer_clone = Arc::clone(&indexer);
@@ -108,7 +107,7 @@ async fn test_reorg_indexer_false_positive_finalizer_filters() {
         ),
     };
 
-    finalizer.feed(ev);
+    finalizer.feed(ev).expect("feed");
 
     let reverted = evm_revert(&rpc_url, &snap).await.expect("evm_revert");
     assert!(reverted, "evm_revert should return true");